@@ -58,6 +58,17 @@ class AppPreferences(
 
     val remoteUrl = stringPreference("remoteUrl", "")
 
+    /**
+     * Stash the uncommitted changes around the pull done before a change,
+     * instead of committing them.
+     */
+    val stashOnSync = booleanPreference("stashOnSync", false)
+
+    /**
+     * Extra extensions, with the format of the `[extensions]` table of `.gitnote.toml`.
+     */
+    val extensionsOverride = stringPreference("extensionsOverride", "")
+
     val credType = enumPreference("credType", CredType.None)
 
     val gitAuthorName = stringPreference("gitAuthorName", "")
@@ -125,6 +136,8 @@ class AppPreferences(
                 userPassPassword.update(cred.password)
             }
 
+            is Cred.SshKeyRef -> throw IllegalArgumentException("a key store reference can't be saved")
+
             null -> credType.update(CredType.None)
         }
     }
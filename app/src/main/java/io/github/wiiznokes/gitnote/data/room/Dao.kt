@@ -11,9 +11,8 @@ import androidx.room.Transaction
 import androidx.room.Upsert
 import androidx.sqlite.db.SimpleSQLiteQuery
 import androidx.sqlite.db.SupportSQLiteQuery
-import io.github.wiiznokes.gitnote.data.platform.NodeFs
 import io.github.wiiznokes.gitnote.manager.Progress
-import io.github.wiiznokes.gitnote.manager.isExtensionSupportedLib
+import io.github.wiiznokes.gitnote.manager.ScanBatch
 import io.github.wiiznokes.gitnote.ui.model.GridNote
 import io.github.wiiznokes.gitnote.ui.model.SortOrder
 import io.github.wiiznokes.gitnote.ui.screen.app.DrawerFolderModel
@@ -25,102 +24,34 @@ import java.nio.ByteOrder
 
 private const val TAG = "Dao"
 
-private const val LIMIT_FILE_SIZE_DB = 2 * 1024 * 1024
-
 @Dao
 interface RepoDatabaseDao {
 
     @Transaction
     suspend fun clearAndInit(
-        rootPath: String,
-        timestamps: HashMap<String, Long>,
+        batches: Flow<ScanBatch>,
         progressCb: ((Progress) -> Unit)? = null
     ) {
         Log.d(TAG, "clearAndInit")
         clearDatabase()
 
-        val rootFs = NodeFs.Folder.fromPath(rootPath)
-        val rootFolder = NoteFolder.new(
-            relativePath = "",
-        )
-
-        val notes = mutableListOf<Note>()
-        val folders = mutableListOf<NoteFolder>()
-
-        folders.add(rootFolder)
-
-        val rootLength = rootFs.path.length + 1
-
-        var folderCount = 0
-
-        val stack = ArrayDeque<NodeFs.Folder>()
-        stack.add(rootFs)
-
-        while (stack.isNotEmpty()) {
-            val folder = stack.removeLast()
-
-            folder.forEachNodeFs { nodeFs ->
-
-                when (nodeFs) {
-                    is NodeFs.File -> {
-                        if (!isExtensionSupportedLib(nodeFs.extension.text)) {
-                            //Log.d(TAG, "skipped ${nodeFs.path} because extension not supported")
-                            return@forEachNodeFs
-                        }
-
-                        val fileSize = nodeFs.fileSize()
-                        if (fileSize > LIMIT_FILE_SIZE_DB) {
-                            Log.d(
-                                TAG,
-                                "skipped ${nodeFs.path} because size was above $LIMIT_FILE_SIZE_DB ($fileSize)"
-                            )
-                            return@forEachNodeFs
-                        }
-
-                        val relativePath = nodeFs.path.substring(startIndex = rootLength)
-                        val note = Note.new(
-                            relativePath = relativePath,
-                            lastModifiedTimeMillis = timestamps[relativePath]
-                                ?: nodeFs.lastModifiedTime().toMillis(),
-                            content = nodeFs.readText(),
-                        )
-
-                        notes.add(note)
-                        if (notes.size >= 1000) {
-                            insertNotes(notes)
-                            notes.clear()
-                        }
-                        //Log.d(TAG, "add note: $note")
-                    }
+        insertFolders(listOf(NoteFolder.new(relativePath = "")))
 
-                    is NodeFs.Folder -> {
-                        if (nodeFs.isHidden() || nodeFs.isSym()) {
-                            return@forEachNodeFs
-                        }
-                        val noteFolder = NoteFolder.new(
-                            relativePath = nodeFs.path.substring(startIndex = rootLength),
-                        )
-                        //Log.d(TAG, "add noteFolder: $noteFolder")
-                        folders.add(noteFolder)
-
-                        if (folders.size >= 1000) {
-                            insertFolders(folders)
-                            folders.clear()
-                        }
-
-                        if (folderCount % 20 == 0) {
-                            progressCb?.invoke(Progress.GeneratingDatabase(noteFolder.relativePath))
-                        }
-                        folderCount += 1
-
-                        stack.addLast(nodeFs)
-                    }
-                }
+        batches.collect { batch ->
+            insertFolders(batch.folders.map { NoteFolder.new(relativePath = it) })
+
+            insertNotes(batch.notes.map {
+                Note.new(
+                    relativePath = it.relativePath,
+                    lastModifiedTimeMillis = it.lastModifiedTimeMillis,
+                    content = it.content,
+                )
+            })
+
+            (batch.notes.lastOrNull()?.relativePath ?: batch.folders.lastOrNull())?.let {
+                progressCb?.invoke(Progress.GeneratingDatabase(it))
             }
         }
-
-        insertNotes(notes)
-        insertFolders(folders)
     }
 
 
@@ -6,6 +6,12 @@ import io.github.wiiznokes.gitnote.MyApp
 import io.github.wiiznokes.gitnote.R
 import io.github.wiiznokes.gitnote.ui.model.Cred
 import io.github.wiiznokes.gitnote.ui.model.GitAuthor
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.channels.trySendBlocking
+import kotlinx.coroutines.flow.Flow
+import kotlinx.coroutines.flow.buffer
+import kotlinx.coroutines.flow.channelFlow
+import kotlinx.coroutines.flow.flowOn
 import kotlinx.coroutines.sync.Mutex
 import kotlinx.coroutines.sync.withLock
 import kotlin.Result.Companion.failure
@@ -37,6 +43,332 @@ class GitException(
     constructor(type: GitExceptionType) : this(type, null)
 }
 
+// important: must match the order of SignatureStatus in rust
+enum class SignatureStatus {
+    Good,
+    UnknownKey,
+    Bad,
+    Unsigned,
+}
+
+/**
+ * This class is constructed from native code
+ */
+@Keep
+data class CommitSignature(
+    val oid: String,
+    val status: Int,
+    val principal: String?,
+) {
+    fun status(): SignatureStatus = SignatureStatus.entries[status]
+}
+
+/**
+ * This class is constructed from native code
+ *
+ * @param createdAt time of the commit which added the note, following renames
+ * @param authorName author of the last modification
+ * @param revisions number of commits which modified the note
+ */
+@Keep
+data class NoteMetadata(
+    val path: String,
+    val createdAt: Long,
+    val lastModified: Long,
+    val authorName: String,
+    val authorEmail: String,
+    val revisions: Int,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param lastModifiedTimeMillis time of the last commit modifying the note,
+ * or the modification time of the file when it is not committed
+ */
+@Keep
+data class ScannedNote(
+    val relativePath: String,
+    val size: Long,
+    val content: String,
+    val lastModifiedTimeMillis: Long,
+)
+
+/**
+ * This class is constructed from native code
+ */
+@Keep
+data class SearchResult(
+    val path: String,
+    val score: Double,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param commit most recent commit containing this version of the note
+ * @param lineNumber starts at 1
+ */
+@Keep
+data class HistoryMatch(
+    val commit: String,
+    val time: Long,
+    val path: String,
+    val lineNumber: Int,
+    val line: String,
+    val contextBefore: String,
+    val contextAfter: String,
+)
+
+sealed class DiffSide {
+    /**
+     * @param revision any revision, like a commit oid or `HEAD`
+     */
+    data class Commit(val revision: String) : DiffSide()
+    data object Index : DiffSide()
+    data object Worktree : DiffSide()
+
+    // important: must match the order of Side in rust
+    fun code(): Int = when (this) {
+        is Commit -> 0
+        Index -> 1
+        Worktree -> 2
+    }
+
+    fun revision(): String? = (this as? Commit)?.revision
+}
+
+// important: must match the order of LineKind in rust
+enum class DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/**
+ * This class is constructed from native code
+ *
+ * @param oldLine starts at 1, -1 for an added line
+ * @param newLine starts at 1, -1 for a removed line
+ * @param changes with the word diff option, the changed parts of the line,
+ * as pairs of `[start, end)` offsets in [content]
+ */
+@Keep
+data class DiffLine(
+    val kind: Int,
+    val oldLine: Int,
+    val newLine: Int,
+    val content: String,
+    val changes: IntArray,
+) {
+    fun kind(): DiffLineKind = DiffLineKind.entries[kind]
+
+    override fun equals(other: Any?): Boolean {
+        if (this === other) return true
+        if (other !is DiffLine) return false
+        return kind == other.kind && oldLine == other.oldLine && newLine == other.newLine
+                && content == other.content && changes.contentEquals(other.changes)
+    }
+
+    override fun hashCode(): Int {
+        var result = kind
+        result = 31 * result + oldLine
+        result = 31 * result + newLine
+        result = 31 * result + content.hashCode()
+        result = 31 * result + changes.contentHashCode()
+        return result
+    }
+}
+
+/**
+ * This class is constructed from native code
+ */
+@Keep
+data class DiffHunk(
+    val oldStart: Int,
+    val oldLines: Int,
+    val newStart: Int,
+    val newLines: Int,
+    val lines: ArrayList<DiffLine>,
+)
+
+/**
+ * This class is constructed from native code
+ */
+@Keep
+data class BlameCommit(
+    val oid: String,
+    val authorName: String,
+    val authorEmail: String,
+    val time: Long,
+)
+
+/**
+ * @param lines for each line of the note, its index in [commits] (-1 when
+ * the line is not committed yet) followed by its line number in this commit
+ */
+class NoteBlame(
+    val commits: List<BlameCommit>,
+    private val lines: IntArray,
+) {
+    val lineCount: Int
+        get() = lines.size / 2
+
+    /**
+     * @param line starts at 0
+     */
+    fun commit(line: Int): BlameCommit? = commits.getOrNull(lines[line * 2])
+
+    /**
+     * @param line starts at 0
+     * @return the line number in [commit], starting at 1
+     */
+    fun originalLine(line: Int): Int = lines[line * 2 + 1]
+}
+
+/**
+ * This class is constructed from native code
+ *
+ * @param body the message without the summary
+ * @param filesChanged compared to the first parent
+ */
+@Keep
+data class LogEntry(
+    val oid: String,
+    val summary: String,
+    val body: String,
+    val authorName: String,
+    val authorEmail: String,
+    val committerName: String,
+    val committerEmail: String,
+    val time: Long,
+    val filesChanged: Int,
+    val insertions: Int,
+    val deletions: Int,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param index position in the stash list, 0 being the most recent
+ */
+@Keep
+data class StashEntry(
+    val index: Int,
+    val message: String,
+    val oid: String,
+    val time: Long,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * A file which couldn't be restored from a stash without conflict.
+ * Each side is the oid of the blob, null when the file doesn't exist on this side.
+ *
+ * @param ours version of the worktree
+ * @param theirs version of the stash
+ */
+@Keep
+data class StashConflict(
+    val path: String,
+    val ancestor: String?,
+    val ours: String?,
+    val theirs: String?,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param target oid of the tagged commit
+ * @param message message of an annotated tag, without its signature
+ * @param taggerName tagger of an annotated tag, or committer of the commit of a lightweight one
+ */
+@Keep
+data class TagInfo(
+    val name: String,
+    val target: String,
+    val annotated: Boolean,
+    val message: String,
+    val signed: Boolean,
+    val taggerName: String,
+    val taggerEmail: String,
+    val time: Long,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param message like "Fast-Forward: Setting ..." or "reset: moving to ..."
+ */
+@Keep
+data class ReflogEntry(
+    val old: String,
+    val new: String,
+    val message: String,
+    val committerName: String,
+    val committerEmail: String,
+    val time: Long,
+)
+
+// important: must match the order of ResetKind in rust
+enum class ResetKind {
+    /**
+     * Only move the branch
+     */
+    Soft,
+
+    /**
+     * Also reset the index
+     */
+    Mixed,
+
+    /**
+     * Also reset the worktree, after saving it in a backup ref
+     */
+    Hard,
+}
+
+/**
+ * This class is constructed from native code
+ *
+ * @param path relative to the root of the repo
+ * @param link relative to the note, to use in its markdown
+ */
+@Keep
+data class Attachment(
+    val path: String,
+    val link: String,
+)
+
+/**
+ * This class is constructed from native code
+ *
+ * @param exists false when the link of the note is broken
+ */
+@Keep
+data class AttachmentReference(
+    val note: String,
+    val attachment: String,
+    val exists: Boolean,
+)
+
+/**
+ * From the `.gitnote.toml` file of the repo, shared by the devices syncing it.
+ *
+ * @param stashOnSync over [io.github.wiiznokes.gitnote.data.AppPreferences.stashOnSync] when set
+ * @param errors invalid values of the file, replaced by their default
+ */
+data class RepoConfig(
+    val stashOnSync: Boolean?,
+    val errors: List<String>,
+)
+
+class ScanBatch(
+    val notes: List<ScannedNote>,
+    val folders: List<String>,
+)
+
 class GitManager {
 
     companion object {
@@ -122,7 +454,7 @@ class GitManager {
         val res = cloneRepoLib(
             repoPath = repoPath,
             remoteUrl = repoUrl,
-            cred = cred,
+            cred = storedCred(cred),
             progressCallback = this
         )
 
@@ -173,10 +505,46 @@ class GitManager {
         currentSignatureLib()
     }.getOrNull()?.let { GitAuthor(name = it.first, email = it.second) }
 
+    private var storedSshKeyFingerprint: String? = null
+
+    /**
+     * Load the ssh key in the native key store, so only its fingerprint is sent afterward.
+     * Only the fingerprint is kept here, the key is loaded again once it expired.
+     */
+    private fun storedCred(cred: Cred?): Cred? {
+        if (cred !is Cred.Ssh) return cred
+
+        val fingerprint = sshKeyFingerprintLib(cred.publicKey)
+            ?.takeIf { hasSshKeyLib(it) }
+            ?: addSshKeyLib(cred.privateKey, cred.passphrase)
+            ?: throw GitException("can't load the ssh key")
+
+        storedSshKeyFingerprint = fingerprint
+
+        return Cred.SshKeyRef(username = cred.username, fingerprint = fingerprint)
+    }
+
+    suspend fun setKeyStoreTimeout(seconds: Long) = safelyAccessLibGit2 {
+        setKeyStoreTimeoutLib(seconds)
+    }
+
+    /**
+     * Sign the next commits with this ssh key, or stop signing when null.
+     */
+    suspend fun setSigningKey(key: Cred.Ssh?) = safelyAccessLibGit2 {
+        val fingerprint = key?.let { (storedCred(it) as Cred.SshKeyRef).fingerprint }
+        setSigningKeyLib(fingerprint)
+    }
+
+    suspend fun forgetSshKey() = safelyAccessLibGit2 {
+        storedSshKeyFingerprint?.let { removeSshKeyLib(it) }
+        storedSshKeyFingerprint = null
+    }
+
     suspend fun push(cred: Cred?): Result<Unit> = safelyAccessLibGit2 {
         Log.d(TAG, "push: $cred")
         if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
-        val res = pushLib(cred)
+        val res = pushLib(storedCred(cred))
 
         if (res < 0) {
             Log.d(TAG, "push: $res")
@@ -192,13 +560,306 @@ class GitManager {
         Log.d(TAG, "pull: $cred")
         if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
 
-        val res = pullLib(cred, author.name, author.email)
+        val res = pullLib(storedCred(cred), author.name, author.email)
 
         if (res < 0) {
             throw Exception(uiHelper.getString(R.string.error_pull_repo, res.toString()))
         }
     }
 
+    /**
+     * Stash the uncommitted changes, pull, then pop them back.
+     *
+     * @return the conflicts of the pop, in which case the stash is kept
+     */
+    suspend fun pullWithStash(cred: Cred?, author: GitAuthor): Result<List<StashConflict>> =
+        safelyAccessLibGit2 {
+            Log.d(TAG, "pullWithStash: $cred")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            val conflicts = ArrayList<StashConflict>()
+
+            val res = pullWithStashLib(conflicts, storedCred(cred), author.name, author.email)
+
+            if (res < 0) {
+                throw Exception(uiHelper.getString(R.string.error_pull_repo, res.toString()))
+            }
+            conflicts
+        }
+
+    /**
+     * @return false when there was nothing to stash
+     */
+    suspend fun stashSave(
+        author: GitAuthor,
+        message: String? = null,
+        includeUntracked: Boolean = true,
+    ): Result<Boolean> = safelyAccessLibGit2 {
+        Log.d(TAG, "stashSave")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = stashSaveLib(author.name, author.email, message, includeUntracked)
+
+        if (res < 0) {
+            throw Exception("stashSaveLib error $res")
+        }
+        res == 1
+    }
+
+    suspend fun stashList(): Result<List<StashEntry>> = safelyAccessLibGit2 {
+        Log.d(TAG, "stashList")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<StashEntry>()
+
+        val res = stashListLib(list)
+
+        if (res < 0) {
+            throw Exception("stashListLib error $res")
+        }
+        list
+    }
+
+    suspend fun stashApply(index: Int): Result<List<StashConflict>> = stashApply(index, false)
+
+    /**
+     * The stash is dropped only if there is no conflict.
+     */
+    suspend fun stashPop(index: Int): Result<List<StashConflict>> = stashApply(index, true)
+
+    private suspend fun stashApply(index: Int, pop: Boolean): Result<List<StashConflict>> =
+        safelyAccessLibGit2 {
+            Log.d(TAG, "stashApply: index = $index, pop = $pop")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            val conflicts = ArrayList<StashConflict>()
+
+            val res = stashApplyLib(conflicts, index, pop)
+
+            if (res < 0) {
+                throw Exception("stashApplyLib error $res")
+            }
+            conflicts
+        }
+
+    suspend fun stashDrop(index: Int): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "stashDrop: index = $index")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = stashDropLib(index)
+
+        if (res < 0) {
+            throw Exception("stashDropLib error $res")
+        }
+    }
+
+    /**
+     * @param message when not null, the tag is annotated, and signed if a signing key is set
+     */
+    suspend fun createTag(
+        name: String,
+        author: GitAuthor,
+        message: String? = null,
+        revision: String = "HEAD",
+        force: Boolean = false,
+    ): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "createTag: $name")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = createTagLib(name, revision, message, author.name, author.email, force)
+
+        if (res < 0) {
+            throw Exception("createTagLib error $res")
+        }
+    }
+
+    suspend fun listTags(): Result<List<TagInfo>> = safelyAccessLibGit2 {
+        Log.d(TAG, "listTags")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<TagInfo>()
+
+        val res = listTagsLib(list)
+
+        if (res < 0) {
+            throw Exception("listTagsLib error $res")
+        }
+        list
+    }
+
+    suspend fun deleteTag(name: String): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "deleteTag: $name")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = deleteTagLib(name)
+
+        if (res < 0) {
+            throw Exception("deleteTagLib error $res")
+        }
+    }
+
+    /**
+     * @param name the tag to push, or all of them when null
+     */
+    suspend fun pushTags(cred: Cred?, name: String? = null): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "pushTags: $name")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = pushTagsLib(storedCred(cred), name)
+
+        if (res < 0) {
+            throw Exception(uiHelper.getString(R.string.error_push_repo, res.toString()))
+        }
+    }
+
+    /**
+     * Write the notes of the tag [name] as read-only files, in the cache of the app.
+     * The previous checkout of the tag is replaced, the repo is not modified.
+     *
+     * @return the folder of the notes
+     */
+    suspend fun checkoutTag(name: String): Result<String> = safelyAccessLibGit2 {
+        val destPath = MyApp.appModule.context.cacheDir.toPath()
+            .resolve("tags")
+            .resolve(name)
+            .toString()
+        Log.d(TAG, "checkoutTag: $name -> $destPath")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = checkoutTagLib(name, destPath)
+
+        if (res < 0) {
+            throw Exception("checkoutTagLib error $res")
+        }
+
+        destPath
+    }
+
+    /**
+     * Movements of [branch], or of HEAD when null, newest first.
+     */
+    suspend fun reflog(branch: String? = null): Result<List<ReflogEntry>> = safelyAccessLibGit2 {
+        Log.d(TAG, "reflog: $branch")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<ReflogEntry>()
+
+        val res = reflogLib(list, branch)
+
+        if (res < 0) {
+            throw Exception("reflogLib error $res")
+        }
+        list
+    }
+
+    /**
+     * Reset the current branch to [revision].
+     *
+     * @return the backup ref created before a hard reset
+     */
+    suspend fun resetTo(
+        revision: String,
+        kind: ResetKind,
+        author: GitAuthor
+    ): Result<String?> = safelyAccessLibGit2 {
+        Log.d(TAG, "resetTo: $revision, $kind")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val backup = ArrayList<String>()
+
+        val res = resetToLib(backup, revision, kind.ordinal, author.name, author.email)
+
+        if (res < 0) {
+            throw Exception("resetToLib error $res")
+        }
+        backup.firstOrNull()
+    }
+
+    /**
+     * Copy the file [source] next to [note], named after its content.
+     */
+    suspend fun addAttachment(note: String, source: String): Result<Attachment> =
+        safelyAccessLibGit2 {
+            Log.d(TAG, "addAttachment: $note, $source")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            val attachment = ArrayList<Attachment>()
+
+            val res = addAttachmentLib(attachment, note, source)
+
+            if (res < 0) {
+                throw Exception("addAttachmentLib error $res")
+            }
+            attachment.first()
+        }
+
+    /**
+     * Images and other attachments referenced by the markdown notes.
+     *
+     * Notes bigger than [maxSize] are skipped.
+     */
+    suspend fun attachmentReferences(maxSize: Long): Result<List<AttachmentReference>> =
+        safelyAccessLibGit2 {
+            Log.d(TAG, "attachmentReferences")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            val list = ArrayList<AttachmentReference>()
+
+            val res = attachmentReferencesLib(list, maxSize)
+
+            if (res < 0) {
+                throw Exception("attachmentReferencesLib error $res")
+            }
+            list
+        }
+
+    /**
+     * @param path relative to the root of the repo
+     * @return true when the path is hidden from the status, the commits and the notes,
+     * by a `.gitignore` file, the `.gitnoteignore` file, `.gitnote.toml`, or the default
+     * patterns for the junk files of the editors
+     */
+    suspend fun isIgnored(path: String): Result<Boolean> = safelyAccessLibGit2 {
+        Log.d(TAG, "isIgnored: $path")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = isIgnoredLib(path)
+
+        if (res < 0) {
+            throw Exception("isIgnoredLib error $res")
+        }
+        res == 1
+    }
+
+    /**
+     * Append a gitignore [pattern] to the `.gitnoteignore` file of the repo.
+     */
+    suspend fun addIgnorePattern(pattern: String): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "addIgnorePattern: $pattern")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = addIgnorePatternLib(pattern)
+
+        if (res < 0) {
+            throw Exception("addIgnorePatternLib error $res")
+        }
+    }
+
+    suspend fun repoConfig(): Result<RepoConfig> = safelyAccessLibGit2 {
+        Log.d(TAG, "repoConfig")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val errors = ArrayList<String>()
+
+        val stashOnSync = when (repoConfigLib(errors)) {
+            1 -> false
+            2 -> true
+            else -> null
+        }
+
+        RepoConfig(stashOnSync, errors)
+    }
+
     suspend fun getTimestamps(): Result<HashMap<String, Long>> = safelyAccessLibGit2 {
         Log.d(TAG, "getTimestamps")
 
@@ -213,9 +874,227 @@ class GitManager {
     }
 
 
+    /**
+     * @param strict refuse to pull commits that are not signed by a key of [allowedSignersPath]
+     */
+    suspend fun setSignatureVerification(allowedSignersPath: String?, strict: Boolean) =
+        safelyAccessLibGit2 {
+            setSignatureVerificationLib(allowedSignersPath, strict)
+        }
+
+    suspend fun verifyCommits(limit: Int): Result<List<CommitSignature>> = safelyAccessLibGit2 {
+        Log.d(TAG, "verifyCommits")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<CommitSignature>()
+
+        val res = verifyCommitsLib(list, limit)
+
+        if (res < 0) {
+            throw Exception("verifyCommitsLib error $res")
+        }
+        list
+    }
+
+    suspend fun getNoteMetadata(): Result<List<NoteMetadata>> = safelyAccessLibGit2 {
+        Log.d(TAG, "getNoteMetadata")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<NoteMetadata>()
+
+        val res = getNoteMetadataLib(list)
+
+        if (res < 0) {
+            throw Exception("getNoteMetadataLib error $res")
+        }
+        list
+    }
+
+    /**
+     * Search the notes at HEAD, best results first.
+     *
+     * Words must all be present, `word*` matches a prefix and `"some words"` a phrase.
+     */
+    suspend fun search(query: String, limit: Int, offset: Int = 0): Result<List<SearchResult>> =
+        safelyAccessLibGit2 {
+            Log.d(TAG, "search: $query")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            val list = ArrayList<SearchResult>()
+
+            val res = searchLib(list, query, limit, offset)
+
+            if (res < 0) {
+                throw Exception("searchLib error $res")
+            }
+            list
+        }
+
+    /**
+     * Search every version of the notes, including deleted ones.
+     *
+     * @param progressCallback receive the percentage of commits searched,
+     * return false to stop with the matches found so far
+     */
+    suspend fun searchHistory(
+        pattern: String,
+        isRegex: Boolean = false,
+        ignoreCase: Boolean = true,
+        context: Int = 1,
+        maxMatches: Int = 500,
+        progressCallback: (Int) -> Boolean = { true },
+    ): Result<List<HistoryMatch>> = safelyAccessLibGit2 {
+        Log.d(TAG, "searchHistory: $pattern")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        actualCb = progressCallback
+
+        val list = ArrayList<HistoryMatch>()
+
+        val res = try {
+            searchHistoryLib(list, pattern, isRegex, ignoreCase, context, maxMatches, this)
+        } finally {
+            actualCb = null
+        }
+
+        if (res < 0) {
+            throw Exception("searchHistoryLib error $res")
+        }
+        list
+    }
+
+    /**
+     * Differences of [path] between [from] and [to]. A missing file is treated as empty.
+     *
+     * @param wordDiff mark the changed words of the modified lines
+     */
+    suspend fun diff(
+        path: String,
+        from: DiffSide,
+        to: DiffSide,
+        wordDiff: Boolean = false,
+    ): Result<List<DiffHunk>> = safelyAccessLibGit2 {
+        Log.d(TAG, "diff: $path, $from, $to")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<DiffHunk>()
+
+        val res = diffLib(
+            list,
+            path,
+            from.code(),
+            from.revision(),
+            to.code(),
+            to.revision(),
+            wordDiff
+        )
+
+        if (res < 0) {
+            throw Exception("diffLib error $res")
+        }
+        list
+    }
+
+    /**
+     * @param oldestCommit lines older than this revision are attributed to it
+     */
+    suspend fun blame(
+        path: String,
+        ignoreWhitespace: Boolean = false,
+        oldestCommit: String? = null,
+    ): Result<NoteBlame> = safelyAccessLibGit2 {
+        Log.d(TAG, "blame: $path")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val commits = ArrayList<BlameCommit>()
+
+        val lines = blameLib(commits, path, ignoreWhitespace, oldestCommit)
+            ?: throw Exception("blameLib error")
+
+        NoteBlame(commits, lines)
+    }
+
+    /**
+     * Commits reachable from HEAD, newest first.
+     *
+     * @param after oid of the last commit of the previous page, to load the next page
+     * @param pathFilter only the commits modifying this file or folder
+     * @param authorFilter case insensitive part of the author name or email
+     * @param since bounds of the commit time, in milliseconds, inclusive
+     */
+    suspend fun log(
+        after: String? = null,
+        limit: Int = 50,
+        pathFilter: String? = null,
+        authorFilter: String? = null,
+        since: Long = Long.MIN_VALUE,
+        until: Long = Long.MAX_VALUE,
+    ): Result<List<LogEntry>> = safelyAccessLibGit2 {
+        Log.d(TAG, "log: after = $after, limit = $limit")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val list = ArrayList<LogEntry>()
+
+        val res = logLib(list, after, limit, pathFilter, authorFilter, since, until)
+
+        if (res < 0) {
+            throw Exception("logLib error $res")
+        }
+        list
+    }
+
+    /**
+     * Move a note or a folder in the worktree and the index, so its history is followed.
+     */
+    suspend fun movePath(from: String, to: String): Result<Unit> = safelyAccessLibGit2 {
+        Log.d(TAG, "movePath: $from -> $to")
+        if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+        val res = movePathLib(from, to)
+
+        if (res < 0) {
+            throw Exception("movePathLib error $res")
+        }
+    }
+
+    private var actualScanCb: ((ScanBatch) -> Boolean)? = null
+
+    /**
+     * This function is called from native code
+     */
+    @Keep
+    fun scanCb(notes: ArrayList<ScannedNote>, folders: ArrayList<String>): Boolean {
+        return actualScanCb?.invoke(ScanBatch(notes, folders)) != false
+    }
+
+    /**
+     * Folders and notes of [rootPath], read in parallel by the native code.
+     * Hidden folders, ignored files and files bigger than [maxSize] are skipped.
+     */
+    fun scanNotes(rootPath: String, maxSize: Long): Flow<ScanBatch> = channelFlow {
+        safelyAccessLibGit2 {
+            Log.d(TAG, "scanNotes: $rootPath")
+            if (!isRepoInitialized) throw GitException(GitExceptionType.RepoNotInit)
+
+            // stop the scan when the collector is cancelled
+            actualScanCb = { batch -> trySendBlocking(batch).isSuccess }
+
+            val res = try {
+                scanNotesLib(rootPath, maxSize, this@GitManager)
+            } finally {
+                actualScanCb = null
+            }
+
+            if (res < 0) {
+                throw Exception("scanNotesLib error $res")
+            }
+        }.getOrThrow()
+    }.buffer(2).flowOn(Dispatchers.IO)
+
     fun closeRepoWithoutLock() {
         if (isRepoInitialized) closeRepoLib()
         isRepoInitialized = false
+        storedSshKeyFingerprint = null
     }
 
     suspend fun closeRepo() = safelyAccessLibGit2 {
@@ -262,7 +1141,167 @@ private external fun isChangeLib(): Int
 
 private external fun getTimestampsLib(timestamps: HashMap<String, Long>): Int
 
-external fun generateSshKeysLib(): Pair<String, String>
+private external fun getNoteMetadataLib(metadata: ArrayList<NoteMetadata>): Int
+
+private external fun searchLib(
+    results: ArrayList<SearchResult>,
+    query: String,
+    limit: Int,
+    offset: Int
+): Int
+
+private external fun searchHistoryLib(
+    matches: ArrayList<HistoryMatch>,
+    pattern: String,
+    isRegex: Boolean,
+    ignoreCase: Boolean,
+    context: Int,
+    maxMatches: Int,
+    progressCallback: GitManager
+): Int
+
+private external fun diffLib(
+    hunks: ArrayList<DiffHunk>,
+    path: String,
+    fromSide: Int,
+    fromRevision: String?,
+    toSide: Int,
+    toRevision: String?,
+    wordDiff: Boolean
+): Int
+
+private external fun blameLib(
+    commits: ArrayList<BlameCommit>,
+    path: String,
+    ignoreWhitespace: Boolean,
+    oldestCommit: String?
+): IntArray?
+
+private external fun logLib(
+    entries: ArrayList<LogEntry>,
+    after: String?,
+    limit: Int,
+    pathFilter: String?,
+    authorFilter: String?,
+    since: Long,
+    until: Long
+): Int
+
+private external fun pullWithStashLib(
+    conflicts: ArrayList<StashConflict>,
+    cred: Cred?,
+    name: String,
+    email: String
+): Int
+
+private external fun stashSaveLib(
+    name: String,
+    email: String,
+    message: String?,
+    includeUntracked: Boolean
+): Int
+
+private external fun stashListLib(stashes: ArrayList<StashEntry>): Int
+
+private external fun stashApplyLib(conflicts: ArrayList<StashConflict>, index: Int, pop: Boolean): Int
+
+private external fun stashDropLib(index: Int): Int
+
+private external fun createTagLib(
+    name: String,
+    revision: String,
+    message: String?,
+    taggerName: String,
+    taggerEmail: String,
+    force: Boolean
+): Int
+
+private external fun listTagsLib(tags: ArrayList<TagInfo>): Int
+
+private external fun deleteTagLib(name: String): Int
+
+private external fun pushTagsLib(cred: Cred?, name: String?): Int
+
+private external fun checkoutTagLib(name: String, dest: String): Int
+
+private external fun reflogLib(entries: ArrayList<ReflogEntry>, branch: String?): Int
+
+private external fun resetToLib(
+    backup: ArrayList<String>,
+    revision: String,
+    kind: Int,
+    name: String,
+    email: String
+): Int
+
+private external fun addAttachmentLib(
+    attachment: ArrayList<Attachment>,
+    note: String,
+    source: String
+): Int
+
+private external fun attachmentReferencesLib(
+    references: ArrayList<AttachmentReference>,
+    maxSize: Long
+): Int
+
+private external fun isIgnoredLib(path: String): Int
+
+private external fun addIgnorePatternLib(pattern: String): Int
+
+private external fun repoConfigLib(errors: ArrayList<String>): Int
+
+private external fun movePathLib(from: String, to: String): Int
+
+private external fun scanNotesLib(rootPath: String, maxSize: Long, callback: GitManager): Int
+
+// important: must match the order of KeyAlgorithm in rust
+enum class KeyAlgorithm {
+    Ed25519,
+    EcdsaP256,
+    Rsa3072,
+    Rsa4096,
+}
+
+/**
+ * @param passphrase when not null, the private key is encrypted with it
+ */
+fun generateSshKeys(
+    algorithm: KeyAlgorithm = KeyAlgorithm.Ed25519,
+    comment: String = "GitNote",
+    passphrase: String? = null,
+): Result<Pair<String, String>> {
+    val keys = generateSshKeysLib(algorithm.ordinal, comment, passphrase)
+        ?: return Result.failure(
+            GitException(MyApp.appModule.context.getString(R.string.error_generate_ssh_keys))
+        )
+    return Result.success(keys)
+}
+
+// return null on error
+private external fun generateSshKeysLib(
+    algorithm: Int,
+    comment: String?,
+    passphrase: String?
+): Pair<String, String>?
+
+// return the fingerprint of the key, or null on error
+private external fun addSshKeyLib(privateKey: String, passphrase: String?): String?
+
+private external fun hasSshKeyLib(fingerprint: String): Boolean
+
+// return the fingerprint of the public key, or null on error
+private external fun sshKeyFingerprintLib(publicKey: String): String?
+
+private external fun removeSshKeyLib(fingerprint: String): Boolean
+
+private external fun setKeyStoreTimeoutLib(seconds: Long)
+
+private external fun setSigningKeyLib(fingerprint: String?)
+
+private external fun setSignatureVerificationLib(allowedSignersPath: String?, strict: Boolean)
+
+private external fun verifyCommitsLib(commits: ArrayList<CommitSignature>, limit: Int): Int
 
 // return true if url is ssh
 external fun getUrlInfoLib(url: String): Boolean?
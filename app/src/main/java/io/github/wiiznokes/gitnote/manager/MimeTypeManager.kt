@@ -1,25 +1,112 @@
 package io.github.wiiznokes.gitnote.manager
 
+import io.github.wiiznokes.gitnote.manager.ExtensionType.Attachment
+import io.github.wiiznokes.gitnote.manager.ExtensionType.Binary
+import io.github.wiiznokes.gitnote.manager.ExtensionType.Code
+import io.github.wiiznokes.gitnote.manager.ExtensionType.Image
 import io.github.wiiznokes.gitnote.manager.ExtensionType.Markdown
 import io.github.wiiznokes.gitnote.manager.ExtensionType.Text
 
 
 enum class ExtensionType {
     Text,
-    Markdown;
+    Markdown,
+    Image,
+    Attachment,
+    Code,
+    Binary;
 }
 
-fun extensionType(extension: String): ExtensionType? =
-    extensionTypeFromNumber(extensionTypeLib(extension))
+/**
+ * @param language like "rust" or "python", for [ExtensionType.Code]
+ */
+data class FileType(
+    val type: ExtensionType,
+    val language: String?,
+)
+
+/**
+ * Classify a file from its [name], and its first bytes when [path] is set:
+ * well-known names like README, shebangs, modelines and binary content are recognized.
+ */
+fun fileType(name: String, path: String? = null): FileType? {
+    val language = ArrayList<String>()
+    val type = extensionTypeFromNumber(extensionTypeLib(name, path, language)) ?: return null
+
+    return FileType(type, language.firstOrNull())
+}
+
+fun extensionType(extension: String): ExtensionType? = fileType("file.$extension")?.type
 
 private fun extensionTypeFromNumber(num: Int): ExtensionType? =
     when (num) {
         0 -> null
         1 -> Text
         2 -> Markdown
+        3 -> Image
+        4 -> Attachment
+        5 -> Code
+        6 -> Binary
         else -> throw Exception("Invalid number for ExtensionType: ^$num")
     }
 
-private external fun extensionTypeLib(extension: String): Int
+/**
+ * Extensions added by the app, over the built-in ones and the ones of `.gitnote.toml`,
+ * in the same format:
+ * ```toml
+ * [extensions]
+ * org = "markdown"
+ * tex = { type = "code", language = "latex" }
+ * ```
+ *
+ * @return false if [config] is invalid
+ */
+fun setAppExtensions(config: String): Boolean = setAppExtensionsLib(config) >= 0
+
+private external fun setAppExtensionsLib(config: String?): Int
+
+private external fun extensionTypeLib(
+    name: String,
+    path: String?,
+    language: ArrayList<String>
+): Int
+
+enum class TokenStyle {
+    Keyword,
+    Type,
+    Literal,
+    String,
+    Number,
+    Comment,
+    Attribute,
+    Tag,
+    Variable,
+    Function;
+}
+
+/**
+ * @param spans a `[start, end, style]` triple for each span, in offsets of the text
+ */
+class Highlight(
+    private val spans: IntArray,
+) {
+    val spanCount: Int
+        get() = spans.size / 3
+
+    fun start(span: Int): Int = spans[span * 3]
+
+    fun end(span: Int): Int = spans[span * 3 + 1]
+
+    fun style(span: Int): TokenStyle = TokenStyle.entries[spans[span * 3 + 2] - 1]
+}
+
+/**
+ * Syntax highlighting of [text], empty when [language] has no highlighter.
+ *
+ * @param language the one of [FileType]
+ */
+fun highlight(language: String, text: String): Highlight = Highlight(highlightLib(language, text))
+
+private external fun highlightLib(language: String, text: String): IntArray
 
-external fun isExtensionSupportedLib(extension: String): Boolean
\ No newline at end of file
+external fun isExtensionSupportedLib(extension: String): Boolean
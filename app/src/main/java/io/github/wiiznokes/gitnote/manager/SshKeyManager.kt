@@ -0,0 +1,63 @@
+package io.github.wiiznokes.gitnote.manager
+
+import androidx.annotation.Keep
+
+
+// important: must match the order of KeyError in rust, 0 is reserved for success
+enum class SshKeyError {
+    InvalidFormat,
+    PemFormat,
+    PuttyFormat,
+    PublicKeyInstead,
+    PassphraseRequired,
+    WrongPassphrase,
+    InvalidPublicKey,
+    PublicKeyMismatch;
+}
+
+/**
+ * @param detail why the key was rejected, to show to the user
+ */
+class SshKeyException(val error: SshKeyError, val detail: String) :
+    Exception(detail.ifEmpty { error.name })
+
+/**
+ * This class is constructed from native code
+ */
+@Keep
+data class SshKeyInfo(
+    val error: Int,
+    /**
+     * Why the key was rejected, empty on success
+     */
+    val errorMessage: String,
+    val algorithm: String,
+    val fingerprint: String,
+    val comment: String,
+    val encrypted: Boolean,
+    val publicKey: String,
+)
+
+/**
+ * Parse a pasted private key, check the passphrase, and that [publicKey] match it.
+ * The public key is derived from the private key when [publicKey] is null.
+ */
+fun inspectSshKey(
+    privateKey: String,
+    passphrase: String?,
+    publicKey: String?
+): Result<SshKeyInfo> {
+    val info = inspectSshKeyLib(privateKey, passphrase, publicKey)
+
+    return if (info.error == 0) {
+        Result.success(info)
+    } else {
+        Result.failure(SshKeyException(SshKeyError.entries[info.error - 1], info.errorMessage))
+    }
+}
+
+private external fun inspectSshKeyLib(
+    privateKey: String,
+    passphrase: String?,
+    publicKey: String?
+): SshKeyInfo
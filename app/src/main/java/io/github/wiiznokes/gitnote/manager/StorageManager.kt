@@ -16,6 +16,8 @@ import kotlin.Result.Companion.success
 
 private const val TAG = "StorageManager"
 
+private const val LIMIT_FILE_SIZE_DB: Long = 2 * 1024 * 1024
+
 sealed interface SyncState {
 
     data class Ok(val isConsumed: Boolean) : SyncState
@@ -136,9 +138,7 @@ class StorageManager {
         Log.d(TAG, "repoPath = $repoPath")
 
         progressCb?.invoke(Progress.Timestamps)
-        val timestamps = gitManager.getTimestamps().getOrThrow()
-
-        dao.clearAndInit(repoPath, timestamps, progressCb)
+        dao.clearAndInit(gitManager.scanNotes(repoPath, LIMIT_FILE_SIZE_DB), progressCb)
         prefs.databaseCommit.update(fsCommit)
 
         return success(Unit)
@@ -169,19 +169,27 @@ class StorageManager {
 
             val rootPath = prefs.repoPath()
             val previousFile = previous.toFileFs(rootPath)
+            val newFile = new.toFileFs(rootPath)
 
-            previousFile.delete().onFailure {
-                val message =
-                    uiHelper.getString(R.string.error_delete_file, previousFile.path, it.message)
-                Log.e(TAG, message)
-                uiHelper.makeToast(message)
-            }
+            // moving it with git keeps its history
+            val moved = previous.relativePath != new.relativePath &&
+                    gitManager.movePath(previous.relativePath, new.relativePath).onFailure {
+                        Log.w(TAG, "movePath failed: ${it.message}")
+                    }.isSuccess
 
-            val newFile = new.toFileFs(rootPath)
-            newFile.create().onFailure {
-                val message = uiHelper.getString(R.string.error_create_file, it.message)
-                Log.e(TAG, message)
-                uiHelper.makeToast(message)
+            if (!moved) {
+                previousFile.delete().onFailure {
+                    val message =
+                        uiHelper.getString(R.string.error_delete_file, previousFile.path, it.message)
+                    Log.e(TAG, message)
+                    uiHelper.makeToast(message)
+                }
+
+                newFile.create().onFailure {
+                    val message = uiHelper.getString(R.string.error_create_file, it.message)
+                    Log.e(TAG, message)
+                    uiHelper.makeToast(message)
+                }
             }
 
             newFile.write(new.content).onFailure {
@@ -326,21 +334,41 @@ class StorageManager {
 
         var isError = false
 
-        gitManager.commitAll(
-            author,
-            "commit from gitnote, before doing a change"
-        ).onFailure { err ->
-            err.message?.let { Log.e(TAG, it) }
-            _syncState.emit(SyncState.Error(err.message))
-            return failure(err)
-        }
+        val stashOnSync = gitManager.repoConfig().getOrNull()?.stashOnSync
+            ?: prefs.stashOnSync.get()
 
-        if (remoteUrl.isNotEmpty()) {
+        if (remoteUrl.isNotEmpty() && stashOnSync) {
             _syncState.emit(SyncState.Pull)
-            gitManager.pull(cred, author).onFailure { err ->
+            gitManager.pullWithStash(cred, author).onFailure { err ->
                 isError = true
                 err.message?.let { Log.e(TAG, it) }
                 _syncState.emit(SyncState.Error(err.message))
+            }.onSuccess { conflicts ->
+                if (conflicts.isNotEmpty()) {
+                    val message = "conflicts when restoring the stash: ${conflicts.joinToString { it.path }}"
+                    Log.e(TAG, message)
+                    _syncState.emit(SyncState.Error(message))
+                    // the stash is kept, don't commit the conflict markers
+                    return failure(Exception(message))
+                }
+            }
+        } else {
+            gitManager.commitAll(
+                author,
+                "commit from gitnote, before doing a change"
+            ).onFailure { err ->
+                err.message?.let { Log.e(TAG, it) }
+                _syncState.emit(SyncState.Error(err.message))
+                return failure(err)
+            }
+
+            if (remoteUrl.isNotEmpty()) {
+                _syncState.emit(SyncState.Pull)
+                gitManager.pull(cred, author).onFailure { err ->
+                    isError = true
+                    err.message?.let { Log.e(TAG, it) }
+                    _syncState.emit(SyncState.Error(err.message))
+                }
             }
         }
 
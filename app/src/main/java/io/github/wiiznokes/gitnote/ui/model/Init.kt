@@ -27,6 +27,14 @@ sealed class Cred : Parcelable {
             return "Ssh(username=$username, publicKey=$publicKey, privateKeyLen=${privateKey.length}, passphraseLen=${passphrase?.length})"
         }
     }
+
+    /**
+     * Reference to a key loaded in the native key store
+     */
+    data class SshKeyRef(
+        val username: String = "git",
+        val fingerprint: String,
+    ) : Cred()
 }
 
 enum class CredType {
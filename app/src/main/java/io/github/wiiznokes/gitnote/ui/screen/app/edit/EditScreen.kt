@@ -44,7 +44,7 @@ import androidx.compose.ui.text.input.TextFieldValue
 import androidx.compose.ui.unit.dp
 import io.github.wiiznokes.gitnote.R
 import io.github.wiiznokes.gitnote.manager.ExtensionType
-import io.github.wiiznokes.gitnote.manager.extensionType
+import io.github.wiiznokes.gitnote.manager.fileType
 import io.github.wiiznokes.gitnote.ui.component.RequestConfirmationDialog
 import io.github.wiiznokes.gitnote.ui.component.SimpleIcon
 import io.github.wiiznokes.gitnote.ui.destination.EditParams
@@ -68,10 +68,17 @@ fun EditScreen(
 
     val extension = editParams.fileExtension()
 
-    val vm = when (extensionType(extension.text)) {
-        ExtensionType.Text -> newEditViewModel(editParams)
+    val note = when (editParams) {
+        is EditParams.Idle -> editParams.note
+        is EditParams.Saved -> editParams.note
+    }
+
+    val fileType = fileType(note.fullName())
+
+    val vm = when (fileType?.type) {
+        ExtensionType.Text, ExtensionType.Code -> newEditViewModel(editParams)
         ExtensionType.Markdown -> newMarkDownVM(editParams)
-        null -> throw Exception("file extension not supported, but present in the database?? $extension")
+        ExtensionType.Image, ExtensionType.Attachment, ExtensionType.Binary, null -> throw Exception("file extension not supported, but present in the database?? $extension")
     }
 
     val showShouldQuitDialog = rememberSaveable {
@@ -241,7 +248,8 @@ fun EditScreen(
                             textFocusRequester = textFocusRequester,
                             onFinished = onFinished,
                             isReadOnlyModeActive = isReadOnlyModeActive,
-                            textContent = textContent
+                            textContent = textContent,
+                            language = fileType?.language,
                         )
                     }
                 }
@@ -293,6 +301,7 @@ fun GenericTextField(
     onFinished: () -> Unit,
     isReadOnlyModeActive: Boolean = false,
     textContent: TextFieldValue,
+    language: String? = null,
 ) {
     TextField(
         modifier = Modifier
@@ -311,7 +320,8 @@ fun GenericTextField(
         keyboardActions = KeyboardActions(
             onDone = { vm.save(onSuccess = onFinished) }
         ),
-        readOnly = isReadOnlyModeActive
+        readOnly = isReadOnlyModeActive,
+        visualTransformation = rememberSyntaxHighlight(language),
     )
 
 
@@ -0,0 +1,71 @@
+package io.github.wiiznokes.gitnote.ui.screen.app.edit
+
+import androidx.compose.material3.ColorScheme
+import androidx.compose.material3.MaterialTheme
+import androidx.compose.runtime.Composable
+import androidx.compose.runtime.remember
+import androidx.compose.ui.graphics.Color
+import androidx.compose.ui.text.AnnotatedString
+import androidx.compose.ui.text.SpanStyle
+import androidx.compose.ui.text.font.FontFamily
+import androidx.compose.ui.text.font.FontStyle
+import androidx.compose.ui.text.input.OffsetMapping
+import androidx.compose.ui.text.input.TransformedText
+import androidx.compose.ui.text.input.VisualTransformation
+import io.github.wiiznokes.gitnote.manager.TokenStyle
+import io.github.wiiznokes.gitnote.manager.highlight
+
+
+private fun ColorScheme.tokenColor(style: TokenStyle): Color = when (style) {
+    TokenStyle.Keyword -> primary
+    TokenStyle.Type -> tertiary
+    TokenStyle.Literal, TokenStyle.Number -> secondary
+    TokenStyle.String -> tertiary.copy(alpha = 0.8f)
+    TokenStyle.Comment -> onSurfaceVariant
+    TokenStyle.Attribute, TokenStyle.Tag -> primary.copy(alpha = 0.8f)
+    TokenStyle.Variable, TokenStyle.Function -> secondary.copy(alpha = 0.8f)
+}
+
+/**
+ * Render the text of a code note with the spans of the native highlighter.
+ */
+class SyntaxHighlightTransformation(
+    private val language: String,
+    private val colorScheme: ColorScheme,
+) : VisualTransformation {
+
+    override fun filter(text: AnnotatedString): TransformedText {
+        val highlight = highlight(language, text.text)
+
+        val styled = AnnotatedString.Builder(text).apply {
+            addStyle(SpanStyle(fontFamily = FontFamily.Monospace), 0, text.length)
+
+            for (span in 0 until highlight.spanCount) {
+                val style = highlight.style(span)
+                addStyle(
+                    SpanStyle(
+                        color = colorScheme.tokenColor(style),
+                        fontStyle = if (style == TokenStyle.Comment) FontStyle.Italic else null,
+                    ),
+                    highlight.start(span),
+                    highlight.end(span),
+                )
+            }
+        }.toAnnotatedString()
+
+        return TransformedText(styled, OffsetMapping.Identity)
+    }
+}
+
+@Composable
+fun rememberSyntaxHighlight(language: String?): VisualTransformation {
+    val colorScheme = MaterialTheme.colorScheme
+
+    return remember(language, colorScheme) {
+        if (language == null) {
+            VisualTransformation.None
+        } else {
+            SyntaxHighlightTransformation(language, colorScheme)
+        }
+    }
+}
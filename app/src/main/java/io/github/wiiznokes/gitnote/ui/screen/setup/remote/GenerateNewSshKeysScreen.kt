@@ -52,7 +52,7 @@ fun GenerateNewSshKeysScreen(
     storageConfig: StorageConfiguration,
     url: String,
     vm: SetupViewModelI,
-    generateSshKeys: () -> Pair<String, String>,
+    generateSshKeys: () -> Pair<String, String>?,
     onClone: () -> Unit,
     onSuccess: () -> Unit,
 ) {
@@ -69,7 +69,7 @@ fun GenerateNewSshKeysScreen(
         val privateKey = rememberSaveable { mutableStateOf("") }
 
         LaunchedEffect(true) {
-            val (public, private) = generateSshKeys()
+            val (public, private) = generateSshKeys() ?: return@LaunchedEffect
             Log.d(TAG, public)
             publicKey.value = public
             privateKey.value = private
@@ -120,9 +120,10 @@ fun GenerateNewSshKeysScreen(
                 SetupButton(
                     text = stringResource(R.string.regenerate_key),
                     onClick = {
-                        val (public, private) = generateSshKeys()
-                        publicKey.value = public
-                        privateKey.value = private
+                        generateSshKeys()?.let { (public, private) ->
+                            publicKey.value = public
+                            privateKey.value = private
+                        }
                     }
                 )
             }
@@ -13,7 +13,7 @@ import dev.olshevski.navigation.reimagined.NavTransitionSpec
 import dev.olshevski.navigation.reimagined.navigate
 import dev.olshevski.navigation.reimagined.pop
 import dev.olshevski.navigation.reimagined.rememberNavController
-import io.github.wiiznokes.gitnote.manager.generateSshKeysLib
+import io.github.wiiznokes.gitnote.manager.generateSshKeys
 import io.github.wiiznokes.gitnote.ui.destination.RemoteDestination
 import io.github.wiiznokes.gitnote.ui.destination.RemoteDestination.AuthorizeGitNote
 import io.github.wiiznokes.gitnote.ui.destination.RemoteDestination.EnterUrl
@@ -148,7 +148,9 @@ fun RemoteScreen(
                 storageConfig = storageConfig,
                 url = remoteDestination.url,
                 vm = vm,
-                generateSshKeys = ::generateSshKeysLib,
+                generateSshKeys = {
+                    generateSshKeys().onFailure { vm.uiHelper.makeToast(it.message) }.getOrNull()
+                },
                 onSuccess = onInitSuccess,
                 onClone = { navController.navigate(RemoteDestination.Cloning) }
             )
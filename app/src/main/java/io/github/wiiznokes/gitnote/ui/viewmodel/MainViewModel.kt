@@ -1,17 +1,22 @@
 package io.github.wiiznokes.gitnote.ui.viewmodel
 
+import android.util.Log
 import androidx.lifecycle.ViewModel
 import io.github.wiiznokes.gitnote.MyApp
+import io.github.wiiznokes.gitnote.R
 import io.github.wiiznokes.gitnote.data.AppPreferences
 import io.github.wiiznokes.gitnote.data.StorageConfig
 import io.github.wiiznokes.gitnote.data.platform.NodeFs
 import io.github.wiiznokes.gitnote.helper.StoragePermissionHelper
 import io.github.wiiznokes.gitnote.helper.UiHelper
+import io.github.wiiznokes.gitnote.manager.setAppExtensions
 import io.github.wiiznokes.gitnote.ui.model.StorageConfiguration
 import kotlinx.coroutines.CoroutineScope
 import kotlinx.coroutines.Dispatchers
 import kotlinx.coroutines.launch
 
+private const val TAG = "MainViewModel"
+
 class MainViewModel : ViewModel() {
 
     val prefs: AppPreferences = MyApp.appModule.appPreferences
@@ -49,9 +54,20 @@ class MainViewModel : ViewModel() {
             return false
         }
 
+        if (!setAppExtensions(prefs.extensionsOverride.get())) {
+            Log.e(TAG, "invalid extensions override")
+        }
+
         gitManager.openRepo(storageConfig.repoPath()).onFailure {
             return false
         }
+        gitManager.repoConfig().onSuccess { config ->
+            if (config.errors.isNotEmpty()) {
+                uiHelper.makeToast(
+                    uiHelper.getString(R.string.error_invalid_repo_config, config.errors.first())
+                )
+            }
+        }
         prefs.applyGitAuthorDefaults(null, gitManager.currentSignature())
 
         CoroutineScope(Dispatchers.IO).launch {
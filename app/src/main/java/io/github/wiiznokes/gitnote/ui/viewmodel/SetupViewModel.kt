@@ -12,7 +12,7 @@ import io.github.wiiznokes.gitnote.data.AppPreferences
 import io.github.wiiznokes.gitnote.data.platform.NodeFs
 import io.github.wiiznokes.gitnote.helper.UiHelper
 import io.github.wiiznokes.gitnote.manager.Progress
-import io.github.wiiznokes.gitnote.manager.generateSshKeysLib
+import io.github.wiiznokes.gitnote.manager.generateSshKeys
 import io.github.wiiznokes.gitnote.provider.GithubProvider
 import io.github.wiiznokes.gitnote.provider.Provider
 import io.github.wiiznokes.gitnote.provider.ProviderType
@@ -325,7 +325,11 @@ class SetupViewModel(val authFlow: SharedFlow<String>) : ViewModel(), SetupViewM
     ) {
 
         runCloneJob {
-            val (publicKey, privateKey) = generateSshKeysLib()
+            val (publicKey, privateKey) = generateSshKeys().getOrElse {
+                Log.e(TAG, "generateSshKeys: ${it.message}, $it")
+                _initState.emit(InitState.Error(it.message))
+                return@runCloneJob
+            }
 
             _initState.emit(InitState.AddingDeployKey)
             try {
@@ -375,7 +379,11 @@ class SetupViewModel(val authFlow: SharedFlow<String>) : ViewModel(), SetupViewM
                 return@runCloneJob
             }
 
-            val (publicKey, privateKey) = generateSshKeysLib()
+            val (publicKey, privateKey) = generateSshKeys().getOrElse {
+                Log.e(TAG, "generateSshKeys: ${it.message}, $it")
+                _initState.emit(InitState.Error(it.message))
+                return@runCloneJob
+            }
 
             _initState.emit(InitState.AddingDeployKey)
             try {
@@ -0,0 +1,1074 @@
+//! Syntax highlighting of the code notes, with a tokenizer per language id
+//! of [`crate::mime_types`] instead of a grammar engine.
+
+// important: 0 is reserved for None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Keyword = 1,
+    Type = 2,
+    /// Like `true` or `null`, and the html entities.
+    Literal = 3,
+    String = 4,
+    Number = 5,
+    Comment = 6,
+    /// Annotations, attributes, and the preprocessor directives.
+    Attribute = 7,
+    /// Markup tags, and the sections of the configuration files.
+    Tag = 8,
+    /// Like `$HOME` in the shells.
+    Variable = 9,
+    Function = 10,
+}
+
+/// `start..end` are byte offsets in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+struct Syntax {
+    line_comments: &'static [&'static str],
+    /// Only at the start of a line, after the indentation.
+    line_start_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Delimiters of the strings which can span several lines.
+    multiline_strings: &'static [&'static str],
+    /// Strings with backslash escapes.
+    quotes: &'static [char],
+    /// Strings without escape.
+    raw_quotes: &'static [char],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    literals: &'static [&'static str],
+    /// The words are compared without case, the lists are lowercase.
+    case_insensitive: bool,
+    /// Words starting with an uppercase letter are types.
+    capitalized_types: bool,
+    /// Prefix of the variables.
+    variable: Option<char>,
+    /// Prefix of the annotations, like `@` or `#[`.
+    attributes: &'static [&'static str],
+    /// `#include` like lines.
+    preprocessor: bool,
+    /// `[section]` lines.
+    sections: bool,
+    /// `'a` are lifetimes, not characters.
+    lifetimes: bool,
+}
+
+const DEFAULT: Syntax = Syntax {
+    line_comments: &[],
+    line_start_comments: &[],
+    block_comment: None,
+    multiline_strings: &[],
+    quotes: &['"'],
+    raw_quotes: &[],
+    keywords: &[],
+    types: &[],
+    literals: &["false", "true"],
+    case_insensitive: false,
+    capitalized_types: false,
+    variable: None,
+    attributes: &[],
+    preprocessor: false,
+    sections: false,
+    lifetimes: false,
+};
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for",
+    "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static", "struct",
+    "switch", "typedef", "union", "volatile", "while",
+];
+const C_TYPES: &[&str] = &[
+    "bool", "char", "double", "float", "int", "int16_t", "int32_t", "int64_t", "int8_t", "long",
+    "short", "signed", "size_t", "uint16_t", "uint32_t", "uint64_t", "uint8_t", "unsigned", "void",
+];
+
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type", "unsafe",
+        "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "f32", "f64", "i128", "i16", "i32", "i64", "i8", "isize", "str", "u128",
+        "u16", "u32", "u64", "u8", "usize",
+    ],
+    quotes: &['"', '\''],
+    capitalized_types: true,
+    attributes: &["#![", "#["],
+    lifetimes: true,
+    ..DEFAULT
+};
+
+const RON: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    capitalized_types: true,
+    attributes: &["#!["],
+    ..DEFAULT
+};
+
+const C: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    keywords: C_KEYWORDS,
+    types: C_TYPES,
+    literals: &["NULL", "false", "true"],
+    preprocessor: true,
+    ..DEFAULT
+};
+
+const CPP: Syntax = Syntax {
+    keywords: &[
+        "auto",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "constexpr",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "explicit",
+        "extern",
+        "for",
+        "friend",
+        "goto",
+        "if",
+        "inline",
+        "namespace",
+        "new",
+        "noexcept",
+        "operator",
+        "override",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "sizeof",
+        "static",
+        "struct",
+        "switch",
+        "template",
+        "this",
+        "throw",
+        "try",
+        "typedef",
+        "typename",
+        "union",
+        "using",
+        "virtual",
+        "volatile",
+        "while",
+    ],
+    literals: &["NULL", "false", "nullptr", "true"],
+    ..C
+};
+
+const JAVA: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    multiline_strings: &["\"\"\""],
+    quotes: &['"', '\''],
+    keywords: &[
+        "abstract",
+        "assert",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "continue",
+        "default",
+        "do",
+        "else",
+        "enum",
+        "extends",
+        "final",
+        "finally",
+        "for",
+        "if",
+        "implements",
+        "import",
+        "instanceof",
+        "interface",
+        "native",
+        "new",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "record",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "synchronized",
+        "this",
+        "throw",
+        "throws",
+        "try",
+        "var",
+        "volatile",
+        "while",
+        "yield",
+    ],
+    types: &[
+        "boolean", "byte", "char", "double", "float", "int", "long", "short", "void",
+    ],
+    literals: &["false", "null", "true"],
+    capitalized_types: true,
+    attributes: &["@"],
+    ..DEFAULT
+};
+
+const KOTLIN: Syntax = Syntax {
+    keywords: &[
+        "abstract",
+        "as",
+        "break",
+        "by",
+        "class",
+        "companion",
+        "const",
+        "continue",
+        "data",
+        "do",
+        "else",
+        "enum",
+        "final",
+        "for",
+        "fun",
+        "if",
+        "import",
+        "in",
+        "inline",
+        "interface",
+        "internal",
+        "is",
+        "lateinit",
+        "object",
+        "open",
+        "operator",
+        "override",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "sealed",
+        "super",
+        "suspend",
+        "this",
+        "throw",
+        "try",
+        "typealias",
+        "val",
+        "var",
+        "when",
+        "while",
+    ],
+    types: &[],
+    ..JAVA
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    multiline_strings: &["`"],
+    quotes: &['"', '\''],
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "of",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+    literals: &["NaN", "false", "null", "true", "undefined"],
+    ..DEFAULT
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    multiline_strings: &["\"\"\"", "'''"],
+    quotes: &['"', '\''],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def",
+        "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+        "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+        "while", "with", "yield",
+    ],
+    literals: &["False", "None", "True"],
+    attributes: &["@"],
+    ..DEFAULT
+};
+
+const RUBY: Syntax = Syntax {
+    line_comments: &["#"],
+    quotes: &['"'],
+    raw_quotes: &['\''],
+    keywords: &[
+        "alias", "and", "begin", "break", "case", "class", "def", "do", "else", "elsif", "end",
+        "ensure", "for", "if", "in", "module", "next", "not", "or", "redo", "rescue", "retry",
+        "return", "self", "super", "then", "undef", "unless", "until", "when", "while", "yield",
+    ],
+    literals: &["false", "nil", "true"],
+    capitalized_types: true,
+    variable: Some('@'),
+    ..DEFAULT
+};
+
+const SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    raw_quotes: &['\''],
+    keywords: &[
+        "break", "case", "continue", "declare", "do", "done", "elif", "else", "esac", "exit",
+        "export", "fi", "for", "function", "if", "in", "local", "readonly", "return", "select",
+        "shift", "source", "then", "unset", "until", "while",
+    ],
+    variable: Some('$'),
+    ..DEFAULT
+};
+
+const FISH: Syntax = Syntax {
+    keywords: &[
+        "and", "begin", "break", "case", "continue", "else", "end", "for", "function", "if", "in",
+        "not", "or", "return", "set", "switch", "while",
+    ],
+    ..SHELL
+};
+
+const NUSHELL: Syntax = Syntax {
+    keywords: &[
+        "and", "break", "const", "continue", "def", "do", "else", "export", "extern", "for", "if",
+        "in", "let", "loop", "match", "module", "mut", "not", "or", "return", "use", "where",
+        "while",
+    ],
+    literals: &["false", "null", "true"],
+    ..SHELL
+};
+
+const POWERSHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: Some(("<#", "#>")),
+    raw_quotes: &['\''],
+    keywords: &[
+        "begin",
+        "break",
+        "catch",
+        "class",
+        "continue",
+        "data",
+        "do",
+        "dynamicparam",
+        "else",
+        "elseif",
+        "end",
+        "exit",
+        "filter",
+        "finally",
+        "for",
+        "foreach",
+        "function",
+        "if",
+        "in",
+        "param",
+        "process",
+        "return",
+        "switch",
+        "throw",
+        "trap",
+        "try",
+        "until",
+        "using",
+        "while",
+    ],
+    case_insensitive: true,
+    variable: Some('$'),
+    ..DEFAULT
+};
+
+const BATCH: Syntax = Syntax {
+    line_start_comments: &["::", "rem "],
+    keywords: &[
+        "call",
+        "cd",
+        "defined",
+        "do",
+        "echo",
+        "else",
+        "endlocal",
+        "errorlevel",
+        "exist",
+        "exit",
+        "for",
+        "goto",
+        "if",
+        "in",
+        "not",
+        "set",
+        "setlocal",
+    ],
+    literals: &[],
+    case_insensitive: true,
+    variable: Some('%'),
+    ..DEFAULT
+};
+
+const MAKE: Syntax = Syntax {
+    line_comments: &["#"],
+    quotes: &[],
+    keywords: &[
+        "define", "else", "endef", "endif", "export", "ifdef", "ifeq", "ifndef", "ifneq",
+        "include", "override",
+    ],
+    literals: &[],
+    variable: Some('$'),
+    ..DEFAULT
+};
+
+const DOCKERFILE: Syntax = Syntax {
+    keywords: &[
+        "add",
+        "arg",
+        "as",
+        "cmd",
+        "copy",
+        "entrypoint",
+        "env",
+        "expose",
+        "from",
+        "healthcheck",
+        "label",
+        "onbuild",
+        "run",
+        "shell",
+        "stopsignal",
+        "user",
+        "volume",
+        "workdir",
+    ],
+    case_insensitive: true,
+    ..SHELL
+};
+
+const JUST: Syntax = Syntax {
+    line_comments: &["#"],
+    multiline_strings: &["\"\"\"", "'''"],
+    raw_quotes: &['\''],
+    keywords: &["alias", "else", "export", "if", "import", "mod", "set"],
+    ..DEFAULT
+};
+
+const TOML: Syntax = Syntax {
+    line_comments: &["#"],
+    multiline_strings: &["\"\"\"", "'''"],
+    raw_quotes: &['\''],
+    sections: true,
+    ..DEFAULT
+};
+
+const INI: Syntax = Syntax {
+    line_start_comments: &["#", ";"],
+    sections: true,
+    ..DEFAULT
+};
+
+const IGNORE: Syntax = Syntax {
+    line_start_comments: &["#"],
+    quotes: &[],
+    literals: &[],
+    ..DEFAULT
+};
+
+fn syntax(language: &str) -> Option<&'static Syntax> {
+    let syntax = match language {
+        "batch" => &BATCH,
+        "c" => &C,
+        "cpp" => &CPP,
+        "dockerfile" => &DOCKERFILE,
+        "fish" => &FISH,
+        "gitattributes" | "gitignore" => &IGNORE,
+        "ini" => &INI,
+        "java" => &JAVA,
+        "javascript" => &JAVASCRIPT,
+        "just" => &JUST,
+        "kotlin" => &KOTLIN,
+        "make" => &MAKE,
+        "nushell" => &NUSHELL,
+        "powershell" => &POWERSHELL,
+        "python" => &PYTHON,
+        "ron" => &RON,
+        "ruby" => &RUBY,
+        "rust" => &RUST,
+        "shell" => &SHELL,
+        "toml" => &TOML,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+fn is_markup(language: &str) -> bool {
+    matches!(language, "html" | "xml")
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Length of the identifier at the start of `s`.
+fn ident_len(s: &str) -> usize {
+    s.find(|c| !is_ident(c)).unwrap_or(s.len())
+}
+
+fn starts_with(s: &str, prefix: &str, case_insensitive: bool) -> bool {
+    match s.get(..prefix.len()) {
+        Some(start) if case_insensitive => start.eq_ignore_ascii_case(prefix),
+        Some(start) => start == prefix,
+        None => false,
+    }
+}
+
+/// End of the line starting at `start`, without the line break.
+fn line_end(source: &str, start: usize) -> usize {
+    source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i)
+}
+
+/// End of the string starting at `start` with `quote`.
+///
+/// Unterminated strings stop at the end of the line.
+fn string_end(source: &str, start: usize, quote: char, escapes: bool) -> usize {
+    let mut chars = source[start + 1..].char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if escapes => {
+                chars.next();
+            }
+            '\n' => return start + 1 + i,
+            c if c == quote => return start + 1 + i + 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// End of the number starting at `start`, like `0x1F`, `1_000` or `1.5e3`.
+fn number_end(source: &str, start: usize) -> usize {
+    let bytes = source.as_bytes();
+    let mut end = start;
+
+    while end < bytes.len() {
+        let b = bytes[end];
+        let next_is_digit = bytes.get(end + 1).is_some_and(u8::is_ascii_digit);
+
+        if b.is_ascii_alphanumeric() || b == b'_' || (b == b'.' && next_is_digit) {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    end
+}
+
+fn style_of_word(syntax: &Syntax, word: &str) -> Option<Style> {
+    let lowercase;
+    let key = if syntax.case_insensitive {
+        lowercase = word.to_ascii_lowercase();
+        lowercase.as_str()
+    } else {
+        word
+    };
+
+    if syntax.keywords.contains(&key) {
+        Some(Style::Keyword)
+    } else if syntax.types.contains(&key) {
+        Some(Style::Type)
+    } else if syntax.literals.contains(&key) {
+        Some(Style::Literal)
+    } else if syntax.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+        Some(Style::Type)
+    } else {
+        None
+    }
+}
+
+fn tokenize(syntax: &Syntax, source: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut push = |start: usize, end: usize, style: Style| {
+        if start < end {
+            spans.push(Span { start, end, style });
+        }
+    };
+
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+
+        if c == '\n' {
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+
+        let at_line_start = std::mem::replace(&mut line_start, false);
+
+        if at_line_start {
+            if syntax
+                .line_start_comments
+                .iter()
+                .any(|p| starts_with(rest, p, syntax.case_insensitive))
+            {
+                let end = line_end(source, i);
+                push(i, end, Style::Comment);
+                i = end;
+                continue;
+            }
+            if (syntax.preprocessor && c == '#') || (syntax.sections && c == '[') {
+                let end = line_end(source, i);
+                let style = if c == '#' {
+                    Style::Attribute
+                } else {
+                    Style::Tag
+                };
+                push(i, end, style);
+                i = end;
+                continue;
+            }
+        }
+
+        if syntax.line_comments.iter().any(|p| rest.starts_with(p)) {
+            let end = line_end(source, i);
+            push(i, end, Style::Comment);
+            i = end;
+            continue;
+        }
+
+        if let Some((open, close)) = syntax.block_comment
+            && rest.starts_with(open)
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(source.len(), |e| i + open.len() + e + close.len());
+            push(i, end, Style::Comment);
+            i = end;
+            continue;
+        }
+
+        if let Some(delimiter) = syntax
+            .multiline_strings
+            .iter()
+            .find(|d| rest.starts_with(*d))
+        {
+            let end = rest[delimiter.len()..]
+                .find(delimiter)
+                .map_or(source.len(), |e| i + delimiter.len() + e + delimiter.len());
+            push(i, end, Style::String);
+            i = end;
+            continue;
+        }
+
+        if syntax.lifetimes && c == '\'' {
+            let len = ident_len(&rest[1..]);
+            if len > 0 && !rest[1 + len..].starts_with('\'') {
+                push(i, i + 1 + len, Style::Type);
+                i += 1 + len;
+                continue;
+            }
+        }
+
+        if syntax.quotes.contains(&c) || syntax.raw_quotes.contains(&c) {
+            let end = string_end(source, i, c, syntax.quotes.contains(&c));
+            push(i, end, Style::String);
+            i = end;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let end = number_end(source, i);
+            push(i, end, Style::Number);
+            i = end;
+            continue;
+        }
+
+        if syntax.variable == Some(c) {
+            let after = &rest[1..];
+            let len = if after.starts_with('{') {
+                after.find('}').map_or(0, |e| e + 1)
+            } else if after.starts_with(|c: char| "#?@*!$-".contains(c) || c.is_ascii_digit()) {
+                1
+            } else {
+                ident_len(after)
+            };
+
+            if len > 0 {
+                let mut end = i + 1 + len;
+                // %variable% in batch
+                if source[end..].starts_with(c) && c == '%' {
+                    end += 1;
+                }
+                push(i, end, Style::Variable);
+                i = end;
+                continue;
+            }
+        }
+
+        if let Some(prefix) = syntax.attributes.iter().find(|p| rest.starts_with(*p)) {
+            let end = if prefix.ends_with('[') {
+                rest[..line_end(rest, 0)]
+                    .find(']')
+                    .map_or(i + prefix.len(), |e| i + e + 1)
+            } else {
+                i + prefix.len() + ident_len(&rest[prefix.len()..])
+            };
+            push(i, end, Style::Attribute);
+            i = end;
+            continue;
+        }
+
+        if is_ident_start(c) {
+            let end = i + ident_len(rest);
+            let word = &source[i..end];
+
+            let style = style_of_word(syntax, word).or_else(|| {
+                source[end..]
+                    .trim_start_matches([' ', '\t'])
+                    .starts_with('(')
+                    .then_some(Style::Function)
+            });
+            if let Some(style) = style {
+                push(i, end, style);
+            }
+            i = end;
+            continue;
+        }
+
+        i += c.len_utf8();
+    }
+
+    spans
+}
+
+/// Tags, attributes and entities of html and xml.
+fn tokenize_markup(source: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut push = |start: usize, end: usize, style: Style| {
+        if start < end {
+            spans.push(Span { start, end, style });
+        }
+    };
+
+    let mut i = 0;
+
+    while let Some(offset) = source[i..].find(['<', '&']) {
+        i += offset;
+        let rest = &source[i..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment
+                .find("-->")
+                .map_or(source.len(), |e| i + "<!--".len() + e + "-->".len());
+            push(i, end, Style::Comment);
+            i = end;
+            continue;
+        }
+
+        if let Some(entity) = rest.strip_prefix('&') {
+            let len = entity
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+                .unwrap_or(entity.len());
+            if entity[len..].starts_with(';') && len > 0 {
+                push(i, i + len + 2, Style::Literal);
+                i += len + 2;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+
+        // <name, </name, <?xml or <!DOCTYPE
+        let name_start = rest[1..]
+            .find(|c: char| !matches!(c, '/' | '?' | '!'))
+            .map_or(rest.len(), |s| s + 1);
+        let name_len = rest[name_start..]
+            .find(|c: char| !(is_ident(c) || matches!(c, '-' | ':' | '.')))
+            .unwrap_or(rest.len() - name_start);
+        if name_len == 0 {
+            i += 1;
+            continue;
+        }
+        push(i, i + name_start + name_len, Style::Tag);
+        i += name_start + name_len;
+
+        // attributes, until the end of the tag
+        while i < source.len() {
+            let rest = &source[i..];
+            let Some(c) = rest.chars().next() else {
+                break;
+            };
+
+            match c {
+                '>' => {
+                    push(i, i + 1, Style::Tag);
+                    i += 1;
+                    break;
+                }
+                '/' | '?' if rest[1..].starts_with('>') => {
+                    push(i, i + 2, Style::Tag);
+                    i += 2;
+                    break;
+                }
+                '"' | '\'' => {
+                    let end = rest[1..].find(c).map_or(source.len(), |e| i + 1 + e + 1);
+                    push(i, end, Style::String);
+                    i = end;
+                }
+                c if is_ident_start(c) => {
+                    let len = rest
+                        .find(|c: char| !(is_ident(c) || matches!(c, '-' | ':' | '.')))
+                        .unwrap_or(rest.len());
+                    push(i, i + len, Style::Attribute);
+                    i += len;
+                }
+                // a new tag, the previous one is not closed
+                '<' => break,
+                c => i += c.len_utf8(),
+            }
+        }
+    }
+
+    spans
+}
+
+/// Styled spans of `source`, sorted and without overlap.
+///
+/// Empty for the languages without highlighter.
+pub fn highlight(language: &str, source: &str) -> Vec<Span> {
+    if is_markup(language) {
+        return tokenize_markup(source);
+    }
+
+    match syntax(language) {
+        Some(syntax) => tokenize(syntax, source),
+        None => Vec::new(),
+    }
+}
+
+/// Convert the byte offsets of `spans` to utf-16 offsets, the ones of the JVM strings.
+pub fn to_utf16(source: &str, spans: &mut [Span]) {
+    let mut chars = source.char_indices().peekable();
+    let mut utf16 = 0;
+
+    let mut offset = |byte: usize| {
+        while let Some((_, c)) = chars.next_if(|(i, _)| *i < byte) {
+            utf16 += c.len_utf16();
+        }
+        utf16
+    };
+
+    for span in spans {
+        span.start = offset(span.start);
+        span.end = offset(span.end);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn styled<'a>(language: &str, source: &'a str) -> Vec<(&'a str, Style)> {
+        highlight(language, source)
+            .into_iter()
+            .map(|span| (&source[span.start..span.end], span.style))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust() {
+        let source = r#"#[derive(Debug)]
+struct Note<'a> {
+    // the title
+    name: &'a str,
+}
+
+fn main() {
+    let c = 'x';
+    let s = "a \" b"; /* done */
+    println!("{}", 1_000.5);
+}
+"#;
+
+        assert_eq!(
+            styled("rust", source),
+            [
+                ("#[derive(Debug)]", Style::Attribute),
+                ("struct", Style::Keyword),
+                ("Note", Style::Type),
+                ("'a", Style::Type),
+                ("// the title", Style::Comment),
+                ("'a", Style::Type),
+                ("str", Style::Type),
+                ("fn", Style::Keyword),
+                ("main", Style::Function),
+                ("let", Style::Keyword),
+                ("'x'", Style::String),
+                ("let", Style::Keyword),
+                (r#""a \" b""#, Style::String),
+                ("/* done */", Style::Comment),
+                (r#""{}""#, Style::String),
+                ("1_000.5", Style::Number),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell() {
+        let source = "#!/bin/sh\nif [ -z \"$HOME\" ]; then echo '$no' ${PATH} $1; fi # end\n";
+
+        assert_eq!(
+            styled("shell", source),
+            [
+                ("#!/bin/sh", Style::Comment),
+                ("if", Style::Keyword),
+                ("\"$HOME\"", Style::String),
+                ("then", Style::Keyword),
+                ("'$no'", Style::String),
+                ("${PATH}", Style::Variable),
+                ("$1", Style::Variable),
+                ("fi", Style::Keyword),
+                ("# end", Style::Comment),
+            ]
+        );
+
+        // without case, and %variables%
+        assert_eq!(
+            styled("batch", "REM comment\nEcho %NAME%\n"),
+            [
+                ("REM comment", Style::Comment),
+                ("Echo", Style::Keyword),
+                ("%NAME%", Style::Variable),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let source = "# config\n[extensions]\norg = \"markdown\"\nraw = 'C:\\'\nsize = 0x10\n";
+
+        assert_eq!(
+            styled("toml", source),
+            [
+                ("# config", Style::Comment),
+                ("[extensions]", Style::Tag),
+                ("\"markdown\"", Style::String),
+                ("'C:\\'", Style::String),
+                ("0x10", Style::Number),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markup() {
+        let source = "<!-- note -->\n<a href=\"x.md\" hidden>A &amp; B</a><br/>";
+
+        assert_eq!(
+            styled("html", source),
+            [
+                ("<!-- note -->", Style::Comment),
+                ("<a", Style::Tag),
+                ("href", Style::Attribute),
+                ("\"x.md\"", Style::String),
+                ("hidden", Style::Attribute),
+                (">", Style::Tag),
+                ("&amp;", Style::Literal),
+                ("</a", Style::Tag),
+                (">", Style::Tag),
+                ("<br", Style::Tag),
+                ("/>", Style::Tag),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_utf16() {
+        let source = "// é😀\nfn";
+        let mut spans = highlight("rust", source);
+        to_utf16(source, &mut spans);
+
+        assert_eq!(
+            spans,
+            [
+                Span {
+                    start: 0,
+                    end: 6,
+                    style: Style::Comment
+                },
+                Span {
+                    start: 7,
+                    end: 9,
+                    style: Style::Keyword
+                },
+            ]
+        );
+
+        assert!(highlight("brainfuck", "+").is_empty());
+    }
+}
@@ -1,31 +1,117 @@
 use anyhow::anyhow;
 use rand_core::OsRng;
 use ssh_key::{
-    PrivateKey, PublicKey,
-    private::{Ed25519Keypair, KeypairData},
-    public::KeyData,
+    EcdsaCurve, PrivateKey,
+    private::{EcdsaKeypair, Ed25519Keypair, KeypairData, RsaKeypair},
 };
 use zeroize::Zeroizing;
 
+pub const DEFAULT_COMMENT: &str = "GitNote";
+
+// important: must match the order of the KeyAlgorithm enum in kotlin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    Ed25519 = 0,
+    EcdsaP256 = 1,
+    Rsa3072 = 2,
+    Rsa4096 = 3,
+}
+
+impl TryFrom<i32> for KeyAlgorithm {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Ed25519),
+            1 => Ok(Self::EcdsaP256),
+            2 => Ok(Self::Rsa3072),
+            3 => Ok(Self::Rsa4096),
+            other => Err(anyhow!("invalid key algorithm: {other}")),
+        }
+    }
+}
+
 pub struct SshKeys {
     pub public: String,
     pub private: Zeroizing<String>,
 }
 
-pub fn gen_keys() -> anyhow::Result<SshKeys> {
-    let comment = "GitNote";
+/// Generate a new key pair. When `passphrase` is given, the private key is encrypted with it.
+pub fn gen_keys(
+    algorithm: KeyAlgorithm,
+    comment: &str,
+    passphrase: Option<&str>,
+) -> anyhow::Result<SshKeys> {
+    let keypair = match algorithm {
+        KeyAlgorithm::Ed25519 => KeypairData::Ed25519(Ed25519Keypair::random(&mut OsRng)),
+        KeyAlgorithm::EcdsaP256 => KeypairData::Ecdsa(
+            EcdsaKeypair::random(&mut OsRng, EcdsaCurve::NistP256).map_err(|e| anyhow!("{e}"))?,
+        ),
+        KeyAlgorithm::Rsa3072 => {
+            KeypairData::Rsa(RsaKeypair::random(&mut OsRng, 3072).map_err(|e| anyhow!("{e}"))?)
+        }
+        KeyAlgorithm::Rsa4096 => {
+            KeypairData::Rsa(RsaKeypair::random(&mut OsRng, 4096).map_err(|e| anyhow!("{e}"))?)
+        }
+    };
 
-    let pair = Ed25519Keypair::random(&mut OsRng);
+    let mut private_key = PrivateKey::new(keypair, comment).map_err(|e| anyhow!("{e}"))?;
 
-    let private_key: PrivateKey =
-        PrivateKey::new(KeypairData::Ed25519(pair.clone()), comment).map_err(|e| anyhow!("{e}"))?;
+    let public = private_key
+        .public_key()
+        .to_openssh()
+        .map_err(|e| anyhow!("{e}"))?;
 
-    let public_key: PublicKey = PublicKey::new(KeyData::Ed25519(pair.public), comment);
+    if let Some(passphrase) = passphrase {
+        private_key = private_key
+            .encrypt(&mut OsRng, passphrase)
+            .map_err(|e| anyhow!("{e}"))?;
+    }
 
-    let public = public_key.to_openssh().map_err(|e| anyhow!("{e}"))?;
     let private = private_key
         .to_openssh(ssh_key::LineEnding::LF)
         .map_err(|e| anyhow!("{e}"))?;
 
     Ok(SshKeys { public, private })
 }
+
+#[cfg(test)]
+mod test {
+    use ssh_key::PublicKey;
+
+    use super::*;
+
+    fn check(algorithm: KeyAlgorithm, passphrase: Option<&str>) {
+        let keys = gen_keys(algorithm, "me@phone", passphrase).unwrap();
+
+        let public = PublicKey::from_openssh(&keys.public).unwrap();
+        assert_eq!(public.comment(), "me@phone");
+
+        let mut private = PrivateKey::from_openssh(keys.private.as_str()).unwrap();
+        assert_eq!(private.is_encrypted(), passphrase.is_some());
+
+        if let Some(passphrase) = passphrase {
+            private = private.decrypt(passphrase).unwrap();
+        }
+        assert_eq!(private.public_key().key_data(), public.key_data());
+    }
+
+    #[test]
+    fn gen_ed25519() {
+        check(KeyAlgorithm::Ed25519, None);
+        check(KeyAlgorithm::Ed25519, Some("passphrase"));
+    }
+
+    #[test]
+    fn gen_ecdsa() {
+        check(KeyAlgorithm::EcdsaP256, Some("passphrase"));
+    }
+
+    // cargo test gen_rsa --release -- --ignored
+    #[test]
+    #[ignore = "slow without optimizations"]
+    fn gen_rsa() {
+        check(KeyAlgorithm::Rsa3072, None);
+        check(KeyAlgorithm::Rsa4096, Some("passphrase"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+use ssh_key::{HashAlg, LineEnding, PrivateKey, PublicKey};
+use zeroize::Zeroizing;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+static KEY_STORE: LazyLock<Mutex<KeyStore>> = LazyLock::new(|| Mutex::new(KeyStore::new()));
+
+struct StoredKey {
+    public: String,
+    /// Decrypted private key, in the OpenSSH format.
+    private: Zeroizing<String>,
+    expire_at: Instant,
+}
+
+struct KeyStore {
+    keys: HashMap<String, StoredKey>,
+    timeout: Duration,
+}
+
+impl KeyStore {
+    fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    fn remove_expired(&mut self) {
+        let now = Instant::now();
+        self.keys.retain(|_, key| key.expire_at > now);
+    }
+}
+
+/// Decrypt `private_key` with `passphrase` and keep it in memory until the session timeout.
+/// Return the SHA256 fingerprint used to reference the key.
+pub fn add_key(private_key: &str, passphrase: Option<&str>) -> anyhow::Result<String> {
+    let mut key = PrivateKey::from_openssh(private_key).map_err(|e| anyhow!("{e}"))?;
+
+    if key.is_encrypted() {
+        let Some(passphrase) = passphrase else {
+            bail!("the private key is encrypted but no passphrase was given");
+        };
+        key = key.decrypt(passphrase).map_err(|e| anyhow!("{e}"))?;
+    }
+
+    let fingerprint = key.fingerprint(HashAlg::Sha256).to_string();
+    let public = key.public_key().to_openssh().map_err(|e| anyhow!("{e}"))?;
+    let private = key.to_openssh(LineEnding::LF).map_err(|e| anyhow!("{e}"))?;
+
+    let mut store = KEY_STORE.lock().expect("key store lock");
+    store.remove_expired();
+
+    let expire_at = Instant::now() + store.timeout;
+
+    store.keys.insert(
+        fingerprint.clone(),
+        StoredKey {
+            public,
+            private,
+            expire_at,
+        },
+    );
+
+    Ok(fingerprint)
+}
+
+/// Return the public and decrypted private key, if the key is still in the store.
+pub fn get_key(fingerprint: &str) -> Option<(String, Zeroizing<String>)> {
+    let mut store = KEY_STORE.lock().expect("key store lock");
+    store.remove_expired();
+
+    store
+        .keys
+        .get(fingerprint)
+        .map(|key| (key.public.clone(), key.private.clone()))
+}
+
+/// SHA256 fingerprint of `public_key`, the one [`add_key`] returns for its private key.
+pub fn public_key_fingerprint(public_key: &str) -> anyhow::Result<String> {
+    let key = PublicKey::from_openssh(public_key.trim()).map_err(|e| anyhow!("{e}"))?;
+    Ok(key.fingerprint(HashAlg::Sha256).to_string())
+}
+
+pub fn contains_key(fingerprint: &str) -> bool {
+    get_key(fingerprint).is_some()
+}
+
+pub fn remove_key(fingerprint: &str) -> bool {
+    let mut store = KEY_STORE.lock().expect("key store lock");
+    store.keys.remove(fingerprint).is_some()
+}
+
+/// Set the session timeout. Keys already in the store keep their current expiration.
+pub fn set_timeout(timeout: Duration) {
+    let mut store = KEY_STORE.lock().expect("key store lock");
+    store.timeout = timeout;
+}
+
+/// Wipe every key from memory.
+pub fn clear() {
+    let mut store = KEY_STORE.lock().expect("key store lock");
+    store.keys.clear();
+}
+
+#[cfg(test)]
+mod test {
+    use rand_core::OsRng;
+    use ssh_key::{Algorithm, PrivateKey};
+
+    use super::*;
+
+    #[test]
+    fn add_encrypted_key() {
+        let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let encrypted = key
+            .encrypt(&mut OsRng, "passphrase")
+            .unwrap()
+            .to_openssh(LineEnding::LF)
+            .unwrap();
+
+        assert!(add_key(&encrypted, None).is_err());
+        assert!(add_key(&encrypted, Some("wrong")).is_err());
+
+        let fingerprint = add_key(&encrypted, Some("passphrase")).unwrap();
+        assert_eq!(fingerprint, key.fingerprint(HashAlg::Sha256).to_string());
+
+        let public = key.public_key().to_openssh().unwrap();
+        assert_eq!(public_key_fingerprint(&public).unwrap(), fingerprint);
+
+        let (_public, private) = get_key(&fingerprint).unwrap();
+        assert!(
+            !PrivateKey::from_openssh(private.as_str())
+                .unwrap()
+                .is_encrypted()
+        );
+
+        assert!(remove_key(&fingerprint));
+        assert!(!contains_key(&fingerprint));
+    }
+}
@@ -1,20 +1,26 @@
 use std::fmt::{Debug, Display};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::anyhow;
 use git2::Signature;
-use jni::objects::{JClass, JObject, JString, JValue};
-use jni::sys::{jboolean, jint};
+use jni::objects::{JClass, JIntArray, JObject, JString, JValue};
+use jni::sys::{jboolean, jint, jlong};
 use jni::{Env, NativeMethod, jni_sig, jni_str, native_method};
+use zeroize::Zeroizing;
 
-use crate::callback::JniProgressCB;
-use crate::key_gen::gen_keys;
+use crate::callback::{JniProgressCB, JniScanCB};
+use crate::key_gen::{KeyAlgorithm, gen_keys};
 use crate::utils::install_panic_hook;
 
 #[macro_use]
 extern crate log;
 #[macro_use]
 mod utils;
+mod highlight;
 mod key_gen;
+mod key_import;
+mod key_store;
 mod libgit2;
 mod mime_types;
 mod url;
@@ -34,6 +40,10 @@ enum Error {
         error: jni::errors::Error,
         msg: String,
     },
+    Io {
+        error: std::io::Error,
+        msg: String,
+    },
 }
 
 impl From<git2::Error> for Error {
@@ -63,6 +73,13 @@ impl Error {
         }
     }
 
+    fn io(error: std::io::Error, msg: &str) -> Self {
+        Self::Io {
+            error,
+            msg: msg.into(),
+        }
+    }
+
     fn add_message(self, msg1: &str) -> Self {
         match self {
             Error::Git2 { error, msg } => Error::Git2 {
@@ -73,6 +90,10 @@ impl Error {
                 error,
                 msg: format!("{}: {}", msg1, msg),
             },
+            Error::Io { error, msg } => Error::Io {
+                error,
+                msg: format!("{}: {}", msg1, msg),
+            },
         }
     }
 }
@@ -81,7 +102,7 @@ impl From<Error> for jint {
     fn from(value: Error) -> Self {
         match value {
             Error::Git2 { error, .. } => error.raw_code(),
-            Error::Jni { .. } => -1,
+            Error::Jni { .. } | Error::Io { .. } => -1,
         }
     }
 }
@@ -91,6 +112,7 @@ impl Display for Error {
         match self {
             Error::Git2 { error, msg } => write!(f, "{msg}: {error}"),
             Error::Jni { error, msg } => write!(f, "{msg}: {error}"),
+            Error::Io { error, msg } => write!(f, "{msg}: {error}"),
         }
     }
 }
@@ -165,15 +187,198 @@ const _GET_TIMESTAMPS_LIB_METHOD: NativeMethod = native_method! {
     static extern fn get_timestamps_lib(j_map: JObject) -> jint,
 };
 
+const _GET_NOTE_METADATA_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_getNoteMetadataLib",
+    static extern fn get_note_metadata_lib(j_list: JObject) -> jint,
+};
+
+const _SCAN_NOTES_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_scanNotesLib",
+    static extern fn scan_notes_lib(root_path: JString, max_size: jlong, callback: JObject) -> jint,
+};
+
+const _SEARCH_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_searchLib",
+    static extern fn search_lib(j_list: JObject, query: JString, limit: jint, offset: jint) -> jint,
+};
+
+const _SEARCH_HISTORY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_searchHistoryLib",
+    static extern fn search_history_lib(
+        j_list: JObject,
+        pattern: JString,
+        is_regex: jboolean,
+        ignore_case: jboolean,
+        context: jint,
+        max_matches: jint,
+        progress_callback: JObject,
+    ) -> jint,
+};
+
+const _DIFF_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_diffLib",
+    static extern fn diff_lib(
+        j_list: JObject,
+        path: JString,
+        from_side: jint,
+        from_revision: JString,
+        to_side: jint,
+        to_revision: JString,
+        word_diff: jboolean,
+    ) -> jint,
+};
+
+const _BLAME_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_blameLib",
+    static extern fn blame_lib(
+        j_commits: JObject,
+        path: JString,
+        ignore_whitespace: jboolean,
+        oldest_commit: JString,
+    ) -> jint[],
+};
+
+const _LOG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_logLib",
+    static extern fn log_lib(
+        j_list: JObject,
+        after: JString,
+        limit: jint,
+        path_filter: JString,
+        author_filter: JString,
+        since: jlong,
+        until: jlong,
+    ) -> jint,
+};
+
+const _MOVE_PATH_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn move_path_lib(from: JString, to: JString) -> jint,
+};
+
+const _PULL_WITH_STASH_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_pullWithStashLib",
+    static extern fn pull_with_stash_lib(j_conflicts: JObject, cred: JObject, name: JString, email: JString) -> jint,
+};
+
+const _STASH_SAVE_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn stash_save_lib(name: JString, email: JString, message: JString, include_untracked: jboolean) -> jint,
+};
+
+const _STASH_LIST_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_stashListLib",
+    static extern fn stash_list_lib(j_list: JObject) -> jint,
+};
+
+const _STASH_APPLY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_stashApplyLib",
+    static extern fn stash_apply_lib(j_conflicts: JObject, index: jint, pop: jboolean) -> jint,
+};
+
+const _STASH_DROP_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn stash_drop_lib(index: jint) -> jint,
+};
+
+const _CREATE_TAG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn create_tag_lib(name: JString, revision: JString, message: JString, tagger_name: JString, tagger_email: JString, force: jboolean) -> jint,
+};
+
+const _LIST_TAGS_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_listTagsLib",
+    static extern fn list_tags_lib(j_list: JObject) -> jint,
+};
+
+const _DELETE_TAG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn delete_tag_lib(name: JString) -> jint,
+};
+
+const _PUSH_TAGS_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_pushTagsLib",
+    static extern fn push_tags_lib(cred: JObject, name: JString) -> jint,
+};
+
+const _CHECKOUT_TAG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn checkout_tag_lib(name: JString, dest: JString) -> jint,
+};
+
+const _REFLOG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_reflogLib",
+    static extern fn reflog_lib(j_list: JObject, branch: JString) -> jint,
+};
+
+const _RESET_TO_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_resetToLib",
+    static extern fn reset_to_lib(j_backup: JObject, revision: JString, kind: jint, name: JString, email: JString) -> jint,
+};
+
+const _ADD_ATTACHMENT_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_addAttachmentLib",
+    static extern fn add_attachment_lib(j_attachment: JObject, note: JString, source: JString) -> jint,
+};
+
+const _ATTACHMENT_REFERENCES_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_attachmentReferencesLib",
+    static extern fn attachment_references_lib(j_list: JObject, max_size: jlong) -> jint,
+};
+
+const _IS_IGNORED_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn is_ignored_lib(path: JString) -> jint,
+};
+
+const _ADD_IGNORE_PATTERN_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn add_ignore_pattern_lib(pattern: JString) -> jint,
+};
+
+const _REPO_CONFIG_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_repoConfigLib",
+    static extern fn repo_config_lib(j_errors: JObject) -> jint,
+};
+
 const _GENERATE_SSH_KEYS_LIB_METHOD: NativeMethod = native_method! {
     java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
     export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_generateSshKeysLib",
-    static extern fn generate_ssh_keys_lib() -> JObject,
+    static extern fn generate_ssh_keys_lib(algorithm: jint, comment: JString, passphrase: JString) -> JObject,
 };
 
 const _EXTENSION_TYPE_LIB_METHOD: NativeMethod = native_method! {
     java_type = "io.github.wiiznokes.gitnote.manager.MimeTypeManagerKt",
-    static extern fn extension_type_lib(extension: JString) -> jint,
+    export = "Java_io_github_wiiznokes_gitnote_manager_MimeTypeManagerKt_extensionTypeLib",
+    static extern fn extension_type_lib(name: JString, path: JString, j_language: JObject) -> jint,
+};
+
+const _SET_APP_EXTENSIONS_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.MimeTypeManagerKt",
+    static extern fn set_app_extensions_lib(config: JString) -> jint,
+};
+
+const _HIGHLIGHT_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.MimeTypeManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_MimeTypeManagerKt_highlightLib",
+    static extern fn highlight_lib(language: JString, text: JString) -> jint[],
 };
 
 const _IS_EXTENSION_SUPPORTED_LIB_METHOD: NativeMethod = native_method! {
@@ -181,6 +386,53 @@ const _IS_EXTENSION_SUPPORTED_LIB_METHOD: NativeMethod = native_method! {
     static extern fn is_extension_supported_lib(extension: JString) -> jboolean,
 };
 
+const _ADD_SSH_KEY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn add_ssh_key_lib(private_key: JString, passphrase: JString) -> JString,
+};
+
+const _HAS_SSH_KEY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn has_ssh_key_lib(fingerprint: JString) -> jboolean,
+};
+
+const _SSH_KEY_FINGERPRINT_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn ssh_key_fingerprint_lib(public_key: JString) -> JString,
+};
+
+const _REMOVE_SSH_KEY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn remove_ssh_key_lib(fingerprint: JString) -> jboolean,
+};
+
+const _SET_KEY_STORE_TIMEOUT_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn set_key_store_timeout_lib(seconds: jlong),
+};
+
+const _INSPECT_SSH_KEY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.SshKeyManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_SshKeyManagerKt_inspectSshKeyLib",
+    static extern fn inspect_ssh_key_lib(private_key: JString, passphrase: JString, public_key: JString) -> JObject,
+};
+
+const _SET_SIGNING_KEY_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn set_signing_key_lib(fingerprint: JString),
+};
+
+const _SET_SIGNATURE_VERIFICATION_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    static extern fn set_signature_verification_lib(allowed_signers_path: JString, strict: jboolean),
+};
+
+const _VERIFY_COMMITS_LIB_METHOD: NativeMethod = native_method! {
+    java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
+    export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_verifyCommitsLib",
+    static extern fn verify_commits_lib(j_list: JObject, limit: jint) -> jint,
+};
+
 const _GET_URL_INFO_LIB_METHOD: NativeMethod = native_method! {
     java_type = "io.github.wiiznokes.gitnote.manager.GitManagerKt",
     export = "Java_io_github_wiiznokes_gitnote_manager_GitManagerKt_getUrlInfoLib",
@@ -244,6 +496,10 @@ pub enum Cred {
         private_key: String,
         passphrase: Option<String>,
     },
+    /// Reference to a key loaded in the key store.
+    SshKeyRef {
+        fingerprint: String,
+    },
 }
 
 pub struct GitAuthor {
@@ -278,6 +534,10 @@ impl Debug for Cred {
                 .debug_struct("Ssh")
                 .field("public_key", public_key)
                 .finish(),
+            Self::SshKeyRef { fingerprint } => f
+                .debug_struct("SshKeyRef")
+                .field("fingerprint", fingerprint)
+                .finish(),
         }
     }
 }
@@ -312,6 +572,27 @@ macro_rules! jstring_field_nullable {
     }};
 }
 
+fn try_to_string_nullable(
+    env: &mut Env,
+    s: &JString,
+) -> Result<Option<String>, jni::errors::Error> {
+    if s.is_null() {
+        Ok(None)
+    } else {
+        s.try_to_string(env).map(Some)
+    }
+}
+
+fn list_add(env: &mut Env, list: &JObject, obj: &JObject) -> Result<(), jni::errors::Error> {
+    env.call_method(
+        list,
+        jni_str!("add"),
+        jni_sig!((JObject) -> jboolean),
+        &[obj.into()],
+    )?;
+    Ok(())
+}
+
 impl Cred {
     pub fn from_jni(env: &mut Env, cred_obj: &JObject) -> anyhow::Result<Option<Self>> {
         if cred_obj.is_null() {
@@ -349,13 +630,20 @@ impl Cred {
                     passphrase,
                 }))
             }
+            "io.github.wiiznokes.gitnote.ui.model.Cred$SshKeyRef" => {
+                let fingerprint = jstring_field!(env, cred_obj, "fingerprint");
+
+                Ok(Some(Cred::SshKeyRef { fingerprint }))
+            }
             other => Err(anyhow!("Unknown class name: {}", other)),
         }
     }
 }
 
 mod callback {
-    use jni::{Env, jni_sig, jni_str, objects::JObject};
+    use jni::{Env, jni_sig, jni_str, objects::JObject, sys::jlong};
+
+    use crate::{libgit2::scan::ScannedNote, list_add};
 
     pub struct JniProgressCB<'ptr, 'local> {
         env: &'ptr mut Env<'local>,
@@ -400,6 +688,79 @@ mod callback {
     pub trait ProgressCB {
         fn progress(&mut self, progress: i32) -> bool;
     }
+
+    pub struct JniScanCB<'ptr, 'local> {
+        env: &'ptr mut Env<'local>,
+        callback_class: JObject<'local>,
+    }
+
+    impl<'ptr, 'local> JniScanCB<'ptr, 'local> {
+        pub fn new(env: &'ptr mut Env<'local>, callback_class: JObject<'local>) -> Self {
+            Self {
+                env,
+                callback_class,
+            }
+        }
+
+        fn call(
+            &mut self,
+            notes: Vec<ScannedNote>,
+            folders: Vec<String>,
+        ) -> Result<bool, jni::errors::Error> {
+            let env = &mut *self.env;
+
+            let j_notes = env.new_object(jni_str!("java/util/ArrayList"), jni_sig!(()), &[])?;
+            for note in &notes {
+                let j_path = env.new_string(&note.path)?;
+                let j_content = env.new_string(&note.content)?;
+
+                let obj = env.new_object(
+                    jni_str!("io/github/wiiznokes/gitnote/manager/ScannedNote"),
+                    jni_sig!((JString, jlong, JString, jlong)),
+                    &[
+                        (&j_path).into(),
+                        (note.size as jlong).into(),
+                        (&j_content).into(),
+                        note.timestamp.into(),
+                    ],
+                )?;
+                list_add(env, &j_notes, &obj)?;
+                env.delete_local_ref(obj);
+                env.delete_local_ref(j_content);
+                env.delete_local_ref(j_path);
+            }
+
+            let j_folders = env.new_object(jni_str!("java/util/ArrayList"), jni_sig!(()), &[])?;
+            for folder in &folders {
+                let j_folder = env.new_string(folder)?;
+                list_add(env, &j_folders, &j_folder)?;
+                env.delete_local_ref(j_folder);
+            }
+
+            env.call_method(
+                &self.callback_class,
+                jni_str!("scanCb"),
+                jni_sig!((java.util.ArrayList, java.util.ArrayList) -> jboolean),
+                &[(&j_notes).into(), (&j_folders).into()],
+            )?
+            .z()
+        }
+    }
+
+    impl<'ptr, 'local> ScanCB for JniScanCB<'ptr, 'local> {
+        fn batch(&mut self, notes: Vec<ScannedNote>, folders: Vec<String>) -> bool {
+            self.call(notes, folders).unwrap_or_else(|e| {
+                error!("scanCb: {e}");
+                false
+            })
+        }
+    }
+
+    /// Receive the notes and folders found by [`crate::libgit2::scan::scan_notes`].
+    pub trait ScanCB {
+        /// Return `false` to stop the scan.
+        fn batch(&mut self, notes: Vec<ScannedNote>, folders: Vec<String>) -> bool;
+    }
 }
 fn clone_repo_lib<'local>(
     env: &mut Env<'local>,
@@ -513,6 +874,7 @@ fn free_lib<'local>(
     _env: &mut Env<'local>,
     _class: JClass<'local>,
 ) -> Result<(), jni::errors::Error> {
+    key_store::clear();
     Ok(())
 }
 
@@ -521,6 +883,7 @@ fn close_repo_lib<'local>(
     _class: JClass<'local>,
 ) -> Result<(), jni::errors::Error> {
     libgit2::close();
+    key_store::clear();
     Ok(())
 }
 fn is_change_lib<'local>(
@@ -577,58 +940,1083 @@ fn get_timestamps_lib<'local>(
     Ok(OK)
 }
 
-fn generate_ssh_keys_lib<'local>(
+fn get_note_metadata_lib<'local>(
     env: &mut Env<'local>,
     _class: JClass<'local>,
-) -> Result<JObject<'local>, jni::errors::Error> {
-    let keys = match gen_keys() {
-        Ok(keys) => keys,
-        Err(e) => {
-            error!("can't gen keys: {e}");
-            return Ok(JObject::null());
-        }
+    j_list: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    unwrap_or_log!(
+        libgit2::get_note_metadata(|path, metadata| {
+            let j_path = env.new_string(path)?;
+            let j_author_name = env.new_string(&metadata.author_name)?;
+            let j_author_email = env.new_string(&metadata.author_email)?;
+
+            let obj = env.new_object(
+                jni_str!("io/github/wiiznokes/gitnote/manager/NoteMetadata"),
+                jni_sig!((JString, jlong, jlong, JString, JString, jint)),
+                &[
+                    (&j_path).into(),
+                    metadata.created_at.into(),
+                    metadata.last_modified.into(),
+                    (&j_author_name).into(),
+                    (&j_author_email).into(),
+                    (metadata.revisions as jint).into(),
+                ],
+            )?;
+
+            list_add(env, &j_list, &obj)
+        }),
+        "get_note_metadata"
+    );
+
+    Ok(OK)
+}
+
+fn scan_notes_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    root_path: JString<'local>,
+    max_size: jlong,
+    callback: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let root_path = root_path.try_to_string(env)?;
+
+    let cb = JniScanCB::new(env, callback);
+
+    unwrap_or_log!(
+        libgit2::scan_notes(&root_path, max_size as u64, cb),
+        "scan_notes"
+    );
+
+    Ok(OK)
+}
+
+fn search_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    query: JString<'local>,
+    limit: jint,
+    offset: jint,
+) -> Result<jint, jni::errors::Error> {
+    let query = query.try_to_string(env)?;
+
+    let results = unwrap_or_log!(
+        libgit2::search(&query, limit.max(0) as usize, offset.max(0) as usize),
+        "search"
+    );
+
+    for result in results {
+        let j_path = env.new_string(&result.path)?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/SearchResult"),
+            jni_sig!((JString, jdouble)),
+            &[(&j_path).into(), result.score.into()],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search_history_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    pattern: JString<'local>,
+    is_regex: jboolean,
+    ignore_case: jboolean,
+    context: jint,
+    max_matches: jint,
+    progress_callback: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let query = libgit2::history::HistoryQuery {
+        pattern: pattern.try_to_string(env)?,
+        is_regex,
+        ignore_case,
+        context: context.max(0) as usize,
+        max_matches: max_matches.max(0) as usize,
     };
 
-    let public_jstring = env.new_string(&keys.public).unwrap();
-    let private_jstring = env.new_string(&keys.private).unwrap();
+    let cb = JniProgressCB::new(env, progress_callback);
 
-    let pair_class = env.find_class(jni_str!("kotlin/Pair")).unwrap();
+    let matches = unwrap_or_log!(libgit2::search_history(&query, cb), "search_history");
+
+    for m in matches {
+        let j_commit = env.new_string(m.commit.to_string())?;
+        let j_path = env.new_string(&m.path)?;
+        let j_line = env.new_string(&m.line)?;
+        let j_before = env.new_string(&m.context_before)?;
+        let j_after = env.new_string(&m.context_after)?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/HistoryMatch"),
+            jni_sig!((JString, jlong, JString, jint, JString, JString, JString)),
+            &[
+                (&j_commit).into(),
+                m.time.into(),
+                (&j_path).into(),
+                (m.line_number as jint).into(),
+                (&j_line).into(),
+                (&j_before).into(),
+                (&j_after).into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
 
-    let pair_obj = env
-        .new_object(
-            &pair_class,
-            jni_sig!((JObject, JObject)),
-            &[(&public_jstring).into(), (&private_jstring).into()],
-        )
-        .unwrap();
+    Ok(OK)
+}
 
-    Ok(pair_obj)
+#[allow(clippy::too_many_arguments)]
+fn diff_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    path: JString<'local>,
+    from_side: jint,
+    from_revision: JString<'local>,
+    to_side: jint,
+    to_revision: JString<'local>,
+    word_diff: jboolean,
+) -> Result<jint, jni::errors::Error> {
+    let path = path.try_to_string(env)?;
+
+    let from = try_to_string_nullable(env, &from_revision)?;
+    let from = unwrap_or_log!(libgit2::diff::Side::new(from_side, from), "diff");
+    let to = try_to_string_nullable(env, &to_revision)?;
+    let to = unwrap_or_log!(libgit2::diff::Side::new(to_side, to), "diff");
+
+    let hunks = unwrap_or_log!(libgit2::diff(&path, &from, &to, word_diff), "diff");
+
+    for hunk in hunks {
+        let j_lines = env.new_object(jni_str!("java/util/ArrayList"), jni_sig!(()), &[])?;
+
+        for line in hunk.lines {
+            let j_content = env.new_string(&line.content)?;
+
+            let changes = line
+                .changes
+                .iter()
+                .flat_map(|(start, end)| [*start as jint, *end as jint])
+                .collect::<Vec<_>>();
+            let j_changes = env.new_int_array(changes.len())?;
+            j_changes.set_region(env, 0, &changes)?;
+
+            let obj = env.new_object(
+                jni_str!("io/github/wiiznokes/gitnote/manager/DiffLine"),
+                jni_sig!((jint, jint, jint, JString, jint[])),
+                &[
+                    (line.kind as jint).into(),
+                    line.old_line.map_or(-1, |l| l as jint).into(),
+                    line.new_line.map_or(-1, |l| l as jint).into(),
+                    (&j_content).into(),
+                    (&j_changes).into(),
+                ],
+            )?;
+
+            list_add(env, &j_lines, &obj)?;
+        }
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/DiffHunk"),
+            jni_sig!((jint, jint, jint, jint, java.util.ArrayList)),
+            &[
+                (hunk.old_start as jint).into(),
+                (hunk.old_lines as jint).into(),
+                (hunk.new_start as jint).into(),
+                (hunk.new_lines as jint).into(),
+                (&j_lines).into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
 }
 
-fn extension_type_lib<'local>(
+/// Return a `[commit index, original line]` pair for each line, or null on error.
+fn blame_lib<'local>(
     env: &mut Env<'local>,
     _class: JClass<'local>,
-    extension: JString<'local>,
+    j_commits: JObject<'local>,
+    path: JString<'local>,
+    ignore_whitespace: jboolean,
+    oldest_commit: JString<'local>,
+) -> Result<JIntArray<'local>, jni::errors::Error> {
+    let path = path.try_to_string(env)?;
+
+    let options = libgit2::blame::BlameOptions {
+        ignore_whitespace,
+        oldest_commit: try_to_string_nullable(env, &oldest_commit)?,
+    };
+
+    let blame = unwrap_or_log!(
+        libgit2::blame(&path, &options),
+        "blame",
+        Ok(JIntArray::null())
+    );
+
+    for commit in &blame.commits {
+        let j_oid = env.new_string(commit.oid.to_string())?;
+        let j_author_name = env.new_string(&commit.author_name)?;
+        let j_author_email = env.new_string(&commit.author_email)?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/BlameCommit"),
+            jni_sig!((JString, JString, JString, jlong)),
+            &[
+                (&j_oid).into(),
+                (&j_author_name).into(),
+                (&j_author_email).into(),
+                commit.time.into(),
+            ],
+        )?;
+
+        list_add(env, &j_commits, &obj)?;
+    }
+
+    let lines = blame
+        .lines
+        .iter()
+        .flat_map(|line| {
+            [
+                line.commit.map_or(-1, |c| c as jint),
+                line.original_line as jint,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let j_lines = env.new_int_array(lines.len())?;
+    j_lines.set_region(env, 0, &lines)?;
+
+    Ok(j_lines)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn log_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    after: JString<'local>,
+    limit: jint,
+    path_filter: JString<'local>,
+    author_filter: JString<'local>,
+    since: jlong,
+    until: jlong,
 ) -> Result<jint, jni::errors::Error> {
-    let extension = extension.try_to_string(env).unwrap();
+    let after = unwrap_or_log!(
+        try_to_string_nullable(env, &after)?
+            .map(|oid| git2::Oid::from_str(&oid))
+            .transpose()
+            .map_err(|e| Error::git2(e, "after")),
+        "log"
+    );
 
-    let res = match mime_types::extension_type(extension.as_str()) {
-        Some(ext_type) => ext_type as jint,
-        None => 0,
+    let query = libgit2::commit_log::LogQuery {
+        after,
+        limit: limit.max(0) as usize,
+        path: try_to_string_nullable(env, &path_filter)?,
+        author: try_to_string_nullable(env, &author_filter)?,
+        since,
+        until,
     };
 
-    Ok(res)
+    let entries = unwrap_or_log!(libgit2::log(&query), "log");
+
+    for entry in entries {
+        let strings = [
+            entry.oid.to_string(),
+            entry.summary,
+            entry.body,
+            entry.author_name,
+            entry.author_email,
+            entry.committer_name,
+            entry.committer_email,
+        ]
+        .iter()
+        .map(|s| env.new_string(s))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/LogEntry"),
+            jni_sig!((
+                JString, JString, JString, JString, JString, JString, JString, jlong, jint, jint,
+                jint
+            )),
+            &[
+                (&strings[0]).into(),
+                (&strings[1]).into(),
+                (&strings[2]).into(),
+                (&strings[3]).into(),
+                (&strings[4]).into(),
+                (&strings[5]).into(),
+                (&strings[6]).into(),
+                entry.time.into(),
+                (entry.files_changed as jint).into(),
+                (entry.insertions as jint).into(),
+                (entry.deletions as jint).into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
 }
 
-fn is_extension_supported_lib<'local>(
+fn move_path_lib<'local>(
     env: &mut Env<'local>,
     _class: JClass<'local>,
-    extension: JString<'local>,
-) -> Result<jboolean, jni::errors::Error> {
-    let extension = extension.try_to_string(env).unwrap();
+    from: JString<'local>,
+    to: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let from = from.try_to_string(env)?;
+    let to = to.try_to_string(env)?;
 
-    let res = mime_types::is_extension_supported(extension.as_str());
-    Ok(res)
+    unwrap_or_log!(libgit2::move_path(&from, &to), "move_path");
+
+    Ok(OK)
+}
+
+fn new_string_nullable<'local>(
+    env: &mut Env<'local>,
+    s: Option<String>,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    match s {
+        Some(s) => Ok(env.new_string(s)?.into()),
+        None => Ok(JObject::null()),
+    }
+}
+
+fn add_stash_conflicts<'local>(
+    env: &mut Env<'local>,
+    j_conflicts: &JObject<'local>,
+    conflicts: Vec<libgit2::stash::Conflict>,
+) -> Result<(), jni::errors::Error> {
+    for conflict in conflicts {
+        let path = env.new_string(&conflict.path)?;
+        let ancestor = new_string_nullable(env, conflict.ancestor.map(|oid| oid.to_string()))?;
+        let ours = new_string_nullable(env, conflict.ours.map(|oid| oid.to_string()))?;
+        let theirs = new_string_nullable(env, conflict.theirs.map(|oid| oid.to_string()))?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/StashConflict"),
+            jni_sig!((JString, JString, JString, JString)),
+            &[
+                (&path).into(),
+                (&ancestor).into(),
+                (&ours).into(),
+                (&theirs).into(),
+            ],
+        )?;
+
+        list_add(env, j_conflicts, &obj)?;
+    }
+
+    Ok(())
+}
+
+fn pull_with_stash_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_conflicts: JObject<'local>,
+    cred: JObject<'local>,
+    name: JString<'local>,
+    email: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let cred = Cred::from_jni(env, &cred).unwrap();
+    let name: String = name.try_to_string(env)?;
+    let email: String = email.try_to_string(env)?;
+    let author = GitAuthor { name, email };
+
+    let conflicts = unwrap_or_log!(libgit2::pull_with_stash(cred, &author), "pull_with_stash");
+
+    add_stash_conflicts(env, &j_conflicts, conflicts)?;
+
+    Ok(OK)
+}
+
+/// Returns 1 when the changes were stashed, 0 when there was nothing to stash.
+fn stash_save_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    name: JString<'local>,
+    email: JString<'local>,
+    message: JString<'local>,
+    include_untracked: jboolean,
+) -> Result<jint, jni::errors::Error> {
+    let name: String = name.try_to_string(env)?;
+    let email: String = email.try_to_string(env)?;
+    let author = GitAuthor { name, email };
+    let message = try_to_string_nullable(env, &message)?;
+
+    let stashed = unwrap_or_log!(
+        libgit2::stash_save(&author, message.as_deref(), include_untracked),
+        "stash_save"
+    );
+
+    Ok(stashed.is_some() as jint)
+}
+
+fn stash_list_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let stashes = unwrap_or_log!(libgit2::stash_list(), "stash_list");
+
+    for stash in stashes {
+        let message = env.new_string(&stash.message)?;
+        let oid = env.new_string(stash.oid.to_string())?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/StashEntry"),
+            jni_sig!((jint, JString, JString, jlong)),
+            &[
+                (stash.index as jint).into(),
+                (&message).into(),
+                (&oid).into(),
+                stash.time.into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
+}
+
+fn stash_apply_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_conflicts: JObject<'local>,
+    index: jint,
+    pop: jboolean,
+) -> Result<jint, jni::errors::Error> {
+    let index = index.max(0) as usize;
+
+    let conflicts = if pop {
+        unwrap_or_log!(libgit2::stash_pop(index), "stash_pop")
+    } else {
+        unwrap_or_log!(libgit2::stash_apply(index), "stash_apply")
+    };
+
+    add_stash_conflicts(env, &j_conflicts, conflicts)?;
+
+    Ok(OK)
+}
+
+fn stash_drop_lib<'local>(
+    _env: &mut Env<'local>,
+    _class: JClass<'local>,
+    index: jint,
+) -> Result<jint, jni::errors::Error> {
+    unwrap_or_log!(libgit2::stash_drop(index.max(0) as usize), "stash_drop");
+
+    Ok(OK)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_tag_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    name: JString<'local>,
+    revision: JString<'local>,
+    message: JString<'local>,
+    tagger_name: JString<'local>,
+    tagger_email: JString<'local>,
+    force: jboolean,
+) -> Result<jint, jni::errors::Error> {
+    let name = name.try_to_string(env)?;
+    let revision = revision.try_to_string(env)?;
+    let message = try_to_string_nullable(env, &message)?;
+    let tagger = GitAuthor {
+        name: tagger_name.try_to_string(env)?,
+        email: tagger_email.try_to_string(env)?,
+    };
+
+    unwrap_or_log!(
+        libgit2::create_tag(&name, &revision, message.as_deref(), &tagger, force),
+        "create_tag"
+    );
+
+    Ok(OK)
+}
+
+fn list_tags_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let tags = unwrap_or_log!(libgit2::list_tags(), "list_tags");
+
+    for tag in tags {
+        let strings = [
+            tag.name,
+            tag.target.to_string(),
+            tag.message,
+            tag.tagger_name,
+            tag.tagger_email,
+        ]
+        .iter()
+        .map(|s| env.new_string(s))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/TagInfo"),
+            jni_sig!((
+                JString, JString, jboolean, JString, jboolean, JString, JString, jlong
+            )),
+            &[
+                (&strings[0]).into(),
+                (&strings[1]).into(),
+                tag.annotated.into(),
+                (&strings[2]).into(),
+                tag.signed.into(),
+                (&strings[3]).into(),
+                (&strings[4]).into(),
+                tag.time.into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
+}
+
+fn delete_tag_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    name: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let name = name.try_to_string(env)?;
+
+    unwrap_or_log!(libgit2::delete_tag(&name), "delete_tag");
+
+    Ok(OK)
+}
+
+/// Push the tag `name`, or all the tags when it is null.
+fn push_tags_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    cred: JObject<'local>,
+    name: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let cred = Cred::from_jni(env, &cred).unwrap();
+    let names = try_to_string_nullable(env, &name)?
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    unwrap_or_log!(libgit2::push_tags(cred, &names), "push_tags");
+
+    Ok(OK)
+}
+
+fn checkout_tag_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    name: JString<'local>,
+    dest: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let name = name.try_to_string(env)?;
+    let dest = dest.try_to_string(env)?;
+
+    unwrap_or_log!(libgit2::checkout_tag(&name, &dest), "checkout_tag");
+
+    Ok(OK)
+}
+
+fn reflog_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    branch: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let branch = try_to_string_nullable(env, &branch)?;
+
+    let entries = unwrap_or_log!(libgit2::reflog(branch.as_deref()), "reflog");
+
+    for entry in entries {
+        let strings = [
+            entry.old.to_string(),
+            entry.new.to_string(),
+            entry.message,
+            entry.committer_name,
+            entry.committer_email,
+        ]
+        .iter()
+        .map(|s| env.new_string(s))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/ReflogEntry"),
+            jni_sig!((JString, JString, JString, JString, JString, jlong)),
+            &[
+                (&strings[0]).into(),
+                (&strings[1]).into(),
+                (&strings[2]).into(),
+                (&strings[3]).into(),
+                (&strings[4]).into(),
+                entry.time.into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
+}
+
+/// The backup ref created before a hard reset is added to `j_backup`.
+fn reset_to_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_backup: JObject<'local>,
+    revision: JString<'local>,
+    kind: jint,
+    name: JString<'local>,
+    email: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let kind = match libgit2::reset::ResetKind::try_from(kind) {
+        Ok(kind) => kind,
+        Err(e) => {
+            error!("{e}");
+            return Ok(-1);
+        }
+    };
+    let revision = revision.try_to_string(env)?;
+    let author = GitAuthor {
+        name: name.try_to_string(env)?,
+        email: email.try_to_string(env)?,
+    };
+
+    let backup = unwrap_or_log!(libgit2::reset_to(&revision, kind, &author), "reset_to");
+
+    if let Some(backup) = backup {
+        let backup = env.new_string(backup)?;
+        list_add(env, &j_backup, &backup)?;
+    }
+
+    Ok(OK)
+}
+
+fn add_attachment_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_attachment: JObject<'local>,
+    note: JString<'local>,
+    source: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let note = note.try_to_string(env)?;
+    let source = source.try_to_string(env)?;
+
+    let attachment = unwrap_or_log!(
+        libgit2::add_attachment(&note, Path::new(&source)),
+        "add_attachment"
+    );
+
+    let path = env.new_string(attachment.path)?;
+    let link = env.new_string(attachment.link)?;
+
+    let obj = env.new_object(
+        jni_str!("io/github/wiiznokes/gitnote/manager/Attachment"),
+        jni_sig!((JString, JString)),
+        &[(&path).into(), (&link).into()],
+    )?;
+
+    list_add(env, &j_attachment, &obj)?;
+
+    Ok(OK)
+}
+
+fn attachment_references_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    max_size: jlong,
+) -> Result<jint, jni::errors::Error> {
+    let references = unwrap_or_log!(
+        libgit2::attachment_references(max_size as u64),
+        "attachment_references"
+    );
+
+    for reference in references {
+        let note = env.new_string(reference.note)?;
+        let attachment = env.new_string(reference.attachment)?;
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/AttachmentReference"),
+            jni_sig!((JString, JString, jboolean)),
+            &[
+                (&note).into(),
+                (&attachment).into(),
+                reference.exists.into(),
+            ],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
+}
+
+fn generate_ssh_keys_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    algorithm: jint,
+    comment: JString<'local>,
+    passphrase: JString<'local>,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    let algorithm = match KeyAlgorithm::try_from(algorithm) {
+        Ok(algorithm) => algorithm,
+        Err(e) => {
+            error!("{e}");
+            return Ok(JObject::null());
+        }
+    };
+    let comment = try_to_string_nullable(env, &comment)?
+        .unwrap_or_else(|| key_gen::DEFAULT_COMMENT.to_string());
+    let passphrase = try_to_string_nullable(env, &passphrase)?.map(Zeroizing::new);
+
+    let keys = match gen_keys(
+        algorithm,
+        &comment,
+        passphrase.as_deref().map(|p| p.as_str()),
+    ) {
+        Ok(keys) => keys,
+        Err(e) => {
+            error!("can't gen keys: {e}");
+            return Ok(JObject::null());
+        }
+    };
+
+    let public_jstring = env.new_string(&keys.public).unwrap();
+    let private_jstring = env.new_string(&keys.private).unwrap();
+
+    let pair_class = env.find_class(jni_str!("kotlin/Pair")).unwrap();
+
+    let pair_obj = env
+        .new_object(
+            &pair_class,
+            jni_sig!((JObject, JObject)),
+            &[(&public_jstring).into(), (&private_jstring).into()],
+        )
+        .unwrap();
+
+    Ok(pair_obj)
+}
+
+/// Return 1 when `path` is ignored, 0 otherwise.
+fn is_ignored_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    path: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let path = path.try_to_string(env)?;
+
+    let ignored = unwrap_or_log!(libgit2::is_ignored(&path), "is_ignored");
+
+    Ok(ignored as jint)
+}
+
+fn add_ignore_pattern_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    pattern: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let pattern = pattern.try_to_string(env)?;
+
+    unwrap_or_log!(
+        libgit2::add_ignore_patterns(&[pattern]),
+        "add_ignore_patterns"
+    );
+
+    Ok(OK)
+}
+
+/// Fill `j_errors` with the errors of the configuration file of the repo,
+/// and return its `sync.stash`: 0 when unset, 1 for false and 2 for true.
+fn repo_config_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_errors: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    for error in libgit2::config_errors() {
+        let error = env.new_string(error)?;
+        list_add(env, &j_errors, &error)?;
+    }
+
+    let stash = match libgit2::repo_config().stash_on_sync {
+        None => 0,
+        Some(false) => 1,
+        Some(true) => 2,
+    };
+
+    Ok(stash)
+}
+
+fn extension_type_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    name: JString<'local>,
+    path: JString<'local>,
+    j_language: JObject<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let name = name.try_to_string(env)?;
+    let path = try_to_string_nullable(env, &path)?;
+
+    // without the content, only the name is used
+    let head = match path {
+        Some(path) => mime_types::sniff(Path::new(&path)).unwrap_or_else(|e| {
+            warn!("can't read {path}: {e}");
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+
+    let Some(file_type) = mime_types::classify(&name, &head) else {
+        return Ok(0);
+    };
+
+    if let Some(language) = file_type.language {
+        let language = env.new_string(language)?;
+        list_add(env, &j_language, &language)?;
+    }
+
+    Ok(file_type.kind as jint)
+}
+
+fn set_app_extensions_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    config: JString<'local>,
+) -> Result<jint, jni::errors::Error> {
+    let config = try_to_string_nullable(env, &config)?.unwrap_or_default();
+
+    let extensions = match config
+        .parse::<toml::Table>()
+        .map_err(|e| e.message().to_string())
+        .and_then(|c| mime_types::parse_extensions(&c))
+    {
+        Ok(extensions) => extensions,
+        Err(e) => {
+            error!("app extensions: {e}");
+            return Ok(-1);
+        }
+    };
+
+    mime_types::set_app_extensions(extensions);
+
+    Ok(OK)
+}
+
+/// Return a `[start, end, style]` triple for each span, in utf-16 offsets.
+fn highlight_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    language: JString<'local>,
+    text: JString<'local>,
+) -> Result<JIntArray<'local>, jni::errors::Error> {
+    let language = language.try_to_string(env)?;
+    let text = text.try_to_string(env)?;
+
+    let mut spans = highlight::highlight(&language, &text);
+    highlight::to_utf16(&text, &mut spans);
+
+    let spans = spans
+        .iter()
+        .flat_map(|span| [span.start as jint, span.end as jint, span.style as jint])
+        .collect::<Vec<_>>();
+
+    let j_spans = env.new_int_array(spans.len())?;
+    j_spans.set_region(env, 0, &spans)?;
+
+    Ok(j_spans)
+}
+
+fn is_extension_supported_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    extension: JString<'local>,
+) -> Result<jboolean, jni::errors::Error> {
+    let extension = extension.try_to_string(env).unwrap();
+
+    let res = mime_types::is_extension_supported(extension.as_str());
+    Ok(res)
+}
+
+fn add_ssh_key_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    private_key: JString<'local>,
+    passphrase: JString<'local>,
+) -> Result<JString<'local>, jni::errors::Error> {
+    let private_key = Zeroizing::new(private_key.try_to_string(env)?);
+    let passphrase = try_to_string_nullable(env, &passphrase)?.map(Zeroizing::new);
+
+    let fingerprint =
+        match key_store::add_key(&private_key, passphrase.as_deref().map(|p| p.as_str())) {
+            Ok(fingerprint) => fingerprint,
+            Err(e) => {
+                error!("add_ssh_key: {e}");
+                return Ok(JString::null());
+            }
+        };
+
+    env.new_string(fingerprint)
+}
+
+fn has_ssh_key_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    fingerprint: JString<'local>,
+) -> Result<jboolean, jni::errors::Error> {
+    let fingerprint = fingerprint.try_to_string(env)?;
+
+    Ok(key_store::contains_key(&fingerprint))
+}
+
+fn ssh_key_fingerprint_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    public_key: JString<'local>,
+) -> Result<JString<'local>, jni::errors::Error> {
+    let public_key = public_key.try_to_string(env)?;
+
+    match key_store::public_key_fingerprint(&public_key) {
+        Ok(fingerprint) => env.new_string(fingerprint),
+        Err(e) => {
+            error!("ssh_key_fingerprint: {e}");
+            Ok(JString::null())
+        }
+    }
+}
+
+fn remove_ssh_key_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    fingerprint: JString<'local>,
+) -> Result<jboolean, jni::errors::Error> {
+    let fingerprint = fingerprint.try_to_string(env)?;
+
+    Ok(key_store::remove_key(&fingerprint))
+}
+
+fn set_key_store_timeout_lib<'local>(
+    _env: &mut Env<'local>,
+    _class: JClass<'local>,
+    seconds: jlong,
+) -> Result<(), jni::errors::Error> {
+    key_store::set_timeout(Duration::from_secs(seconds.max(0) as u64));
+    Ok(())
+}
+
+fn inspect_ssh_key_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    private_key: JString<'local>,
+    passphrase: JString<'local>,
+    public_key: JString<'local>,
+) -> Result<JObject<'local>, jni::errors::Error> {
+    let private_key = Zeroizing::new(private_key.try_to_string(env)?);
+    let passphrase = try_to_string_nullable(env, &passphrase)?.map(Zeroizing::new);
+    let public_key = try_to_string_nullable(env, &public_key)?;
+
+    let res = key_import::validate_private_key(
+        &private_key,
+        passphrase.as_deref().map(|p| p.as_str()),
+        public_key.as_deref(),
+    );
+
+    let (error, message, info) = match res {
+        Ok(info) => (OK, String::new(), Some(info)),
+        Err(e) => {
+            warn!("inspect_ssh_key: {e}");
+            (e.code(), e.to_string(), None)
+        }
+    };
+
+    let message = env.new_string(message)?;
+    let algorithm = env.new_string(info.as_ref().map_or("", |i| &i.algorithm))?;
+    let fingerprint = env.new_string(info.as_ref().map_or("", |i| &i.fingerprint))?;
+    let comment = env.new_string(info.as_ref().map_or("", |i| &i.comment))?;
+    let public_key = env.new_string(info.as_ref().map_or("", |i| &i.public_key))?;
+    let encrypted = info.as_ref().is_some_and(|i| i.encrypted);
+
+    env.new_object(
+        jni_str!("io/github/wiiznokes/gitnote/manager/SshKeyInfo"),
+        jni_sig!((jint, JString, JString, JString, JString, jboolean, JString)),
+        &[
+            error.into(),
+            (&message).into(),
+            (&algorithm).into(),
+            (&fingerprint).into(),
+            (&comment).into(),
+            encrypted.into(),
+            (&public_key).into(),
+        ],
+    )
+}
+
+fn set_signing_key_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    fingerprint: JString<'local>,
+) -> Result<(), jni::errors::Error> {
+    let fingerprint = try_to_string_nullable(env, &fingerprint)?;
+    libgit2::sign::set_signing_key(fingerprint);
+    Ok(())
+}
+
+fn set_signature_verification_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    allowed_signers_path: JString<'local>,
+    strict: jboolean,
+) -> Result<(), jni::errors::Error> {
+    let allowed_signers_path = try_to_string_nullable(env, &allowed_signers_path)?;
+    libgit2::verify::set_verification(allowed_signers_path.map(PathBuf::from), strict);
+    Ok(())
+}
+
+fn verify_commits_lib<'local>(
+    env: &mut Env<'local>,
+    _class: JClass<'local>,
+    j_list: JObject<'local>,
+    limit: jint,
+) -> Result<jint, jni::errors::Error> {
+    let commits = unwrap_or_log!(
+        libgit2::verify_commits(limit.max(0) as usize),
+        "verify_commits"
+    );
+
+    for (oid, status) in commits {
+        let j_oid = env.new_string(oid.to_string())?;
+        let j_principal = match &status {
+            libgit2::verify::SignatureStatus::Good { principal } => {
+                JObject::from(env.new_string(principal)?)
+            }
+            _ => JObject::null(),
+        };
+
+        let obj = env.new_object(
+            jni_str!("io/github/wiiznokes/gitnote/manager/CommitSignature"),
+            jni_sig!((JString, jint, JString)),
+            &[(&j_oid).into(), status.code().into(), (&j_principal).into()],
+        )?;
+
+        list_add(env, &j_list, &obj)?;
+    }
+
+    Ok(OK)
 }
 
 fn get_url_info_lib<'local>(
@@ -0,0 +1,227 @@
+//! Images and other binary files stored next to the notes, and the links to them.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use git2::Repository;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    Error,
+    mime_types::{self, ExtensionType},
+};
+
+use super::{rename, scan};
+
+/// Folder of the attachments, next to the note.
+pub const ATTACHMENTS_DIR: &str = "attachments";
+/// Length of the hash in the file names, in hex characters.
+const NAME_HASH_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Relative to the root of the repo.
+    pub path: String,
+    /// Relative to the note, to use in its links.
+    pub link: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Reference {
+    pub note: String,
+    /// Relative to the root of the repo.
+    pub attachment: String,
+    /// The link is broken otherwise.
+    pub exists: bool,
+}
+
+fn error(msg: &str) -> Error {
+    Error::git2(git2::Error::from_str(msg), "attachment")
+}
+
+/// `/` separated, whatever the platform.
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Store `content` next to `note`, in [`ATTACHMENTS_DIR`].
+///
+/// The file is named after the hash of its content, so adding
+/// the same file twice reuses the first copy.
+pub fn add(
+    workdir: &Path,
+    note: &str,
+    extension: &str,
+    content: &[u8],
+) -> Result<Attachment, Error> {
+    let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+
+    if !mime_types::is_attachment(&extension) {
+        return Err(error(&format!("not an attachment extension: {extension}")));
+    }
+
+    if !rename::is_in_worktree(note) {
+        return Err(error(&format!("not in the worktree: {note}")));
+    }
+
+    let hash = Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let name = format!("{}.{extension}", &hash[..NAME_HASH_LEN]);
+
+    let dir = Path::new(note)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(ATTACHMENTS_DIR);
+    let path = dir.join(&name);
+    let full_path = workdir.join(&path);
+
+    let exists = fs::metadata(&full_path).is_ok_and(|m| m.len() == content.len() as u64);
+
+    if !exists {
+        let full_dir = workdir.join(&dir);
+        fs::create_dir_all(&full_dir).map_err(|e| Error::io(e, &full_dir.to_string_lossy()))?;
+
+        // never leave a truncated attachment
+        let tmp = full_path.with_extension("tmp");
+        fs::write(&tmp, content).map_err(|e| Error::io(e, &tmp.to_string_lossy()))?;
+        fs::rename(&tmp, &full_path).map_err(|e| Error::io(e, &full_path.to_string_lossy()))?;
+    }
+
+    Ok(Attachment {
+        path: to_slash(&path),
+        link: format!("{ATTACHMENTS_DIR}/{name}"),
+    })
+}
+
+static INLINE_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\]\(\s*(<[^>\n]*>|[^)\s]+)").expect("inline link regex"));
+static REFERENCE_DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^ {0,3}\[[^\]\n]+\]:\s*(<[^>\n]*>|\S+)").expect("reference definition regex")
+});
+static HTML_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<(?:img|a|video|audio|source)\b[^>]*?\b(?:src|href)\s*=\s*["']([^"']+)["']"#)
+        .expect("html link regex")
+});
+
+/// Destinations of the links and images of a markdown note.
+pub fn links(content: &str) -> Vec<String> {
+    [&INLINE_LINK, &REFERENCE_DEFINITION, &HTML_LINK]
+        .iter()
+        .flat_map(|regex| regex.captures_iter(content))
+        .map(|c| {
+            c[1].trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Path relative to the root of the repo of the link `link` of `note`,
+/// `None` for the urls and the links escaping the repo.
+pub fn resolve(note: &str, link: &str) -> Option<String> {
+    // urls, and `mailto:` like schemes
+    if link.contains("://") || link.split_once(':').is_some_and(|(s, _)| !s.contains('/')) {
+        return None;
+    }
+
+    let link = link.split(['#', '?']).next()?;
+    if link.is_empty() {
+        return None;
+    }
+    let link = percent_decode(link);
+
+    let joined = match link.strip_prefix('/') {
+        // relative to the root of the repo
+        Some(link) => PathBuf::from(link),
+        None => Path::new(note).parent().unwrap_or(Path::new("")).join(link),
+    };
+
+    let mut path = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::Normal(c) => path.push(c),
+            Component::ParentDir if !path.pop() => return None,
+            _ => {}
+        }
+    }
+
+    Some(to_slash(&path))
+}
+
+/// Attachments referenced by each markdown note of the worktree.
+pub fn references(repo: &Repository, max_size: u64) -> Result<Vec<Reference>, Error> {
+    let workdir = repo.workdir().ok_or_else(|| error("bare repository"))?;
+
+    let (_, files) = scan::collect(repo, workdir, max_size)?;
+
+    let mut references = BTreeSet::new();
+
+    for file in files {
+        let extension = Path::new(&file.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        if mime_types::extension_type(extension) != Some(ExtensionType::Markdown) {
+            continue;
+        }
+
+        let content = fs::read(&file.full_path)
+            .map_err(|e| Error::io(e, &file.full_path.to_string_lossy()))?;
+        let content = String::from_utf8_lossy(&content);
+
+        for link in links(&content) {
+            let Some(attachment) = resolve(&file.path, &link) else {
+                continue;
+            };
+
+            let extension = Path::new(&attachment)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            if !mime_types::is_attachment(&extension) {
+                continue;
+            }
+
+            references.insert(Reference {
+                note: file.path.clone(),
+                exists: workdir.join(&attachment).is_file(),
+                attachment,
+            });
+        }
+    }
+
+    Ok(references.into_iter().collect())
+}
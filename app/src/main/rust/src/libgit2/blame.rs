@@ -0,0 +1,112 @@
+//! Which commit last modified each line of a note.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use git2::{Oid, Repository};
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameCommit {
+    pub oid: Oid,
+    pub author_name: String,
+    pub author_email: String,
+    /// Author time, in milliseconds.
+    pub time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Index in [`Blame::commits`], `None` for a line which is not committed yet.
+    pub commit: Option<usize>,
+    /// Line number in the commit, starting at 1.
+    pub original_line: usize,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Blame {
+    /// Each commit is present once, shared by its lines.
+    pub commits: Vec<BlameCommit>,
+    pub lines: Vec<BlameLine>,
+}
+
+#[derive(Default)]
+pub struct BlameOptions {
+    pub ignore_whitespace: bool,
+    /// Lines older than this revision are attributed to it.
+    pub oldest_commit: Option<String>,
+}
+
+/// Blame of the working file of `path`, or of its version at HEAD when it is deleted.
+pub fn blame(repo: &Repository, path: &str, options: &BlameOptions) -> Result<Blame, Error> {
+    let mut opts = git2::BlameOptions::new();
+    opts.ignore_whitespace(options.ignore_whitespace);
+
+    if let Some(revision) = &options.oldest_commit {
+        opts.oldest_commit(repo.revparse_single(revision)?.peel_to_commit()?.id());
+    }
+
+    let committed = repo
+        .blame_file(Path::new(path), Some(&mut opts))
+        .map_err(|e| Error::git2(e, "blame"))?;
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+
+    // attribute the uncommitted lines to no commit
+    let blame = match fs::read(workdir.join(path)) {
+        Ok(content) => committed.blame_buffer(&content)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => committed,
+        Err(e) => {
+            return Err(Error::io(e, "read worktree"));
+        }
+    };
+
+    let mut result = Blame::default();
+    let mut indexes: HashMap<Oid, usize> = HashMap::new();
+
+    for hunk in blame.iter() {
+        let oid = hunk.final_commit_id();
+
+        let commit = if oid.is_zero() {
+            None
+        } else {
+            let index = match indexes.get(&oid) {
+                Some(index) => *index,
+                None => {
+                    let commit = match hunk.final_signature() {
+                        Some(signature) => BlameCommit {
+                            oid,
+                            author_name: String::from_utf8_lossy(signature.name_bytes()).into(),
+                            author_email: String::from_utf8_lossy(signature.email_bytes()).into(),
+                            time: signature.when().seconds() * 1000,
+                        },
+                        None => BlameCommit {
+                            oid,
+                            author_name: String::new(),
+                            author_email: String::new(),
+                            time: 0,
+                        },
+                    };
+
+                    result.commits.push(commit);
+                    indexes.insert(oid, result.commits.len() - 1);
+                    result.commits.len() - 1
+                }
+            };
+            Some(index)
+        };
+
+        let start = hunk.orig_start_line();
+
+        for i in 0..hunk.lines_in_hunk() {
+            result.lines.push(BlameLine {
+                commit,
+                original_line: start + i,
+            });
+        }
+    }
+
+    Ok(result)
+}
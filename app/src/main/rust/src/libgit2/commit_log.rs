@@ -0,0 +1,226 @@
+//! Paged list of the commits reachable from HEAD.
+
+use std::path::Path;
+
+use git2::{Commit, DiffOptions, Oid, Repository};
+
+use crate::Error;
+
+use super::rename::renames;
+
+pub struct LogQuery {
+    /// Last commit of the previous page, the log resumes after it.
+    pub after: Option<Oid>,
+    pub limit: usize,
+    /// Only the commits modifying this file or folder.
+    pub path: Option<String>,
+    /// Case insensitive part of the author name or email.
+    pub author: Option<String>,
+    /// Bounds of the commit time, in milliseconds, inclusive.
+    pub since: i64,
+    pub until: i64,
+}
+
+impl Default for LogQuery {
+    fn default() -> Self {
+        Self {
+            after: None,
+            limit: 50,
+            path: None,
+            author: None,
+            since: i64::MIN,
+            until: i64::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub oid: Oid,
+    pub summary: String,
+    /// The message without the summary.
+    pub body: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    /// Commit time, in milliseconds.
+    pub time: i64,
+    /// Compared to the first parent.
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Like `git log -- <path>`: the commit is skipped if it is TREESAME to one of its parents.
+fn modifies(repo: &Repository, commit: &Commit, path: &str) -> Result<bool, git2::Error> {
+    let tree = commit.tree()?;
+    let mut options = DiffOptions::new();
+    options.pathspec(path);
+
+    if commit.parent_count() == 0 {
+        let diff = repo.diff_tree_to_tree(None, Some(&tree), Some(&mut options))?;
+        return Ok(diff.deltas().len() > 0);
+    }
+
+    for parent in commit.parents() {
+        let diff =
+            repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&tree), Some(&mut options))?;
+        if diff.deltas().len() == 0 {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn matches(
+    repo: &Repository,
+    commit: &Commit,
+    query: &LogQuery,
+    path: Option<&str>,
+) -> Result<bool, git2::Error> {
+    let time = commit.time().seconds() * 1000;
+    if time < query.since || time > query.until {
+        return Ok(false);
+    }
+
+    if let Some(author) = &query.author {
+        let author = author.to_lowercase();
+        let signature = commit.author();
+
+        if !lossy(signature.name_bytes())
+            .to_lowercase()
+            .contains(&author)
+            && !lossy(signature.email_bytes())
+                .to_lowercase()
+                .contains(&author)
+        {
+            return Ok(false);
+        }
+    }
+
+    match path {
+        Some(path) => modifies(repo, commit, path),
+        None => Ok(true),
+    }
+}
+
+fn entry(repo: &Repository, commit: &Commit) -> Result<LogEntry, git2::Error> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let stats = diff.stats()?;
+
+    let message = lossy(commit.message_bytes());
+    let (summary, body) = match message.split_once('\n') {
+        Some((summary, body)) => (summary.trim_end(), body.trim()),
+        None => (message.trim_end(), ""),
+    };
+
+    let author = commit.author();
+    let committer = commit.committer();
+
+    Ok(LogEntry {
+        oid: commit.id(),
+        summary: summary.to_string(),
+        body: body.to_string(),
+        author_name: lossy(author.name_bytes()),
+        author_email: lossy(author.email_bytes()),
+        committer_name: lossy(committer.name_bytes()),
+        committer_email: lossy(committer.email_bytes()),
+        time: commit.time().seconds() * 1000,
+        files_changed: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+    })
+}
+
+/// Previous path of the file `path`, if `commit` renamed it, like `git log --follow`.
+fn followed_path(
+    repo: &Repository,
+    commit: &Commit,
+    path: &str,
+) -> Result<Option<String>, git2::Error> {
+    let Ok(parent) = commit.parent(0) else {
+        return Ok(None);
+    };
+    let parent_tree = parent.tree()?;
+
+    if parent_tree.get_path(Path::new(path)).is_ok() {
+        return Ok(None);
+    }
+
+    let tree = commit.tree()?;
+    match tree.get_path(Path::new(path)) {
+        Ok(entry) if entry.kind() == Some(git2::ObjectType::Blob) => {}
+        // folders are not followed
+        _ => return Ok(None),
+    }
+
+    Ok(renames(repo, &parent_tree, &tree)?
+        .remove(path)
+        .map(|rename| rename.from))
+}
+
+/// Commits reachable from HEAD matching `query`, newest first.
+///
+/// A file of the path filter is followed through its renames.
+/// The commits before [`LogQuery::after`] are not matched against the query,
+/// but with a path filter, they are still checked for a rename of the file.
+pub fn log(repo: &Repository, query: &LogQuery) -> Result<Vec<LogEntry>, Error> {
+    let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+        Ok(commit) => commit.id(),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => return Ok(Vec::new()),
+        Err(e) => return Err(Error::git2(e, "head")),
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+    let mut entries = Vec::new();
+    let mut path = query.path.clone();
+    let mut resumed = query.after.is_none();
+
+    for oid in revwalk {
+        if entries.len() >= query.limit {
+            break;
+        }
+
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+
+        let is_match = resumed && matches(repo, &commit, query, path.as_deref())?;
+
+        if let Some(current) = &path
+            && let Some(previous) = followed_path(repo, &commit, current)?
+        {
+            path = Some(previous);
+        }
+
+        if !resumed {
+            resumed = query.after == Some(oid);
+            continue;
+        }
+
+        if is_match {
+            entries.push(entry(repo, &commit)?);
+        }
+    }
+
+    if !resumed {
+        return Err(Error::git2(
+            git2::Error::from_str("the last commit of the page is not in the log anymore"),
+            "log",
+        ));
+    }
+
+    Ok(entries)
+}
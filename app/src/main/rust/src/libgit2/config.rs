@@ -0,0 +1,260 @@
+//! `.gitnote.toml`, the configuration of the repo, shared by all the devices syncing it.
+//!
+//! ```toml
+//! # remote used by the pull and the push
+//! remote = "origin"
+//!
+//! [commit]
+//! # `{message}` is replaced by the message of the app
+//! message = "{message} (phone)"
+//!
+//! [merge]
+//! # "merge", or "fast-forward" to refuse diverged histories
+//! policy = "merge"
+//!
+//! [sync]
+//! # stash the uncommitted changes while pulling, over the setting of the app
+//! stash = true
+//!
+//! [notes]
+//! # gitignore patterns
+//! ignore = ["drafts/", "*.tmp"]
+//!
+//! [extensions]
+//! org = "markdown"
+//! ```
+
+use std::fs;
+
+use git2::Repository;
+
+use crate::mime_types::{self, Extensions};
+
+/// Configuration of the repo, at its root.
+pub const CONFIG_FILE: &str = ".gitnote.toml";
+
+/// The remote created by a clone.
+pub const DEFAULT_REMOTE: &str = "origin";
+
+const MESSAGE_PLACEHOLDER: &str = "{message}";
+
+/// Known keys of each table, the root being `""`.
+const SCHEMA: &[(&str, &[&str])] = &[
+    (
+        "",
+        &["commit", "extensions", "merge", "notes", "remote", "sync"],
+    ),
+    ("commit", &["message"]),
+    ("merge", &["policy"]),
+    ("notes", &["ignore"]),
+    ("sync", &["stash"]),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// A merge commit when the histories diverged.
+    #[default]
+    Merge,
+    /// The pull fails when the histories diverged.
+    FastForward,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub remote: String,
+    /// Template of the commit messages, see [`Config::commit_message`].
+    pub commit_message: String,
+    pub merge_policy: MergePolicy,
+    /// Over the setting of the app when set.
+    pub stash_on_sync: Option<bool>,
+    /// Gitignore patterns, over the `.gitignore` and `.gitnoteignore` files.
+    pub ignore: Vec<String>,
+    pub extensions: Extensions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            remote: DEFAULT_REMOTE.to_string(),
+            commit_message: MESSAGE_PLACEHOLDER.to_string(),
+            merge_policy: MergePolicy::default(),
+            stash_on_sync: None,
+            ignore: Vec::new(),
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+impl Config {
+    /// `message`, from the app, formatted with the template of the repo.
+    pub fn commit_message(&self, message: &str) -> String {
+        self.commit_message.replace(MESSAGE_PLACEHOLDER, message)
+    }
+}
+
+/// A configuration, and what was wrong in its file.
+#[derive(Debug, Clone, Default)]
+pub struct Loaded {
+    pub config: Config,
+    /// The invalid values are replaced by their default.
+    pub errors: Vec<String>,
+}
+
+/// The value at `path`, with a dot between the table and the key.
+fn get<'a>(table: &'a toml::Table, path: &str) -> Option<&'a toml::Value> {
+    match path.split_once('.') {
+        Some((section, key)) => table.get(section)?.get(key),
+        None => table.get(path),
+    }
+}
+
+fn syntax_error(content: &str, e: toml::de::Error) -> String {
+    match e.span() {
+        Some(span) => format!(
+            "line {}: {}",
+            content[..span.start].matches('\n').count() + 1,
+            e.message()
+        ),
+        None => e.message().to_string(),
+    }
+}
+
+fn unexpected(key: &str, expected: &str, value: &toml::Value) -> String {
+    format!("{key}: expected a {expected}, found a {}", value.type_str())
+}
+
+fn check_schema(table: &toml::Table, errors: &mut Vec<String>) {
+    for (section, keys) in SCHEMA {
+        let table = if section.is_empty() {
+            table
+        } else {
+            match table.get(*section) {
+                Some(toml::Value::Table(table)) => table,
+                Some(value) => {
+                    errors.push(unexpected(section, "table", value));
+                    continue;
+                }
+                None => continue,
+            }
+        };
+
+        for key in table.keys() {
+            if !keys.contains(&key.as_str()) {
+                let path = if section.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{section}.{key}")
+                };
+                errors.push(format!("{path}: unknown key"));
+            }
+        }
+    }
+}
+
+fn string(table: &toml::Table, key: &str, errors: &mut Vec<String>) -> Option<String> {
+    match get(table, key)? {
+        toml::Value::String(s) => Some(s.clone()),
+        value => {
+            errors.push(unexpected(key, "string", value));
+            None
+        }
+    }
+}
+
+fn boolean(table: &toml::Table, key: &str, errors: &mut Vec<String>) -> Option<bool> {
+    match get(table, key)? {
+        toml::Value::Boolean(b) => Some(*b),
+        value => {
+            errors.push(unexpected(key, "boolean", value));
+            None
+        }
+    }
+}
+
+fn strings(table: &toml::Table, key: &str, errors: &mut Vec<String>) -> Option<Vec<String>> {
+    let value = get(table, key)?;
+
+    let strings = match value {
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|v| v.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+
+    if strings.is_none() {
+        errors.push(unexpected(key, "list of strings", value));
+    }
+    strings
+}
+
+/// The configuration in `content`, the invalid values being replaced by their default.
+pub fn parse(content: &str) -> Loaded {
+    let table = match content.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(e) => {
+            return Loaded {
+                config: Config::default(),
+                errors: vec![syntax_error(content, e)],
+            };
+        }
+    };
+
+    let mut errors = Vec::new();
+    let mut config = Config::default();
+
+    check_schema(&table, &mut errors);
+
+    if let Some(remote) = string(&table, "remote", &mut errors) {
+        if remote.is_empty() {
+            errors.push("remote: empty name".to_string());
+        } else {
+            config.remote = remote;
+        }
+    }
+
+    if let Some(message) = string(&table, "commit.message", &mut errors) {
+        if message.contains(MESSAGE_PLACEHOLDER) {
+            config.commit_message = message;
+        } else {
+            errors.push(format!("commit.message: missing {MESSAGE_PLACEHOLDER}"));
+        }
+    }
+
+    if let Some(policy) = string(&table, "merge.policy", &mut errors) {
+        match policy.as_str() {
+            "merge" => config.merge_policy = MergePolicy::Merge,
+            "fast-forward" => config.merge_policy = MergePolicy::FastForward,
+            policy => errors.push(format!("merge.policy: unknown policy {policy:?}")),
+        }
+    }
+
+    config.stash_on_sync = boolean(&table, "sync.stash", &mut errors);
+
+    if let Some(ignore) = strings(&table, "notes.ignore", &mut errors) {
+        config.ignore = ignore;
+    }
+
+    match mime_types::parse_extensions(&table) {
+        Ok(extensions) => config.extensions = extensions,
+        Err(e) => errors.push(e),
+    }
+
+    Loaded { config, errors }
+}
+
+/// The configuration file of `repo`, the default one when it is missing.
+pub fn load(repo: &Repository) -> Loaded {
+    let Some(path) = repo.workdir().map(|w| w.join(CONFIG_FILE)) else {
+        return Loaded::default();
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(content) => parse(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Loaded::default(),
+        Err(e) => Loaded {
+            config: Config::default(),
+            errors: vec![e.to_string()],
+        },
+    }
+}
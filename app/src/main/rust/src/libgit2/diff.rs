@@ -0,0 +1,329 @@
+//! Line diff of a note between two versions: a commit, the index or the working file.
+
+use std::{fs, io, path::Path};
+
+use git2::{DiffOptions, Patch, Repository};
+
+use crate::Error;
+
+use super::rename::diff_renames;
+
+/// `[start, end)` ranges of utf-16 code units.
+pub type Ranges = Vec<(u32, u32)>;
+
+/// Above this number of word pairs, the whole lines are marked as changed.
+const MAX_WORD_DIFF_CELLS: usize = 100_000;
+
+// important: must match the order of DiffSide in kotlin
+pub enum Side {
+    /// Any revision, like an oid or `HEAD`.
+    Commit(String),
+    Index,
+    Worktree,
+}
+
+impl Side {
+    pub fn new(code: i32, revision: Option<String>) -> Result<Self, Error> {
+        match (code, revision) {
+            (0, Some(revision)) => Ok(Side::Commit(revision)),
+            (1, _) => Ok(Side::Index),
+            (2, _) => Ok(Side::Worktree),
+            _ => Err(Error::git2(
+                git2::Error::from_str(&format!("invalid diff side: {code}")),
+                "diff",
+            )),
+        }
+    }
+}
+
+// important: must match the order of DiffLineKind in kotlin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// Starts at 1, `None` for an added line.
+    pub old_line: Option<u32>,
+    /// Starts at 1, `None` for a removed line.
+    pub new_line: Option<u32>,
+    /// Without the line ending.
+    pub content: String,
+    /// With the word diff option, the changed parts of an added or removed line.
+    pub changes: Ranges,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+fn read(repo: &Repository, path: &str, side: &Side) -> Result<Vec<u8>, Error> {
+    let content = match side {
+        Side::Commit(revision) => {
+            let tree = repo.revparse_single(revision)?.peel_to_tree()?;
+
+            match tree.get_path(Path::new(path)) {
+                Ok(entry) => repo.find_blob(entry.id())?.content().to_vec(),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Side::Index => match repo.index()?.get_path(Path::new(path), 0) {
+            Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+            None => Vec::new(),
+        },
+        Side::Worktree => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+
+            match fs::read(workdir.join(path)) {
+                Ok(content) => content,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => {
+                    return Err(Error::io(e, "read worktree"));
+                }
+            }
+        }
+    };
+
+    Ok(content)
+}
+
+/// Split `line` into words, whitespace runs and single punctuation characters.
+fn words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<char> = None;
+
+    for (i, c) in line.char_indices() {
+        let same_run = previous.is_some_and(|p| {
+            (p.is_alphanumeric() && c.is_alphanumeric()) || (p.is_whitespace() && c.is_whitespace())
+        });
+
+        if !same_run && i > start {
+            words.push(&line[start..i]);
+            start = i;
+        }
+        previous = Some(c);
+    }
+
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+
+    words
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+/// Ranges of `old` and `new` which are not part of their longest common sequence of words.
+fn word_changes(old: &str, new: &str) -> (Ranges, Ranges) {
+    let old_words = words(old);
+    let new_words = words(new);
+    let (n, m) = (old_words.len(), new_words.len());
+
+    if n * m > MAX_WORD_DIFF_CELLS {
+        return (vec![(0, utf16_len(old))], vec![(0, utf16_len(new))]);
+    }
+
+    // lcs[i][j]: length of the longest common sequence of old_words[i..] and new_words[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_words[i] == new_words[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_changes = Vec::new();
+    let mut new_changes = Vec::new();
+
+    let push = |changes: &mut Ranges, start: u32, len: u32| match changes.last_mut() {
+        Some((_, end)) if *end == start => *end += len,
+        _ => changes.push((start, start + len)),
+    };
+
+    let (mut i, mut j) = (0, 0);
+    let (mut old_pos, mut new_pos) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && old_words[i] == new_words[j] {
+            old_pos += utf16_len(old_words[i]);
+            new_pos += utf16_len(new_words[j]);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            let len = utf16_len(new_words[j]);
+            push(&mut new_changes, new_pos, len);
+            new_pos += len;
+            j += 1;
+        } else {
+            let len = utf16_len(old_words[i]);
+            push(&mut old_changes, old_pos, len);
+            old_pos += len;
+            i += 1;
+        }
+    }
+
+    (old_changes, new_changes)
+}
+
+/// Pair each block of removed lines with the added lines following it,
+/// and mark the words which changed.
+fn add_word_changes(lines: &mut [DiffLine]) {
+    let mut i = 0;
+
+    while i < lines.len() {
+        let removed = lines[i..]
+            .iter()
+            .take_while(|l| l.kind == LineKind::Removed)
+            .count();
+        let added = lines[i + removed..]
+            .iter()
+            .take_while(|l| l.kind == LineKind::Added)
+            .count();
+
+        if removed == 0 && added == 0 {
+            i += 1;
+            continue;
+        }
+
+        for k in 0..removed.max(added) {
+            let (old, new) = (i + k, i + removed + k);
+
+            if k < removed && k < added {
+                let (old_changes, new_changes) =
+                    word_changes(&lines[old].content, &lines[new].content);
+                lines[old].changes = old_changes;
+                lines[new].changes = new_changes;
+            } else {
+                // nothing to compare with
+                let line = if k < removed {
+                    &mut lines[old]
+                } else {
+                    &mut lines[new]
+                };
+                line.changes = vec![(0, utf16_len(&line.content))];
+            }
+        }
+
+        i += removed + added;
+    }
+}
+
+/// Path `path` had in the commit `from`, when it was renamed between `from` and `to`.
+fn previous_path(
+    repo: &Repository,
+    path: &str,
+    from: &Side,
+    to: &Side,
+) -> Result<Option<String>, git2::Error> {
+    let Side::Commit(revision) = from else {
+        return Ok(None);
+    };
+
+    let from_tree = repo.revparse_single(revision)?.peel_to_tree()?;
+    if from_tree.get_path(Path::new(path)).is_ok() {
+        return Ok(None);
+    }
+
+    let mut diff = match to {
+        Side::Commit(revision) => {
+            let to_tree = repo.revparse_single(revision)?.peel_to_tree()?;
+            repo.diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)?
+        }
+        Side::Index => repo.diff_tree_to_index(Some(&from_tree), None, None)?,
+        Side::Worktree => repo.diff_tree_to_workdir_with_index(Some(&from_tree), None)?,
+    };
+
+    Ok(diff_renames(&mut diff)?
+        .remove(path)
+        .map(|rename| rename.from))
+}
+
+/// Hunks of the differences of `path` between `from` and `to`.
+///
+/// A missing file is treated as empty, unless it was renamed since `from`.
+pub fn diff(
+    repo: &Repository,
+    path: &str,
+    from: &Side,
+    to: &Side,
+    word_diff: bool,
+) -> Result<Vec<Hunk>, Error> {
+    let old_path = previous_path(repo, path, from, to)?;
+    let old_path = old_path.as_deref().unwrap_or(path);
+
+    let old = read(repo, old_path, from)?;
+    let new = read(repo, path, to)?;
+
+    let mut options = DiffOptions::new();
+    options.context_lines(3);
+
+    let patch = Patch::from_buffers(
+        &old,
+        Some(Path::new(old_path)),
+        &new,
+        Some(Path::new(path)),
+        Some(&mut options),
+    )?;
+
+    let mut hunks = Vec::new();
+
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+
+        let mut lines = Vec::with_capacity(line_count);
+
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+
+            let kind = match line.origin() {
+                ' ' => LineKind::Context,
+                '+' => LineKind::Added,
+                '-' => LineKind::Removed,
+                // "\ No newline at end of file"
+                _ => continue,
+            };
+
+            let content = String::from_utf8_lossy(line.content());
+
+            lines.push(DiffLine {
+                kind,
+                old_line: line.old_lineno(),
+                new_line: line.new_lineno(),
+                content: content.trim_end_matches(['\n', '\r']).to_string(),
+                changes: Vec::new(),
+            });
+        }
+
+        if word_diff {
+            add_word_changes(&mut lines);
+        }
+
+        hunks.push(Hunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    Ok(hunks)
+}
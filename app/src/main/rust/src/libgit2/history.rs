@@ -0,0 +1,188 @@
+//! Search the content of every version of the notes, including deleted ones.
+
+use std::{collections::HashSet, path::Path};
+
+use git2::{ObjectType, Oid, Repository, Tree};
+use regex::{Regex, RegexBuilder};
+
+use crate::{Error, callback::ProgressCB};
+
+use super::timestamps::is_note;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryMatch {
+    /// Most recent commit containing this version of the note.
+    pub commit: Oid,
+    /// Commit time, in milliseconds.
+    pub time: i64,
+    pub path: String,
+    /// Starts at 1.
+    pub line_number: usize,
+    pub line: String,
+    /// Lines before and after the match, joined with `\n`.
+    pub context_before: String,
+    pub context_after: String,
+}
+
+pub struct HistoryQuery {
+    pub pattern: String,
+    pub is_regex: bool,
+    pub ignore_case: bool,
+    /// Number of lines around each match.
+    pub context: usize,
+    pub max_matches: usize,
+}
+
+impl HistoryQuery {
+    fn regex(&self) -> Result<Regex, Error> {
+        let pattern = if self.is_regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(|e| Error::git2(git2::Error::from_str(&e.to_string()), "history pattern"))
+    }
+}
+
+struct Searcher<'a> {
+    repo: &'a Repository,
+    regex: Regex,
+    query: &'a HistoryQuery,
+    /// Trees and blobs already searched in a more recent commit.
+    seen: HashSet<Oid>,
+    matches: Vec<HistoryMatch>,
+}
+
+impl Searcher<'_> {
+    fn is_full(&self) -> bool {
+        self.matches.len() >= self.query.max_matches
+    }
+
+    fn search_tree(
+        &mut self,
+        tree: &Tree,
+        dir: &str,
+        commit: Oid,
+        time: i64,
+    ) -> Result<(), git2::Error> {
+        for entry in tree.iter() {
+            if self.is_full() {
+                break;
+            }
+            if !self.seen.insert(entry.id()) {
+                continue;
+            }
+            let Ok(name) = entry.name() else {
+                continue;
+            };
+            let path = format!("{dir}{name}");
+
+            match entry.kind() {
+                Some(ObjectType::Tree) => {
+                    let tree = self.repo.find_tree(entry.id())?;
+                    self.search_tree(&tree, &format!("{path}/"), commit, time)?;
+                }
+                Some(ObjectType::Blob) if is_note(Path::new(&path)) => {
+                    let blob = self.repo.find_blob(entry.id())?;
+                    if blob.is_binary() {
+                        continue;
+                    }
+                    self.search_blob(&String::from_utf8_lossy(blob.content()), path, commit, time);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn search_blob(&mut self, content: &str, path: String, commit: Oid, time: i64) {
+        if !self.regex.is_match(content) {
+            return;
+        }
+
+        let lines = content.lines().collect::<Vec<_>>();
+        let context = self.query.context;
+
+        for (i, line) in lines.iter().enumerate() {
+            if self.is_full() {
+                return;
+            }
+            if !self.regex.is_match(line) {
+                continue;
+            }
+
+            let before = &lines[i.saturating_sub(context)..i];
+            let after = &lines[i + 1..(i + 1 + context).min(lines.len())];
+
+            self.matches.push(HistoryMatch {
+                commit,
+                time,
+                path: path.clone(),
+                line_number: i + 1,
+                line: line.to_string(),
+                context_before: before.join("\n"),
+                context_after: after.join("\n"),
+            });
+        }
+    }
+}
+
+/// Search every version of the notes reachable from HEAD, newest commits first.
+///
+/// Each blob is only searched once, in the most recent commit containing it.
+/// `cb` receives the percentage of commits searched, the search stops
+/// with the matches found so far when it returns `false`.
+pub fn search_history(
+    repo: &Repository,
+    query: &HistoryQuery,
+    mut cb: impl ProgressCB,
+) -> Result<Vec<HistoryMatch>, Error> {
+    let mut searcher = Searcher {
+        repo,
+        regex: query.regex()?,
+        query,
+        seen: HashSet::new(),
+        matches: Vec::new(),
+    };
+
+    let revwalk = || -> Result<git2::Revwalk, git2::Error> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        Ok(revwalk)
+    };
+
+    let total = revwalk()?.count().max(1);
+    let mut last_progress = -1;
+
+    for (i, oid) in revwalk()?.enumerate() {
+        let progress = (i * 100 / total) as i32;
+        if progress != last_progress {
+            last_progress = progress;
+            if !cb.progress(progress) {
+                debug!("history search cancelled");
+                break;
+            }
+        }
+
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+
+        if !searcher.seen.insert(tree.id()) {
+            continue;
+        }
+
+        searcher.search_tree(&tree, "", commit.id(), commit.time().seconds() * 1000)?;
+
+        if searcher.is_full() {
+            break;
+        }
+    }
+
+    Ok(searcher.matches)
+}
@@ -0,0 +1,154 @@
+//! Paths hidden from the status, the commits and the notes.
+//!
+//! On top of the `.gitignore` files, in increasing priority: the junk files
+//! of the editors and sync tools, the `.gitnoteignore` file at the root of the repo,
+//! and the `notes.ignore` patterns of the configuration file.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use git2::Repository;
+
+use crate::Error;
+
+/// Gitignore patterns of the app, at the root of the repo.
+pub const IGNORE_FILE: &str = ".gitnoteignore";
+
+/// Files created by the editors, the file managers and the sync tools.
+///
+/// A `!pattern` line of [`IGNORE_FILE`] brings one of them back.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // file managers
+    ".DS_Store",
+    "._*",
+    "Thumbs.db",
+    "desktop.ini",
+    // editors
+    "*.swp",
+    "*.swo",
+    "*~",
+    ".#*",
+    // office lock files
+    "~$*",
+    ".~lock.*#",
+    // sync tools
+    ".syncthing.*.tmp",
+    ".stfolder/",
+    ".stversions/",
+    ".dropbox.attr",
+];
+
+fn ignore_file(repo: &Repository) -> Result<PathBuf, Error> {
+    repo.workdir()
+        .map(|w| w.join(IGNORE_FILE))
+        .ok_or_else(|| Error::git2(git2::Error::from_str("bare repository"), "ignore"))
+}
+
+/// Patterns of [`IGNORE_FILE`], without the comments and the blank lines.
+fn file_patterns(repo: &Repository) -> Vec<String> {
+    let Ok(path) = ignore_file(repo) else {
+        return Vec::new();
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(content) => content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            warn!("{IGNORE_FILE}: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// All the patterns of the app, the last matching one wins.
+pub fn patterns(repo: &Repository, config_patterns: &[String]) -> Vec<String> {
+    DEFAULT_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .chain(file_patterns(repo))
+        .chain(config_patterns.iter().cloned())
+        .collect()
+}
+
+/// Ignore the patterns of the app, and `config_patterns`, in `repo`.
+///
+/// The rules only live in `repo`, they replace the ones of the previous call.
+pub fn set_rules(repo: &Repository, config_patterns: &[String]) {
+    if let Err(e) = repo.clear_ignore_rules() {
+        error!("clear_ignore_rules: {e}");
+    }
+    if let Err(e) = repo.add_ignore_rule(&patterns(repo, config_patterns).join("\n")) {
+        error!("add_ignore_rule: {e}");
+    }
+}
+
+/// Append the new `patterns` to [`IGNORE_FILE`].
+///
+/// Call [`set_rules`] to use them.
+pub fn add_patterns(repo: &Repository, patterns: &[String]) -> Result<(), Error> {
+    let path = ignore_file(repo)?;
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::io(e, &path.to_string_lossy())),
+    };
+
+    let existing = content.lines().map(str::trim_end).collect::<Vec<_>>();
+
+    let mut new = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        if pattern.is_empty() || pattern.contains('\n') {
+            return Err(Error::git2(
+                git2::Error::from_str(&format!("invalid pattern: {pattern:?}")),
+                "ignore",
+            ));
+        }
+        if !existing.contains(&pattern) && !new.contains(&pattern) {
+            new.push(pattern);
+        }
+    }
+
+    if new.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| Error::io(e, &path.to_string_lossy()))?;
+
+    let mut append = String::new();
+    if !content.is_empty() && !content.ends_with('\n') {
+        append.push('\n');
+    }
+    for pattern in new {
+        append.push_str(pattern);
+        append.push('\n');
+    }
+
+    file.write_all(append.as_bytes())
+        .map_err(|e| Error::io(e, &path.to_string_lossy()))
+}
+
+/// `path`, relative to the root of the repo, is ignored.
+pub fn is_ignored(repo: &Repository, path: &Path, is_dir: bool) -> bool {
+    // libgit2 only matches directory patterns with a trailing slash
+    let path = if is_dir {
+        path.join("")
+    } else {
+        path.to_path_buf()
+    };
+
+    repo.is_path_ignored(path).unwrap_or(false)
+}
@@ -0,0 +1,72 @@
+//! HTTP client of the LFS API.
+//!
+//! The certificates are verified with the Mozilla roots of `webpki-roots`,
+//! the credentials are only sent again when a redirection stays on the same host.
+
+use std::{io, sync::LazyLock, time::Duration};
+
+use ureq::{Agent, config::RedirectAuthHeaders};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_REDIRECTS: u32 = 5;
+
+static AGENT: LazyLock<Agent> = LazyLock::new(|| {
+    Agent::config_builder()
+        .timeout_connect(Some(TIMEOUT))
+        .timeout_recv_response(Some(TIMEOUT))
+        .max_redirects(MAX_REDIRECTS)
+        .redirect_auth_headers(RedirectAuthHeaders::SameHost)
+        // the status is checked by the callers, with the body of the errors
+        .http_status_as_error(false)
+        .build()
+        .into()
+});
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+fn http_error(e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+/// Send a request, and read at most `limit` bytes of the response.
+pub fn send(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+    limit: u64,
+) -> io::Result<Response> {
+    let mut request = ureq::http::Request::builder().method(method).uri(url);
+    for (key, value) in headers {
+        request = request.header(key, value);
+    }
+    let request = request
+        .body(body)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut response = AGENT.run(request).map_err(http_error)?;
+
+    let status = response.status().as_u16();
+    let body = response
+        .body_mut()
+        .with_config()
+        // the reader fails once the limit is reached, even at the end of the body
+        .limit(limit.saturating_add(1))
+        .read_to_vec()
+        .map_err(http_error)?;
+
+    Ok(Response { status, body })
+}
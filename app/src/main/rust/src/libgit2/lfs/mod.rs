@@ -0,0 +1,693 @@
+//! Git LFS: large files, like images and PDFs, stored outside of the repo
+//! and replaced by pointer files in the commits.
+//!
+//! libgit2 doesn't run the `lfs` filter, so it is applied around the git operations:
+//! files are cleaned when they are added to the index, and smudged after a checkout,
+//! with their content cached under `.git/lfs/objects`.
+
+mod http;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use base64ct::{Base64, Encoding};
+use git2::{AttrCheckFlags, Index, IndexEntry, IndexTime, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Cred, Error};
+
+pub const POINTER_VERSION: &str = "https://git-lfs.github.com/spec/v1";
+const MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+/// Pointer files are small, bigger blobs are never parsed.
+const MAX_POINTER_SIZE: u64 = 1024;
+/// How far in the past the lfs files are backdated, see [`backdate`].
+const RACY_DELAY: Duration = Duration::from_secs(2);
+/// The responses of the API, the objects are limited by their size.
+const MAX_RESPONSE_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Pointer {
+    /// Hex sha256 of the content.
+    pub oid: String,
+    pub size: u64,
+}
+
+impl Pointer {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        if content.len() as u64 > MAX_POINTER_SIZE {
+            return None;
+        }
+
+        let content = std::str::from_utf8(content).ok()?;
+        let mut lines = content.lines();
+
+        if lines.next()?.strip_prefix("version ")? != POINTER_VERSION {
+            return None;
+        }
+
+        let (mut oid, mut size) = (None, None);
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("oid", value)) => oid = value.strip_prefix("sha256:"),
+                Some(("size", value)) => size = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        let oid = oid?.to_ascii_lowercase();
+        if !is_oid(&oid) {
+            return None;
+        }
+
+        Some(Pointer { oid, size: size? })
+    }
+
+    pub fn from_content(content: &[u8]) -> Self {
+        let hash = Sha256::digest(content);
+
+        Pointer {
+            oid: hash.iter().map(|b| format!("{b:02x}")).collect(),
+            size: content.len() as u64,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        format!(
+            "version {POINTER_VERSION}\noid sha256:{}\nsize {}\n",
+            self.oid, self.size
+        )
+        .into_bytes()
+    }
+}
+
+/// 64 lowercase hex digits, the form of the oids of the pointers.
+fn is_oid(oid: &str) -> bool {
+    oid.len() == 64 && oid.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn lfs_error(msg: impl std::fmt::Display) -> Error {
+    Error::git2(git2::Error::from_str(&msg.to_string()), "lfs")
+}
+
+/// `path` has the `filter=lfs` attribute, from the `.gitattributes` files.
+pub fn is_lfs(repo: &Repository, path: &Path) -> bool {
+    matches!(
+        repo.get_attr(path, "filter", AttrCheckFlags::FILE_THEN_INDEX),
+        Ok(Some("lfs"))
+    )
+}
+
+/// Location of an object in the cache: `.git/lfs/objects/ab/cd/abcd...`.
+///
+/// `oid` comes from a [`Pointer`], never from a response of the server.
+pub fn object_path(repo: &Repository, oid: &str) -> PathBuf {
+    repo.path()
+        .join("lfs/objects")
+        .join(&oid[0..2])
+        .join(&oid[2..4])
+        .join(oid)
+}
+
+fn is_cached(repo: &Repository, pointer: &Pointer) -> bool {
+    fs::metadata(object_path(repo, &pointer.oid)).is_ok_and(|m| m.len() == pointer.size)
+}
+
+fn store(repo: &Repository, pointer: &Pointer, content: &[u8]) -> io::Result<()> {
+    let path = object_path(repo, &pointer.oid);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // never leave a partial object in the cache
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &path)
+}
+
+fn index_time(seconds: i64, nanoseconds: i64) -> IndexTime {
+    IndexTime::new(seconds as i32, nanoseconds as u32)
+}
+
+/// libgit2 hashes the files modified in the same instant as the index ("racy" entries),
+/// and would compare their content with the pointer: the recent lfs files are backdated.
+fn backdate(path: &Path) -> io::Result<()> {
+    let time = SystemTime::now() - RACY_DELAY;
+
+    let file = fs::File::options().write(true).open(path)?;
+    if file.metadata()?.modified()? > time {
+        file.set_modified(time)?;
+    }
+
+    Ok(())
+}
+
+/// Index entry of the worktree file `path`, with the stat data of the file,
+/// so that it isn't seen as modified while its blob is the pointer.
+fn index_entry(workdir: &Path, path: &Path, id: Oid) -> io::Result<IndexEntry> {
+    backdate(&workdir.join(path))?;
+    let metadata = fs::metadata(workdir.join(path))?;
+
+    let executable = metadata.permissions().mode() & 0o111 != 0;
+
+    Ok(IndexEntry {
+        ctime: index_time(metadata.ctime(), metadata.ctime_nsec()),
+        mtime: index_time(metadata.mtime(), metadata.mtime_nsec()),
+        dev: metadata.dev() as u32,
+        ino: metadata.ino() as u32,
+        mode: if executable { 0o100755 } else { 0o100644 },
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        file_size: metadata.len() as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.to_string_lossy().as_bytes().to_vec(),
+    })
+}
+
+/// Clean filter: add the worktree file `path` to `index` as a pointer,
+/// and store its content in the cache.
+pub fn add_to_index(repo: &Repository, index: &mut Index, path: &Path) -> Result<(), Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+
+    let content = fs::read(workdir.join(path)).map_err(|e| Error::io(e, "read"))?;
+
+    // already a pointer, e.g. the object was never downloaded
+    let pointer_bytes = match Pointer::parse(&content) {
+        Some(_) => content,
+        None => {
+            let pointer = Pointer::from_content(&content);
+            if !is_cached(repo, &pointer) {
+                store(repo, &pointer, &content).map_err(|e| Error::io(e, "store"))?;
+            }
+            pointer.to_bytes()
+        }
+    };
+
+    let id = repo.blob(&pointer_bytes)?;
+    let entry = index_entry(workdir, path, id).map_err(|e| Error::io(e, "stat"))?;
+    index.add(&entry)?;
+
+    Ok(())
+}
+
+/// Like `index.add_all`, but the LFS files are added as pointers.
+pub fn add_all(repo: &Repository, index: &mut Index) -> Result<(), Error> {
+    let mut lfs_paths = Vec::new();
+
+    index
+        .add_all(
+            ["*"].iter(),
+            git2::IndexAddOption::DEFAULT,
+            Some(&mut |path: &Path, _: &[u8]| {
+                if is_lfs(repo, path) {
+                    lfs_paths.push(path.to_path_buf());
+                    // skip
+                    1
+                } else {
+                    0
+                }
+            }),
+        )
+        .map_err(|e| Error::git2(e, "add_all"))?;
+
+    for path in lfs_paths {
+        add_to_index(repo, index, &path)?;
+    }
+
+    Ok(())
+}
+
+/// LFS files of the index whose worktree file is still a pointer.
+fn worktree_pointers(repo: &Repository) -> Result<Vec<(PathBuf, Pointer)>, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+    let index = repo.index()?;
+
+    let mut pointers = Vec::new();
+
+    for entry in index.iter() {
+        if entry.file_size as u64 > MAX_POINTER_SIZE {
+            continue;
+        }
+
+        let path = PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned());
+        if !is_lfs(repo, &path) {
+            continue;
+        }
+
+        let Ok(blob) = repo.find_blob(entry.id) else {
+            continue;
+        };
+        let Some(pointer) = Pointer::parse(blob.content()) else {
+            continue;
+        };
+
+        let mut content = Vec::new();
+        let read = fs::File::open(workdir.join(&path))
+            .and_then(|file| file.take(MAX_POINTER_SIZE + 1).read_to_end(&mut content));
+
+        if read.is_ok() && Pointer::parse(&content).as_ref() == Some(&pointer) {
+            pointers.push((path, pointer));
+        }
+    }
+
+    Ok(pointers)
+}
+
+/// Smudge filter: replace the pointer files of the worktree by the cached content,
+/// and keep the index entries clean.
+fn smudge(repo: &Repository, pointers: &[(PathBuf, Pointer)]) -> Result<usize, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+    let mut index = repo.index()?;
+
+    let mut count = 0;
+
+    for (path, pointer) in pointers {
+        if !is_cached(repo, pointer) {
+            continue;
+        }
+
+        fs::copy(object_path(repo, &pointer.oid), workdir.join(path))
+            .map_err(|e| Error::io(e, "smudge"))?;
+
+        let id = match index.get_path(path, 0) {
+            Some(entry) => entry.id,
+            None => continue,
+        };
+        let entry = index_entry(workdir, path, id).map_err(|e| Error::io(e, "stat"))?;
+        index.add(&entry)?;
+
+        count += 1;
+    }
+
+    index.write()?;
+
+    Ok(count)
+}
+
+/// The LFS server, from `lfs.url` in the git config or `.lfsconfig`,
+/// or derived from the url of the remote.
+pub fn endpoint(repo: &Repository, remote: &str) -> Result<String, Error> {
+    if let Ok(url) = repo.config().and_then(|c| c.get_string("lfs.url")) {
+        return Ok(url);
+    }
+
+    if let Some(workdir) = repo.workdir() {
+        let lfsconfig = workdir.join(".lfsconfig");
+        if lfsconfig.exists()
+            && let Ok(url) = git2::Config::open(&lfsconfig).and_then(|c| c.get_string("lfs.url"))
+        {
+            return Ok(url);
+        }
+    }
+
+    let remote = repo.find_remote(remote)?;
+    let url = remote
+        .url()
+        .map_err(|_| lfs_error("the remote has no url"))?;
+
+    endpoint_from_remote(url).ok_or_else(|| lfs_error(format!("no lfs endpoint for {url}")))
+}
+
+/// `<remote>.git/info/lfs`, over https for the ssh remotes.
+pub fn endpoint_from_remote(url: &str) -> Option<String> {
+    let url = url.trim_end_matches('/');
+
+    let base = if url.starts_with("https://") || url.starts_with("http://") {
+        url.to_string()
+    } else {
+        let rest = url.strip_prefix("ssh://").unwrap_or(url);
+        let rest = rest.split_once('@').map_or(rest, |(_, rest)| rest);
+
+        let (host, path) = if url.starts_with("ssh://") {
+            let (authority, path) = rest.split_once('/')?;
+            // the port is the one of ssh
+            (authority.split(':').next()?, path)
+        } else {
+            rest.split_once(':')?
+        };
+
+        format!("https://{host}/{}", path.trim_start_matches('/'))
+    };
+
+    if base.ends_with(".git") {
+        Some(format!("{base}/info/lfs"))
+    } else {
+        Some(format!("{base}.git/info/lfs"))
+    }
+}
+
+fn auth_headers(cred: Option<&Cred>) -> Result<Vec<(String, String)>, Error> {
+    match cred {
+        Some(Cred::UserPassPlainText { username, password }) => {
+            let token = Base64::encode_string(format!("{username}:{password}").as_bytes());
+            Ok(vec![(
+                "Authorization".to_string(),
+                format!("Basic {token}"),
+            )])
+        }
+        // the ssh keys can't authenticate over https
+        Some(Cred::Ssh { .. } | Cred::SshKeyRef { .. }) => {
+            Err(lfs_error("LFS over ssh is not supported"))
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Body of a batch request.
+#[derive(Serialize)]
+struct BatchRequest<'a> {
+    operation: &'a str,
+    transfers: &'a [&'a str],
+    objects: &'a [&'a Pointer],
+    hash_algo: &'a str,
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    objects: Vec<BatchObject>,
+}
+
+#[derive(Deserialize)]
+struct BatchObject {
+    oid: String,
+    #[serde(default)]
+    actions: Actions,
+    error: Option<ObjectError>,
+}
+
+#[derive(Deserialize)]
+struct ObjectError {
+    message: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct Actions {
+    download: Option<Action>,
+    upload: Option<Action>,
+    verify: Option<Action>,
+}
+
+#[derive(Deserialize)]
+struct Action {
+    href: String,
+    /// They replace the headers of the remote.
+    #[serde(default)]
+    header: HashMap<String, String>,
+}
+
+impl Action {
+    fn headers(&self) -> Vec<(String, String)> {
+        self.header
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Requested objects of the batch response, with their actions.
+///
+/// The objects without actions are already on the server for an upload.
+fn batch<'a>(
+    endpoint: &str,
+    cred: Option<&Cred>,
+    operation: &str,
+    pointers: &[&'a Pointer],
+) -> Result<Vec<(&'a Pointer, Actions)>, Error> {
+    let request = BatchRequest {
+        operation,
+        transfers: &["basic"],
+        objects: pointers,
+        hash_algo: "sha256",
+    };
+    let request = serde_json::to_vec(&request).map_err(lfs_error)?;
+
+    let mut headers = auth_headers(cred)?;
+    headers.push(("Accept".into(), MEDIA_TYPE.into()));
+    headers.push(("Content-Type".into(), MEDIA_TYPE.into()));
+
+    let url = format!("{}/objects/batch", endpoint.trim_end_matches('/'));
+    let response = http::send("POST", &url, &headers, &request, MAX_RESPONSE_SIZE)
+        .map_err(|e| Error::io(e, "lfs batch"))?;
+
+    if !response.is_success() {
+        return Err(lfs_error(format!(
+            "batch {operation}: status {}: {}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        )));
+    }
+
+    let response: BatchResponse = serde_json::from_slice(&response.body)
+        .map_err(|e| lfs_error(format!("batch response: {e}")))?;
+
+    response
+        .objects
+        .into_iter()
+        .map(|object| {
+            // the oid is used in the paths of the cache
+            let pointer = pointers
+                .iter()
+                .find(|pointer| is_oid(&object.oid) && pointer.oid == object.oid)
+                .ok_or_else(|| lfs_error(format!("unexpected object {:?}", object.oid)))?;
+
+            if let Some(error) = object.error {
+                return Err(lfs_error(format!(
+                    "object {}: {}",
+                    pointer.oid,
+                    error.message.as_deref().unwrap_or("error")
+                )));
+            }
+
+            Ok((*pointer, object.actions))
+        })
+        .collect()
+}
+
+fn download(
+    repo: &Repository,
+    endpoint: &str,
+    cred: Option<&Cred>,
+    pointers: &[&Pointer],
+) -> Result<(), Error> {
+    for (pointer, actions) in batch(endpoint, cred, "download", pointers)? {
+        let Some(action) = actions.download else {
+            return Err(lfs_error(format!(
+                "object {}: no download action",
+                pointer.oid
+            )));
+        };
+
+        let response = http::send("GET", &action.href, &action.headers(), &[], pointer.size)
+            .map_err(|e| Error::io(e, "lfs download"))?;
+
+        if !response.is_success() {
+            return Err(lfs_error(format!(
+                "download {}: status {}",
+                pointer.oid, response.status
+            )));
+        }
+
+        if Pointer::from_content(&response.body) != *pointer {
+            return Err(lfs_error(format!(
+                "download {}: corrupted content",
+                pointer.oid
+            )));
+        }
+
+        store(repo, pointer, &response.body).map_err(|e| Error::io(e, "store"))?;
+    }
+
+    Ok(())
+}
+
+/// Download the missing LFS objects of the worktree, and smudge their files.
+///
+/// Returns the number of smudged files.
+pub fn pull(repo: &Repository, remote: &str, cred: Option<&Cred>) -> Result<usize, Error> {
+    let pointers = worktree_pointers(repo)?;
+    if pointers.is_empty() {
+        return Ok(0);
+    }
+
+    let missing = pointers
+        .iter()
+        .map(|(_, pointer)| pointer)
+        .filter(|pointer| !is_cached(repo, pointer))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        let endpoint = endpoint(repo, remote)?;
+        download(repo, &endpoint, cred, &missing)?;
+    }
+
+    smudge(repo, &pointers)
+}
+
+/// Pointers of the LFS files added or modified by the commits of `local` missing in `remote`.
+fn pushed_pointers(
+    repo: &Repository,
+    local: Oid,
+    remote: Option<Oid>,
+) -> Result<Vec<Pointer>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(local)?;
+    if let Some(remote) = remote {
+        revwalk.hide(remote)?;
+    }
+
+    let mut seen = HashSet::new();
+    let mut pointers = HashMap::new();
+
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+        for delta in diff.deltas() {
+            let file = delta.new_file();
+            let id = file.id();
+
+            if id.is_zero() || file.size() > MAX_POINTER_SIZE || !seen.insert(id) {
+                continue;
+            }
+            let Some(path) = file.path() else {
+                continue;
+            };
+            if !is_lfs(repo, path) {
+                continue;
+            }
+
+            if let Some(pointer) = Pointer::parse(repo.find_blob(id)?.content()) {
+                pointers.insert(pointer.oid.clone(), pointer);
+            }
+        }
+    }
+
+    Ok(pointers.into_values().collect())
+}
+
+/// Fail before merging `fetched` when its LFS files are missing and can't be downloaded with `cred`.
+pub fn check_download(
+    repo: &Repository,
+    fetched: Oid,
+    head: Option<Oid>,
+    cred: Option<&Cred>,
+) -> Result<(), Error> {
+    let Err(e) = auth_headers(cred) else {
+        return Ok(());
+    };
+
+    let pointers = pushed_pointers(repo, fetched, head)?;
+    if pointers.iter().any(|pointer| !is_cached(repo, pointer)) {
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+fn upload(
+    repo: &Repository,
+    endpoint: &str,
+    cred: Option<&Cred>,
+    pointers: &[&Pointer],
+) -> Result<usize, Error> {
+    let mut count = 0;
+
+    for (pointer, actions) in batch(endpoint, cred, "upload", pointers)? {
+        // already on the server
+        let Some(action) = actions.upload else {
+            continue;
+        };
+
+        let content =
+            fs::read(object_path(repo, &pointer.oid)).map_err(|e| Error::io(e, "read object"))?;
+
+        let mut headers = action.headers();
+        headers.push(("Content-Type".into(), "application/octet-stream".into()));
+
+        let response = http::send("PUT", &action.href, &headers, &content, MAX_RESPONSE_SIZE)
+            .map_err(|e| Error::io(e, "lfs upload"))?;
+
+        if !response.is_success() {
+            return Err(lfs_error(format!(
+                "upload {}: status {}",
+                pointer.oid, response.status
+            )));
+        }
+
+        // only sent when the server asks for it
+        if let Some(verify) = actions.verify {
+            let mut headers = verify.headers();
+            headers.push(("Accept".into(), MEDIA_TYPE.into()));
+            headers.push(("Content-Type".into(), MEDIA_TYPE.into()));
+
+            let body = serde_json::to_vec(pointer).map_err(lfs_error)?;
+
+            let response = http::send("POST", &verify.href, &headers, &body, MAX_RESPONSE_SIZE)
+                .map_err(|e| Error::io(e, "lfs verify"))?;
+
+            if !response.is_success() {
+                return Err(lfs_error(format!(
+                    "verify {}: status {}",
+                    pointer.oid, response.status
+                )));
+            }
+        }
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Upload the LFS objects of the commits of `branch` which are not on the remote yet.
+///
+/// Returns the number of uploaded objects.
+pub fn push(
+    repo: &Repository,
+    remote: &str,
+    branch: &str,
+    cred: Option<&Cred>,
+) -> Result<usize, Error> {
+    let local = repo.refname_to_id(&format!("refs/heads/{branch}"))?;
+    let remote_head = repo
+        .refname_to_id(&format!("refs/remotes/{remote}/{branch}"))
+        .ok();
+
+    let pointers = pushed_pointers(repo, local, remote_head)?;
+
+    // the objects never downloaded are already on the server
+    let pointers = pointers
+        .iter()
+        .filter(|pointer| is_cached(repo, pointer))
+        .collect::<Vec<_>>();
+
+    if pointers.is_empty() {
+        return Ok(0);
+    }
+
+    let endpoint = endpoint(repo, remote)?;
+    upload(repo, &endpoint, cred, &pointers)
+}
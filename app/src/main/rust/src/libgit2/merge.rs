@@ -1,6 +1,10 @@
 use git2::{Repository, Signature};
+use ssh_key::PrivateKey;
 
-use crate::{Error, GitAuthor};
+use crate::{
+    Error, GitAuthor,
+    libgit2::{config::MergePolicy, sign},
+};
 
 fn fast_forward(
     repo: &Repository,
@@ -29,6 +33,7 @@ fn normal_merge(
     local: &git2::AnnotatedCommit,
     remote: &git2::AnnotatedCommit,
     author: &GitAuthor,
+    key: Option<&PrivateKey>,
 ) -> Result<(), git2::Error> {
     let local_tree = repo.find_commit(local.id())?.tree()?;
     let remote_tree = repo.find_commit(remote.id())?.tree()?;
@@ -50,13 +55,14 @@ fn normal_merge(
     let local_commit = repo.find_commit(local.id())?;
     let remote_commit = repo.find_commit(remote.id())?;
     // Do our merge commit and set current branch head to that commit.
-    let _merge_commit = repo.commit(
-        Some("HEAD"),
+    let _merge_commit = sign::commit(
+        repo,
         &sig,
         &sig,
         &msg,
         &result_tree,
         &[&local_commit, &remote_commit],
+        key,
     )?;
     // Set working tree to match head.
     let mut checkout_opts = git2::build::CheckoutBuilder::new();
@@ -71,6 +77,8 @@ pub fn do_merge<'a>(
     remote_branch: &str,
     fetch_commit: git2::AnnotatedCommit<'a>,
     author: &GitAuthor,
+    policy: MergePolicy,
+    key: Option<&PrivateKey>,
 ) -> Result<(), Error> {
     // 1. do a merge analysis
     let analysis = repo
@@ -108,11 +116,20 @@ pub fn do_merge<'a>(
             }
         };
     } else if analysis.0.is_normal() {
+        if policy == MergePolicy::FastForward {
+            return Err(Error::git2(
+                git2::Error::from_str(
+                    "the histories diverged, and the merge policy is fast-forward",
+                ),
+                "merge",
+            ));
+        }
+
         // do a normal merge
         let head_commit = repo
             .reference_to_annotated_commit(&repo.head()?)
             .map_err(|e| Error::git2(e, "reference_to_annotated_commit"))?;
-        normal_merge(repo, &head_commit, &fetch_commit, author)
+        normal_merge(repo, &head_commit, &fetch_commit, author, key)
             .map_err(|e| Error::git2(e, "normal_merge"))?;
     } else {
         // Nothing to do...
@@ -0,0 +1,155 @@
+use std::{collections::HashMap, path::Path};
+
+use git2::{Oid, Repository};
+
+use super::{
+    rename::renames,
+    timestamps::{Tracked, changed_paths, note_paths},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteMetadata {
+    /// Time of the commit which added the note, following renames, in milliseconds.
+    pub created_at: i64,
+    /// Time of the last commit which modified the note, in milliseconds.
+    pub last_modified: i64,
+    /// Author of the last modification.
+    pub author_name: String,
+    pub author_email: String,
+    /// Number of commits reachable from HEAD which modified the note,
+    /// on every side of the merges.
+    pub revisions: u32,
+}
+
+/// Metadata of every note at HEAD.
+///
+/// Like for the timestamps, a note follows the parent it is TREESAME to.
+/// Other commits are revisions of the note, except the ones only moving it.
+/// The history of a note continues with every parent containing it,
+/// or with the first parent when the commit renamed it.
+pub fn metadata(repo: &Repository) -> Result<HashMap<String, NoteMetadata>, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    let mut res: HashMap<String, NoteMetadata> = HashMap::new();
+
+    let mut live: HashMap<Oid, Vec<Tracked>> = HashMap::new();
+    live.insert(
+        head,
+        note_paths(repo, head)?
+            .into_iter()
+            .map(Tracked::new)
+            .collect(),
+    );
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    // children must be visited before their parents
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+    for oid in revwalk {
+        if live.is_empty() {
+            break;
+        }
+
+        let oid = oid?;
+        let Some(tracked) = live.remove(&oid) else {
+            continue;
+        };
+
+        let commit = repo.find_commit(oid)?;
+        let tree = commit.tree()?;
+        let time = commit.time().seconds() * 1000;
+        let author = commit.author();
+
+        let parents = commit
+            .parents()
+            .map(|parent| {
+                let parent_tree = parent.tree()?;
+                let changed = changed_paths(repo, Some(&parent_tree), &tree)?;
+                Ok((parent.id(), parent_tree, changed))
+            })
+            .collect::<Result<Vec<_>, git2::Error>>()?;
+
+        // computed at most once by commit, only when a note was added
+        let mut commit_renames = None;
+
+        for note in tracked {
+            if let Some((parent, _, _)) = parents
+                .iter()
+                .find(|(_, _, changed)| !changed.contains(&note.name))
+            {
+                follow(&mut live, *parent, note);
+                continue;
+            }
+
+            let name = Path::new(&note.name);
+
+            let in_parents = parents
+                .iter()
+                .filter(|(_, parent_tree, _)| parent_tree.get_path(name).is_ok())
+                .map(|(parent, _, _)| *parent)
+                .collect::<Vec<_>>();
+
+            let rename = match (in_parents.is_empty(), parents.first()) {
+                (true, Some((_, parent_tree, _))) => {
+                    if commit_renames.is_none() {
+                        commit_renames = Some(renames(repo, parent_tree, &tree)?);
+                    }
+                    commit_renames
+                        .as_ref()
+                        .and_then(|r| r.get(&note.name))
+                        .cloned()
+                }
+                _ => None,
+            };
+
+            // moving a note without modifying it is not a revision
+            if let Some(rename) = &rename
+                && rename.exact
+            {
+                let name = rename.from.clone();
+                follow(&mut live, parents[0].0, Tracked { name, ..note });
+                continue;
+            }
+
+            let metadata = res
+                .entry(note.path.clone())
+                .or_insert_with(|| NoteMetadata {
+                    created_at: time,
+                    last_modified: time,
+                    author_name: author.name().unwrap_or_default().to_string(),
+                    author_email: author.email().unwrap_or_default().to_string(),
+                    revisions: 0,
+                });
+
+            metadata.revisions += 1;
+            metadata.created_at = metadata.created_at.min(time);
+
+            if let Some(rename) = rename {
+                follow(
+                    &mut live,
+                    parents[0].0,
+                    Tracked {
+                        name: rename.from,
+                        ..note
+                    },
+                );
+            } else {
+                // without parent containing it, the note was created by this commit
+                for parent in in_parents {
+                    follow(&mut live, parent, note.clone());
+                }
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Continue the history of `note` with `parent`, once even if several children lead to it.
+fn follow(live: &mut HashMap<Oid, Vec<Tracked>>, parent: Oid, note: Tracked) {
+    let notes = live.entry(parent).or_default();
+    if !notes.iter().any(|n| n.path == note.path) {
+        notes.push(note);
+    }
+}
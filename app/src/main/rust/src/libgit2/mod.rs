@@ -6,24 +6,83 @@ use std::{
 };
 
 use git2::{
-    CertificateCheckStatus, FetchOptions, IndexAddOption, Progress, PushOptions, RemoteCallbacks,
-    Repository, Signature, StatusOptions,
+    CertificateCheckStatus, FetchOptions, Progress, PushOptions, RemoteCallbacks, Repository,
+    Signature, StatusOptions,
 };
 
-use crate::{Cred, Error, GitAuthor, callback::ProgressCB, mime_types::is_extension_supported};
+use crate::{
+    Cred, Error, GitAuthor,
+    callback::{ProgressCB, ScanCB},
+    key_store, mime_types,
+};
 
+pub mod attachment;
+pub mod blame;
+pub mod commit_log;
+pub mod config;
+pub mod diff;
+pub mod history;
+pub mod ignore;
+pub mod lfs;
 mod merge;
+pub mod metadata;
+pub mod rename;
+pub mod reset;
+pub mod scan;
+pub mod search;
+pub mod sign;
+pub mod stash;
+pub mod tag;
+mod timestamps;
+pub mod verify;
+
 #[cfg(test)]
 mod test;
 #[cfg(test)]
+mod test_attachment;
+#[cfg(test)]
+mod test_blame;
+#[cfg(test)]
 mod test_clone;
-
+#[cfg(test)]
+mod test_commit_log;
+#[cfg(test)]
+mod test_config;
+#[cfg(test)]
+mod test_diff;
+#[cfg(test)]
+mod test_history;
+#[cfg(test)]
+mod test_ignore;
+#[cfg(test)]
+mod test_lfs;
 #[cfg(test)]
 mod test_merge;
-
-const REMOTE: &str = "origin";
+#[cfg(test)]
+mod test_metadata;
+#[cfg(test)]
+mod test_rename;
+#[cfg(test)]
+mod test_reset;
+#[cfg(test)]
+mod test_scan;
+#[cfg(test)]
+mod test_search;
+#[cfg(test)]
+mod test_sign;
+#[cfg(test)]
+mod test_stash;
+#[cfg(test)]
+mod test_tag;
+#[cfg(test)]
+mod test_timestamps;
+#[cfg(test)]
+mod test_utils;
+#[cfg(test)]
+mod test_verify;
 
 static REPO: LazyLock<Mutex<Option<Repository>>> = LazyLock::new(|| Mutex::new(None));
+static CONFIG: LazyLock<Mutex<config::Loaded>> = LazyLock::new(Default::default);
 
 // https://github.com/libgit2/libgit2/pull/7056
 static HOME_PATH: OnceLock<String> = OnceLock::new();
@@ -113,9 +172,35 @@ pub fn init_lib(home_path: String) {
     };
 }
 
+/// Load the configuration file of the repo.
+///
+/// An invalid file is reported by [`config_errors`], but can't prevent the repo from opening.
+fn load_config(repo: &Repository) {
+    let loaded = config::load(repo);
+
+    for e in &loaded.errors {
+        warn!("{}: {e}", config::CONFIG_FILE);
+    }
+
+    mime_types::set_repo_extensions(loaded.config.extensions.clone());
+    ignore::set_rules(repo, &loaded.config.ignore);
+    *CONFIG.lock().expect("config lock") = loaded;
+}
+
+/// Configuration of the opened repo.
+pub fn repo_config() -> config::Config {
+    CONFIG.lock().expect("config lock").config.clone()
+}
+
+/// What was wrong in the configuration file of the opened repo.
+pub fn config_errors() -> Vec<String> {
+    CONFIG.lock().expect("config lock").errors.clone()
+}
+
 pub fn create_repo(repo_path: &str) -> Result<(), Error> {
     let repo = Repository::init(repo_path).map_err(|e| Error::git2(e, "Repository::init"))?;
 
+    load_config(&repo);
     REPO.lock().unwrap().replace(repo);
 
     Ok(())
@@ -124,6 +209,7 @@ pub fn create_repo(repo_path: &str) -> Result<(), Error> {
 pub fn open_repo(repo_path: &str) -> Result<(), Error> {
     let repo = Repository::open(repo_path).map_err(|e| Error::git2(e, "Repository::open"))?;
 
+    load_config(&repo);
     REPO.lock().unwrap().replace(repo);
 
     Ok(())
@@ -163,6 +249,20 @@ fn credential_helper(
             private_key,
             passphrase.as_deref(),
         ),
+        Cred::SshKeyRef { fingerprint } => {
+            let Some((public_key, private_key)) = key_store::get_key(fingerprint) else {
+                return Err(git2::Error::from_str(
+                    "the ssh key is not in the key store anymore",
+                ));
+            };
+
+            git2::Cred::ssh_key_from_memory(
+                username_from_url.unwrap_or("git"),
+                Some(&public_key),
+                &private_key,
+                None,
+            )
+        }
     }
 }
 
@@ -177,10 +277,10 @@ pub fn clone_repo(
 
     callbacks.certificate_check(|_cert, _| Ok(CertificateCheckStatus::CertificateOk));
 
-    if let Some(cred) = cred {
+    if let Some(cred) = &cred {
         callbacks.credentials(move |_url, username_from_url, _allowed_types| {
             debug!("allowed_types: {:?}", _allowed_types);
-            credential_helper(&cred, username_from_url)
+            credential_helper(cred, username_from_url)
         });
     }
 
@@ -193,7 +293,7 @@ pub fn clone_repo(
     let mut fetch_options = FetchOptions::new();
     fetch_options
         .remote_callbacks(callbacks)
-        .download_tags(git2::AutotagOption::None);
+        .download_tags(git2::AutotagOption::Auto);
 
     let mut builder = git2::build::RepoBuilder::new();
 
@@ -202,6 +302,12 @@ pub fn clone_repo(
         .clone(remote_url, std::path::Path::new(&repo_path))
         .map_err(|e| Error::git2(e, "clone"))?;
 
+    // the clone is usable without them, a pull retries the download
+    if let Err(e) = lfs::pull(&repo, config::DEFAULT_REMOTE, cred.as_ref()) {
+        error!("lfs: {e}");
+    }
+
+    load_config(&repo);
     REPO.lock().unwrap().replace(repo);
 
     Ok(())
@@ -246,9 +352,8 @@ pub fn commit_all(name: &str, email: &str, message: &str) -> Result<(), Error> {
 
     let mut index = repo.index().map_err(|e| Error::git2(e, "index"))?;
 
-    index
-        .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
-        .map_err(|e| Error::git2(e, "add_all"))?;
+    // the lfs files are added as pointers
+    lfs::add_all(repo, &mut index)?;
 
     // Write index to disk
     index.write().map_err(|e| Error::git2(e, "write"))?;
@@ -267,10 +372,16 @@ pub fn commit_all(name: &str, email: &str, message: &str) -> Result<(), Error> {
 
     let sig = Signature::now(name, email).map_err(|e| Error::git2(e, "Signature::now"))?;
 
+    let message = repo_config().commit_message(message);
+
+    let key = sign::signing_key().map_err(|e| Error::git2(e, "signing_key"))?;
+
     // Create commit
     match parent_commit {
-        Some(ref parent) => repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[parent]),
-        None => repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[]),
+        Some(ref parent) => {
+            sign::commit(repo, &sig, &sig, &message, &tree, &[parent], key.as_ref())
+        }
+        None => sign::commit(repo, &sig, &sig, &message, &tree, &[], key.as_ref()),
     }
     .map(|_| ())
     .map_err(|e| Error::git2(e, "commit"))
@@ -282,20 +393,59 @@ pub fn push(cred: Option<Cred>) -> Result<(), Error> {
     let repo = REPO.lock().expect("repo lock");
     let repo = repo.as_ref().expect("repo");
 
-    let mut remote = repo
-        .find_remote(REMOTE)
-        .map_err(|e| Error::git2(e, "find_remote"))?;
-
     let branch = current_branch(repo)?;
     let refspecs = [format!("refs/heads/{branch}:refs/heads/{branch}")];
 
+    // the objects must be on the server before the pointers
+    lfs::push(repo, &repo_config().remote, &branch, cred.as_ref())
+        .map_err(|e| e.add_message("lfs push"))?;
+
+    push_refspecs(repo, cred.as_ref(), &refspecs)
+}
+
+/// Push the tags `names`, or all the local tags when it is empty.
+pub fn push_tags(cred: Option<Cred>, names: &[String]) -> Result<(), Error> {
+    apply_ssh_workaround(false);
+
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    let names = if names.is_empty() {
+        repo.tag_names(None)
+            .map_err(|e| Error::git2(e, "tag_names"))?
+            .iter()
+            .flatten()
+            .flatten()
+            .map(str::to_string)
+            .collect()
+    } else {
+        names.to_vec()
+    };
+
+    let refspecs = names
+        .iter()
+        .map(|name| format!("refs/tags/{name}:refs/tags/{name}"))
+        .collect::<Vec<_>>();
+
+    push_refspecs(repo, cred.as_ref(), &refspecs)
+}
+
+fn push_refspecs(repo: &Repository, cred: Option<&Cred>, refspecs: &[String]) -> Result<(), Error> {
+    if refspecs.is_empty() {
+        return Ok(());
+    }
+
+    let mut remote = repo
+        .find_remote(&repo_config().remote)
+        .map_err(|e| Error::git2(e, "find_remote"))?;
+
     let mut callbacks = RemoteCallbacks::new();
 
     callbacks.certificate_check(|_cert, _| Ok(CertificateCheckStatus::CertificateOk));
 
     if let Some(cred) = cred {
         callbacks.credentials(move |_url, username_from_url, _allowed_types| {
-            credential_helper(&cred, username_from_url)
+            credential_helper(cred, username_from_url)
         });
     }
 
@@ -303,7 +453,7 @@ pub fn push(cred: Option<Cred>) -> Result<(), Error> {
     push_opts.remote_callbacks(callbacks);
 
     remote
-        .push(&refspecs, Some(&mut push_opts))
+        .push(refspecs, Some(&mut push_opts))
         .map_err(|e| Error::git2(e, "push"))?;
 
     Ok(())
@@ -315,8 +465,45 @@ pub fn pull(cred: Option<Cred>, author: &GitAuthor) -> Result<(), Error> {
     let repo = REPO.lock().expect("repo lock");
     let repo = repo.as_ref().expect("repo");
 
+    fetch_and_merge(repo, cred.as_ref(), author)
+}
+
+/// Stash the uncommitted changes, pull, then pop them back.
+///
+/// Returns the conflicts of the pop, in which case the stash is kept.
+pub fn pull_with_stash(
+    cred: Option<Cred>,
+    author: &GitAuthor,
+) -> Result<Vec<stash::Conflict>, Error> {
+    apply_ssh_workaround(false);
+
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    let stashed = stash::save(repo, author, Some("gitnote: before pull"), true)?;
+
+    let res = fetch_and_merge(repo, cred.as_ref(), author);
+
+    if stashed.is_none() {
+        return res.map(|_| Vec::new());
+    }
+
+    // restore the changes even if the pull failed
+    let conflicts = stash::apply(repo, 0, true).map_err(|e| e.add_message("stash pop"))?;
+    res?;
+
+    Ok(conflicts)
+}
+
+fn fetch_and_merge(
+    repo: &Repository,
+    cred: Option<&Cred>,
+    author: &GitAuthor,
+) -> Result<(), Error> {
+    let config = repo_config();
+
     let mut remote = repo
-        .find_remote(REMOTE)
+        .find_remote(&config.remote)
         .map_err(|e| Error::git2(e, "find_remote"))?;
 
     let mut callbacks = RemoteCallbacks::new();
@@ -325,17 +512,21 @@ pub fn pull(cred: Option<Cred>, author: &GitAuthor) -> Result<(), Error> {
 
     if let Some(cred) = cred {
         callbacks.credentials(move |_url, username_from_url, _allowed_types| {
-            credential_helper(&cred, username_from_url)
+            credential_helper(cred, username_from_url)
         });
     }
 
     let mut fetch_options = FetchOptions::new();
     fetch_options
         .remote_callbacks(callbacks)
-        .download_tags(git2::AutotagOption::None);
+        // the tags of the fetched commits, named snapshots of the notes
+        .download_tags(git2::AutotagOption::Auto);
 
     let branch = current_branch(repo)?;
-    let refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
+    let refspec = format!(
+        "+refs/heads/{branch}:refs/remotes/{}/{branch}",
+        config.remote
+    );
     remote
         .fetch(&[&refspec], Some(&mut fetch_options), None)
         .map_err(|e| Error::git2(e, "fetch"))?;
@@ -348,7 +539,32 @@ pub fn pull(cred: Option<Cred>, author: &GitAuthor) -> Result<(), Error> {
         .reference_to_annotated_commit(&fetch_head)
         .map_err(|e| Error::git2(e, "reference_to_annotated_commit"))?;
 
-    merge::do_merge(repo, &branch, commit, author).map_err(|e| e.add_message("do_merge"))?;
+    verify::check_fetched(repo, commit.id()).map_err(|e| e.add_message("check_fetched"))?;
+
+    let head = repo.head().ok().and_then(|head| head.target());
+    lfs::check_download(repo, commit.id(), head, cred)
+        .map_err(|e| e.add_message("lfs check_download"))?;
+
+    let key = sign::signing_key().map_err(|e| Error::git2(e, "signing_key"))?;
+
+    merge::do_merge(
+        repo,
+        &branch,
+        commit,
+        author,
+        config.merge_policy,
+        key.as_ref(),
+    )
+    .map_err(|e| e.add_message("do_merge"))?;
+
+    // the checkout of the merge wrote the pointers of the lfs files,
+    // the merge landed even if their download fails, the next pull retries it
+    if let Err(e) = lfs::pull(repo, &config.remote, cred) {
+        error!("lfs: {e}");
+    }
+
+    // the pull may have changed it
+    load_config(repo);
 
     Ok(())
 }
@@ -356,6 +572,9 @@ pub fn pull(cred: Option<Cred>, author: &GitAuthor) -> Result<(), Error> {
 pub fn close() {
     let mut repo = REPO.lock().expect("repo lock");
     repo.take();
+    search::clear();
+    mime_types::set_repo_extensions(Default::default());
+    *CONFIG.lock().expect("config lock") = Default::default();
 }
 
 pub fn is_change() -> Result<bool, Error> {
@@ -380,46 +599,245 @@ pub fn get_timestamps(
     let repo = REPO.lock().expect("repo lock");
     let repo = repo.as_ref().expect("repo");
 
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-    revwalk.set_sorting(git2::Sort::TIME)?;
+    let timestamps = timestamps::timestamps(repo)?;
 
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
+    for (path, timestamp) in &timestamps {
+        insert(path, *timestamp)?;
+    }
 
-        let current_tree = commit.tree()?;
+    Ok(())
+}
 
-        let parent_tree = if commit.parent_count() > 0 {
-            Some(commit.parent(0)?.tree()?)
-        } else {
-            None
-        };
+pub fn get_note_metadata(
+    mut insert: impl FnMut(&str, &metadata::NoteMetadata) -> Result<(), jni::errors::Error>,
+) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
 
-        let mut opts = git2::DiffOptions::new();
-
-        let diff =
-            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&current_tree), Some(&mut opts))?;
-
-        for delta in diff.deltas() {
-            let path = delta.new_file().path().or_else(|| delta.old_file().path());
-
-            if let Some(path) = path
-                && is_extension_supported(
-                    Path::new(&path)
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .unwrap_or(""),
-                ) {
-                    match path.as_os_str().to_str() {
-                        Some(path) => insert(path, commit.time().seconds() * 1000)?,
-                        None => {
-                            warn!("path can't be converted to str");
-                        }
-                    }
-                }
-        }
+    let metadata = metadata::metadata(repo)?;
+
+    for (path, metadata) in &metadata {
+        insert(path, metadata)?;
     }
 
     Ok(())
 }
+
+pub fn scan_notes(root: &str, max_size: u64, cb: impl ScanCB) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    scan::scan_notes(repo, Path::new(root), max_size, cb)
+}
+
+pub fn search(
+    query: &str,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<search::SearchResult>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    search::search(repo, query, limit, offset)
+}
+
+pub fn search_history(
+    query: &history::HistoryQuery,
+    cb: impl ProgressCB,
+) -> Result<Vec<history::HistoryMatch>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    history::search_history(repo, query, cb)
+}
+
+pub fn diff(
+    path: &str,
+    from: &diff::Side,
+    to: &diff::Side,
+    word_diff: bool,
+) -> Result<Vec<diff::Hunk>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    diff::diff(repo, path, from, to, word_diff)
+}
+
+pub fn blame(path: &str, options: &blame::BlameOptions) -> Result<blame::Blame, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    blame::blame(repo, path, options)
+}
+
+pub fn log(query: &commit_log::LogQuery) -> Result<Vec<commit_log::LogEntry>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    commit_log::log(repo, query)
+}
+
+pub fn move_path(from: &str, to: &str) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    rename::move_path(repo, from, to)
+}
+
+pub fn stash_save(
+    author: &GitAuthor,
+    message: Option<&str>,
+    include_untracked: bool,
+) -> Result<Option<git2::Oid>, Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    stash::save(repo, author, message, include_untracked)
+}
+
+pub fn stash_list() -> Result<Vec<stash::StashEntry>, Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    stash::list(repo)
+}
+
+pub fn stash_apply(index: usize) -> Result<Vec<stash::Conflict>, Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    stash::apply(repo, index, false)
+}
+
+pub fn stash_pop(index: usize) -> Result<Vec<stash::Conflict>, Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    stash::apply(repo, index, true)
+}
+
+pub fn stash_drop(index: usize) -> Result<(), Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    stash::drop(repo, index)
+}
+
+pub fn create_tag(
+    name: &str,
+    revision: &str,
+    message: Option<&str>,
+    tagger: &GitAuthor,
+    force: bool,
+) -> Result<git2::Oid, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    // only the annotated tags are signed
+    let key = match message {
+        Some(_) => sign::signing_key().map_err(|e| Error::git2(e, "signing_key"))?,
+        None => None,
+    };
+
+    tag::create(repo, name, revision, message, tagger, force, key.as_ref())
+}
+
+pub fn list_tags() -> Result<Vec<tag::TagInfo>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    tag::list(repo)
+}
+
+pub fn delete_tag(name: &str) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    tag::delete(repo, name)
+}
+
+pub fn checkout_tag(name: &str, dest: &str) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    tag::checkout(repo, name, Path::new(dest))
+}
+
+pub fn reflog(branch: Option<&str>) -> Result<Vec<reset::ReflogEntry>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    reset::reflog(repo, branch)
+}
+
+/// Returns the backup ref created before a hard reset.
+pub fn reset_to(
+    revision: &str,
+    kind: reset::ResetKind,
+    author: &GitAuthor,
+) -> Result<Option<String>, Error> {
+    let mut repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_mut().expect("repo");
+
+    let sig = Signature::now(&author.name, &author.email)?;
+    reset::reset_to(repo, revision, kind, &sig)
+}
+
+/// Copy the file `source` next to `note`, as an attachment.
+pub fn add_attachment(note: &str, source: &Path) -> Result<attachment::Attachment, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let content = fs::read(source).map_err(|e| Error::io(e, "read"))?;
+
+    attachment::add(workdir, note, extension, &content)
+}
+
+pub fn attachment_references(max_size: u64) -> Result<Vec<attachment::Reference>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    attachment::references(repo, max_size)
+}
+
+/// `path`, relative to the root of the repo, is hidden from the status, the commits and the notes.
+pub fn is_ignored(path: &str) -> Result<bool, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    let is_dir = repo.workdir().is_some_and(|w| w.join(path).is_dir());
+
+    Ok(ignore::is_ignored(repo, Path::new(path), is_dir))
+}
+
+/// Append `patterns` to the `.gitnoteignore` file, and use them.
+pub fn add_ignore_patterns(patterns: &[String]) -> Result<(), Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    ignore::add_patterns(repo, patterns)?;
+    ignore::set_rules(repo, &repo_config().ignore);
+
+    Ok(())
+}
+
+/// Signature status of the last `limit` commits, checked against the allowed signers file.
+pub fn verify_commits(limit: usize) -> Result<Vec<(git2::Oid, verify::SignatureStatus)>, Error> {
+    let repo = REPO.lock().expect("repo lock");
+    let repo = repo.as_ref().expect("repo");
+
+    let signers = match verify::allowed_signers_path(repo) {
+        Some(path) => verify::AllowedSigners::from_file(&path)?,
+        None => verify::AllowedSigners::parse(""),
+    };
+
+    verify::verify_commits(repo, &signers, limit).map_err(|e| Error::git2(e, "verify_commits"))
+}
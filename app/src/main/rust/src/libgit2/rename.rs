@@ -0,0 +1,127 @@
+//! Move notes in the worktree and the index, and detect the renames in the history.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path},
+};
+
+use git2::{Diff, DiffFindOptions, IndexEntry, Repository, Tree};
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    /// Path before the rename.
+    pub from: String,
+    /// The content was not modified.
+    pub exact: bool,
+}
+
+/// Renamed files of `diff`, by their new path.
+pub(super) fn diff_renames(diff: &mut Diff) -> Result<HashMap<String, Rename>, git2::Error> {
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() == git2::Delta::Renamed)
+        .filter_map(|delta| {
+            let from = delta.old_file().path()?.to_str()?.to_string();
+            let to = delta.new_file().path()?.to_str()?.to_string();
+            let exact = delta.old_file().id() == delta.new_file().id();
+
+            Some((to, Rename { from, exact }))
+        })
+        .collect())
+}
+
+/// Files renamed between `parent` and `commit`, by their new path.
+pub(super) fn renames(
+    repo: &Repository,
+    parent: &Tree,
+    commit: &Tree,
+) -> Result<HashMap<String, Rename>, git2::Error> {
+    let mut diff = repo.diff_tree_to_tree(Some(parent), Some(commit), None)?;
+    diff_renames(&mut diff)
+}
+
+/// `path` only has normal components, so it stays in the worktree once joined to it.
+pub(super) fn is_in_worktree(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Rename the file or folder `from` to `to`, in the worktree and in the index.
+///
+/// Staged content is kept as is. If the index can't be updated,
+/// the worktree is restored.
+pub fn move_path(repo: &Repository, from: &str, to: &str) -> Result<(), Error> {
+    let from = from.trim_matches('/');
+    let to = to.trim_matches('/');
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("bare repository"))?;
+    let (from_path, to_path) = (workdir.join(from), workdir.join(to));
+
+    if from.is_empty()
+        || to.is_empty()
+        || !is_in_worktree(from)
+        || !is_in_worktree(to)
+        || Path::new(to).starts_with(from)
+    {
+        return Err(Error::git2(
+            git2::Error::from_str(&format!("can't move {from} to {to}")),
+            "move_path",
+        ));
+    }
+    if !from_path.exists() {
+        return Err(Error::git2(
+            git2::Error::from_str(&format!("{from} doesn't exist")),
+            "move_path",
+        ));
+    }
+    if to_path.exists() {
+        return Err(Error::git2(
+            git2::Error::from_str(&format!("{to} already exists")),
+            "move_path",
+        ));
+    }
+
+    let mut index = repo.index()?;
+    let folder_prefix = format!("{from}/");
+
+    let entries = index
+        .iter()
+        .filter(|entry| {
+            entry.path == from.as_bytes() || entry.path.starts_with(folder_prefix.as_bytes())
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(parent) = to_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(e, "move_path"))?;
+    }
+    fs::rename(&from_path, &to_path).map_err(|e| Error::io(e, "move_path"))?;
+
+    let update_index = || -> Result<(), git2::Error> {
+        for entry in entries {
+            let old_path = std::str::from_utf8(&entry.path)
+                .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+            index.remove_path(Path::new(old_path))?;
+
+            let path = [to.as_bytes(), &entry.path[from.len()..]].concat();
+            index.add(&IndexEntry { path, ..entry })?;
+        }
+        index.write()
+    };
+
+    if let Err(e) = update_index() {
+        if let Err(e) = fs::rename(&to_path, &from_path) {
+            error!("can't restore {from}: {e}");
+        }
+        return Err(Error::git2(e, "move_path"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Recover from a bad sync: list the movements of HEAD and reset to a previous commit.
+
+use anyhow::anyhow;
+use git2::{Oid, Repository, ResetType, Signature, StashFlags};
+
+use crate::Error;
+
+/// Namespace of the refs created before a hard reset.
+pub const BACKUP_REFS: &str = "refs/gitnote/backup";
+
+// important: must match the order of the ResetKind enum in kotlin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Only move the branch.
+    Soft = 0,
+    /// Also reset the index.
+    Mixed = 1,
+    /// Also reset the worktree.
+    Hard = 2,
+}
+
+impl TryFrom<i32> for ResetKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Soft),
+            1 => Ok(Self::Mixed),
+            2 => Ok(Self::Hard),
+            other => Err(anyhow!("invalid reset kind: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    pub old: Oid,
+    pub new: Oid,
+    /// Like `Fast-Forward: Setting ...` or `reset: moving to ...`.
+    pub message: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    /// In milliseconds.
+    pub time: i64,
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Movements of the branch `branch`, or of HEAD when it is `None`, newest first.
+pub fn reflog(repo: &Repository, branch: Option<&str>) -> Result<Vec<ReflogEntry>, Error> {
+    let name = match branch {
+        Some(branch) if branch.starts_with("refs/") => branch.to_string(),
+        Some(branch) => format!("refs/heads/{branch}"),
+        None => "HEAD".to_string(),
+    };
+
+    let reflog = repo.reflog(&name).map_err(|e| Error::git2(e, "reflog"))?;
+
+    Ok(reflog
+        .iter()
+        .map(|entry| {
+            let committer = entry.committer();
+
+            ReflogEntry {
+                old: entry.id_old(),
+                new: entry.id_new(),
+                message: lossy(entry.message_bytes().unwrap_or_default()),
+                committer_name: lossy(committer.name_bytes()),
+                committer_email: lossy(committer.email_bytes()),
+                time: committer.when().seconds() * 1000,
+            }
+        })
+        .collect())
+}
+
+/// Save HEAD and the uncommitted changes under [`BACKUP_REFS`].
+///
+/// The backup is a stash commit when there are uncommitted changes,
+/// otherwise the HEAD commit.
+fn backup(repo: &mut Repository, stasher: &Signature) -> Result<String, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    let stash = match repo.stash_save2(
+        stasher,
+        Some("gitnote: before reset"),
+        Some(StashFlags::INCLUDE_UNTRACKED),
+    ) {
+        Ok(oid) => Some(oid),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let oid = stash.unwrap_or(head);
+    let name = format!("{BACKUP_REFS}/{}-{oid}", stasher.when().seconds());
+    repo.reference(&name, oid, true, "backup before reset")?;
+
+    // the backup ref keeps it alive
+    if stash.is_some() {
+        repo.stash_drop(0)?;
+    }
+
+    Ok(name)
+}
+
+/// Reset the current branch to `revision`.
+///
+/// Before a hard reset, a backup ref is created, which is returned.
+pub fn reset_to(
+    repo: &mut Repository,
+    revision: &str,
+    kind: ResetKind,
+    stasher: &Signature,
+) -> Result<Option<String>, Error> {
+    let target = repo
+        .revparse_single(revision)?
+        .peel_to_commit()
+        .map_err(|e| Error::git2(e, "peel_to_commit"))?
+        .id();
+
+    let backup = match kind {
+        ResetKind::Hard => Some(backup(repo, stasher).map_err(|e| Error::git2(e, "backup"))?),
+        _ => None,
+    };
+
+    let reset_type = match kind {
+        ResetKind::Soft => ResetType::Soft,
+        ResetKind::Mixed => ResetType::Mixed,
+        ResetKind::Hard => ResetType::Hard,
+    };
+
+    let target = repo.find_object(target, None)?;
+    repo.reset(&target, reset_type, None)
+        .map_err(|e| Error::git2(e, "reset"))?;
+
+    Ok(backup)
+}
@@ -0,0 +1,205 @@
+//! Walk the worktree and load the notes, to rebuild the database of the app.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use git2::Repository;
+
+use crate::{Error, callback::ScanCB, mime_types::is_note_name};
+
+use super::{
+    ignore,
+    timestamps::{self, Timestamps},
+};
+
+/// Number of notes (or folders) sent to the callback at once.
+pub const BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedNote {
+    /// Relative to the scanned root.
+    pub path: String,
+    pub size: u64,
+    pub content: String,
+    /// Last commit modifying the note, or the modification time of the file, in milliseconds.
+    pub timestamp: i64,
+}
+
+pub(super) struct FileEntry {
+    pub path: String,
+    pub full_path: PathBuf,
+    pub size: u64,
+}
+
+fn is_ignored(repo: &Repository, path: &Path, is_dir: bool) -> bool {
+    repo.workdir()
+        .and_then(|w| path.strip_prefix(w).ok())
+        .is_some_and(|relative| ignore::is_ignored(repo, relative, is_dir))
+}
+
+/// Folders and notes under `root`, skipping hidden folders, symlinks,
+/// ignored paths and files bigger than `max_size`.
+pub(super) fn collect(
+    repo: &Repository,
+    root: &Path,
+    max_size: u64,
+) -> Result<(Vec<String>, Vec<FileEntry>), Error> {
+    let mut folders = Vec::new();
+    let mut files = Vec::new();
+
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| Error::io(e, &dir.to_string_lossy()))? {
+            let entry = entry.map_err(|e| Error::io(e, &dir.to_string_lossy()))?;
+            let full_path = entry.path();
+            let file_type = entry
+                .file_type()
+                .map_err(|e| Error::io(e, &full_path.to_string_lossy()))?;
+
+            let Some(path) = full_path
+                .strip_prefix(root)
+                .ok()
+                .and_then(|p| p.to_str())
+                .map(str::to_string)
+            else {
+                warn!("skipped non utf-8 path: {}", full_path.display());
+                continue;
+            };
+
+            if file_type.is_dir() {
+                if entry.file_name().to_string_lossy().starts_with('.')
+                    || is_ignored(repo, &full_path, true)
+                {
+                    continue;
+                }
+                folders.push(path);
+                stack.push(full_path);
+            } else if file_type.is_file() {
+                let name = entry.file_name();
+
+                if !is_note_name(&name.to_string_lossy()) || is_ignored(repo, &full_path, false) {
+                    continue;
+                }
+
+                let size = entry
+                    .metadata()
+                    .map_err(|e| Error::io(e, &full_path.to_string_lossy()))?
+                    .len();
+                if size > max_size {
+                    debug!("skipped {path} because size was above {max_size} ({size})");
+                    continue;
+                }
+
+                files.push(FileEntry {
+                    path,
+                    full_path,
+                    size,
+                });
+            }
+        }
+    }
+
+    Ok((folders, files))
+}
+
+fn load(file: &FileEntry, timestamps: &Timestamps) -> Result<ScannedNote, Error> {
+    let bytes =
+        fs::read(&file.full_path).map_err(|e| Error::io(e, &file.full_path.to_string_lossy()))?;
+
+    let timestamp = match timestamps.get(&file.path) {
+        Some(timestamp) => *timestamp,
+        None => fs::metadata(&file.full_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default(),
+    };
+
+    Ok(ScannedNote {
+        path: file.path.clone(),
+        size: file.size,
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+        timestamp,
+    })
+}
+
+/// Send every folder and note under `root` to `cb`, in batches of [`BATCH_SIZE`].
+///
+/// Folders are sent first. The notes are read in parallel, so their order is not stable.
+/// Stops as soon as `cb` returns `false`.
+pub fn scan_notes(
+    repo: &Repository,
+    root: &Path,
+    max_size: u64,
+    mut cb: impl ScanCB,
+) -> Result<(), Error> {
+    let timestamps = match timestamps::timestamps(repo) {
+        Ok(timestamps) => timestamps,
+        // no commit yet
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Timestamps::new(),
+        Err(e) => return Err(Error::git2(e, "timestamps")),
+    };
+
+    let (folders, files) = collect(repo, root, max_size)?;
+    debug!("scan: {} folders, {} notes", folders.len(), files.len());
+
+    for chunk in folders.chunks(BATCH_SIZE) {
+        if !cb.batch(Vec::new(), chunk.to_vec()) {
+            return Ok(());
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+
+    thread::scope(|s| {
+        let (tx, rx) = mpsc::sync_channel(BATCH_SIZE);
+
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, stop, files, timestamps) = (&next, &stop, &files, &timestamps);
+
+            s.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        break;
+                    };
+                    if tx.send(load(file, timestamps)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // returning drops the receiver, which stops the workers blocked on a full channel
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+        for note in rx {
+            let note = note.inspect_err(|_| stop.store(true, Ordering::Relaxed))?;
+            batch.push(note);
+
+            if batch.len() >= BATCH_SIZE && !cb.batch(std::mem::take(&mut batch), Vec::new()) {
+                stop.store(true, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+
+        if !batch.is_empty() {
+            cb.batch(batch, Vec::new());
+        }
+
+        Ok(())
+    })
+}
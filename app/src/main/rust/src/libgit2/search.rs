@@ -0,0 +1,547 @@
+//! Full-text index of the notes at HEAD, stored in `.git/gitnote/search`
+//! and updated from the diff between the indexed commit and HEAD.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use git2::{Delta, Oid, Repository};
+
+use crate::{Error, mime_types};
+
+use super::timestamps::{self, Timestamps, is_note};
+
+const CACHE_DIR: &str = "gitnote";
+const CACHE_FILE: &str = "search";
+
+/// Notes modified this number of days before the newest note get half the recency bonus.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+static INDEX: LazyLock<Mutex<Option<(PathBuf, Index)>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Forget the index kept in memory.
+pub fn clear() {
+    INDEX.lock().expect("search index lock").take();
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana, katakana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+    )
+}
+
+/// Lowercase words of `text`, split on anything which is not alphanumeric.
+///
+/// Languages written without spaces are split on each ideograph.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            current.extend(c.to_lowercase());
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub path: String,
+    pub score: f64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Clause {
+    Term(String),
+    /// `word*`
+    Prefix(String),
+    /// `"some words"`
+    Phrase(Vec<String>),
+}
+
+fn parse_query(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+
+    for (i, part) in query.split('"').enumerate() {
+        // odd parts are between quotes
+        if i % 2 == 1 {
+            let mut words = tokenize(part);
+            match words.len() {
+                0 => {}
+                1 => clauses.push(Clause::Term(words.remove(0))),
+                _ => clauses.push(Clause::Phrase(words)),
+            }
+            continue;
+        }
+
+        for word in part.split_whitespace() {
+            let mut words = tokenize(word);
+            let prefix = if word.ends_with('*') {
+                words.pop()
+            } else {
+                None
+            };
+
+            clauses.extend(words.into_iter().map(Clause::Term));
+            clauses.extend(prefix.map(Clause::Prefix));
+        }
+    }
+
+    clauses
+}
+
+struct Posting {
+    doc: u32,
+    positions: Vec<u32>,
+}
+
+/// Inverted index: term -> documents containing it, with the positions of the term.
+#[derive(Default)]
+pub struct Index {
+    head: Option<Oid>,
+    /// [`mime_types::extensions_fingerprint`], the indexed notes depend on it.
+    extensions: u64,
+    /// Path of each document id, `None` once removed.
+    docs: Vec<Option<String>>,
+    /// Recency weight of each document id, between 0 and 1.
+    recency: Vec<f64>,
+    by_path: HashMap<String, u32>,
+    /// Postings are sorted by document id.
+    terms: BTreeMap<String, Vec<Posting>>,
+}
+
+impl Index {
+    fn add(&mut self, path: String, content: &str) {
+        let doc = self.docs.len() as u32;
+
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        for (position, token) in tokenize(content).into_iter().enumerate() {
+            positions.entry(token).or_default().push(position as u32);
+        }
+
+        for (term, positions) in positions {
+            self.terms
+                .entry(term)
+                .or_default()
+                .push(Posting { doc, positions });
+        }
+
+        self.by_path.insert(path.clone(), doc);
+        self.docs.push(Some(path));
+        self.recency.push(0.0);
+    }
+
+    /// Weight the documents by the age of their last modification,
+    /// relative to the newest note.
+    fn set_recency(&mut self, timestamps: &Timestamps) {
+        let newest = timestamps.values().max().copied().unwrap_or_default();
+
+        for (path, recency) in self.docs.iter().zip(&mut self.recency) {
+            let Some(path) = path else {
+                continue;
+            };
+
+            let age_days = timestamps
+                .get(path)
+                .map_or(f64::INFINITY, |t| (newest - t) as f64 / 86_400_000.0);
+            *recency = 0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        }
+    }
+
+    fn remove(&mut self, paths: &HashSet<String>) {
+        let removed = paths
+            .iter()
+            .filter_map(|path| self.by_path.remove(path))
+            .collect::<HashSet<_>>();
+
+        if removed.is_empty() {
+            return;
+        }
+
+        for doc in &removed {
+            self.docs[*doc as usize] = None;
+        }
+
+        self.terms.retain(|_, postings| {
+            postings.retain(|posting| !removed.contains(&posting.doc));
+            !postings.is_empty()
+        });
+    }
+
+    fn len(&self) -> usize {
+        self.by_path.len()
+    }
+
+    /// Index the notes changed between the indexed commit and HEAD.
+    ///
+    /// Return `false` if the index was already up to date.
+    fn update(&mut self, repo: &Repository, head: Oid) -> Result<bool, git2::Error> {
+        let extensions = mime_types::extensions_fingerprint();
+        // other files are notes: start over
+        if self.extensions != extensions {
+            *self = Index::default();
+        }
+
+        if self.head == Some(head) {
+            return Ok(false);
+        }
+
+        let old_tree = match self.head.map(|oid| repo.find_commit(oid)) {
+            Some(Ok(commit)) => Some(commit.tree()?),
+            // the indexed commit doesn't exist anymore
+            Some(Err(_)) => {
+                *self = Index::default();
+                None
+            }
+            None => None,
+        };
+        let new_tree = repo.find_commit(head)?.tree()?;
+
+        let diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)?;
+
+        let mut removed = HashSet::new();
+        let mut added = Vec::new();
+
+        for delta in diff.deltas() {
+            if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
+                removed.insert(path.to_string());
+            }
+
+            if delta.status() == Delta::Deleted {
+                continue;
+            }
+
+            let new_file = delta.new_file();
+            if let Some(path) = new_file.path().and_then(|p| p.to_str())
+                && is_note(Path::new(path))
+            {
+                removed.insert(path.to_string());
+                added.push((path.to_string(), new_file.id()));
+            }
+        }
+
+        debug!(
+            "search index: {} removed, {} added",
+            removed.len(),
+            added.len()
+        );
+
+        self.remove(&removed);
+
+        for (path, oid) in added {
+            let blob = repo.find_blob(oid)?;
+            self.add(path, &String::from_utf8_lossy(blob.content()));
+        }
+
+        // the newest note may have changed, so every weight is updated
+        self.set_recency(&timestamps::timestamps(repo)?);
+
+        self.head = Some(head);
+        self.extensions = extensions;
+        Ok(true)
+    }
+
+    /// Documents matching `clause`, with the score of the clause for each of them.
+    fn matches(&self, clause: &Clause) -> HashMap<u32, f64> {
+        let mut frequencies: HashMap<u32, usize> = HashMap::new();
+
+        match clause {
+            Clause::Term(term) => {
+                for posting in self.terms.get(term).into_iter().flatten() {
+                    frequencies.insert(posting.doc, posting.positions.len());
+                }
+            }
+            Clause::Prefix(prefix) => {
+                let terms = self
+                    .terms
+                    .range(prefix.clone()..)
+                    .take_while(|(term, _)| term.starts_with(prefix.as_str()));
+
+                for (_, postings) in terms {
+                    for posting in postings {
+                        *frequencies.entry(posting.doc).or_default() += posting.positions.len();
+                    }
+                }
+            }
+            Clause::Phrase(words) => {
+                let Some(postings) = words
+                    .iter()
+                    .map(|word| self.terms.get(word))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return HashMap::new();
+                };
+
+                for first in postings[0] {
+                    let next_words = postings[1..]
+                        .iter()
+                        .map(|postings| {
+                            postings
+                                .binary_search_by_key(&first.doc, |p| p.doc)
+                                .ok()
+                                .map(|i| &postings[i].positions)
+                        })
+                        .collect::<Option<Vec<_>>>();
+
+                    let Some(next_words) = next_words else {
+                        continue;
+                    };
+
+                    let count = first
+                        .positions
+                        .iter()
+                        .filter(|&&start| {
+                            next_words.iter().enumerate().all(|(i, positions)| {
+                                positions.binary_search(&(start + i as u32 + 1)).is_ok()
+                            })
+                        })
+                        .count();
+
+                    if count > 0 {
+                        frequencies.insert(first.doc, count);
+                    }
+                }
+            }
+        }
+
+        let idf = (1.0 + self.len() as f64 / frequencies.len().max(1) as f64).ln();
+
+        frequencies
+            .into_iter()
+            .map(|(doc, frequency)| (doc, (1.0 + (frequency as f64).ln()) * idf))
+            .collect()
+    }
+
+    /// Documents matching every clause of `query`, ranked by term frequency and recency.
+    pub fn search(&self, query: &str, limit: usize, offset: usize) -> Vec<SearchResult> {
+        let clauses = parse_query(query);
+
+        let mut scores: Option<HashMap<u32, f64>> = None;
+
+        for clause in &clauses {
+            let matches = self.matches(clause);
+
+            scores = Some(match scores {
+                None => matches,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(doc, score)| Some((doc, score + matches.get(&doc)?)))
+                    .collect(),
+            });
+        }
+
+        let mut results = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(doc, score)| {
+                let path = self.docs[doc as usize].as_ref()?;
+
+                Some(SearchResult {
+                    path: path.clone(),
+                    score: score * (1.0 + self.recency[doc as usize]),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        results.into_iter().skip(offset).take(limit).collect()
+    }
+}
+
+fn cache_path(repo: &Repository) -> PathBuf {
+    repo.path().join(CACHE_DIR).join(CACHE_FILE)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Format: the commit oid and the fingerprint of the extensions, the number of documents,
+/// one `recency\tpath` line per document,
+/// then one `term\tdoc:position,position doc:position` line per term.
+fn read_index(path: &Path) -> io::Result<Index> {
+    let mut lines = BufReader::new(fs::File::open(path)?).lines();
+    let mut next_line = || {
+        lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| invalid_data("truncated index".into()))
+    };
+
+    let header = next_line()?;
+    // the fingerprint is missing from the old indexes
+    let (head, extensions) = header.split_once(' ').unwrap_or((&header, "0"));
+    let head = Oid::from_str(head).map_err(|e| invalid_data(e.to_string()))?;
+    let extensions = extensions
+        .parse::<u64>()
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let count = next_line()?
+        .parse::<usize>()
+        .map_err(|e| invalid_data(e.to_string()))?;
+
+    let mut index = Index {
+        head: Some(head),
+        extensions,
+        ..Default::default()
+    };
+
+    for doc in 0..count {
+        let line = next_line()?;
+        let Some((recency, path)) = line
+            .split_once('\t')
+            .and_then(|(recency, path)| Some((recency.parse::<f64>().ok()?, path)))
+        else {
+            return Err(invalid_data(format!("invalid index document: {line}")));
+        };
+
+        index.by_path.insert(path.to_string(), doc as u32);
+        index.docs.push(Some(path.to_string()));
+        index.recency.push(recency);
+    }
+
+    let parse_posting = |posting: &str| -> Option<Posting> {
+        let (doc, positions) = posting.split_once(':')?;
+        let doc = doc
+            .parse::<u32>()
+            .ok()
+            .filter(|doc| (*doc as usize) < count)?;
+        let positions = positions
+            .split(',')
+            .map(|p| p.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Posting { doc, positions })
+    };
+
+    while let Ok(line) = next_line() {
+        let postings = line.split_once('\t').and_then(|(term, postings)| {
+            let postings = postings
+                .split(' ')
+                .map(parse_posting)
+                .collect::<Option<Vec<_>>>()?;
+            Some((term.to_string(), postings))
+        });
+
+        let Some((term, postings)) = postings else {
+            return Err(invalid_data(format!("invalid index line: {line}")));
+        };
+        index.terms.insert(term, postings);
+    }
+
+    Ok(index)
+}
+
+fn write_index(path: &Path, index: &Index) -> io::Result<()> {
+    let Some(head) = index.head else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(path.parent().expect("cache dir"))?;
+
+    // renumber the documents, without the removed ones
+    let mut ids = HashMap::new();
+    let mut paths = Vec::new();
+    for (doc, path) in index.docs.iter().enumerate() {
+        if let Some(path) = path.as_ref().filter(|p| !p.contains('\n')) {
+            ids.insert(doc as u32, paths.len());
+            paths.push((index.recency[doc], path));
+        }
+    }
+
+    // write in a temporary file first, so a crash can't leave a truncated index
+    let tmp_path = path.with_extension("tmp");
+    let mut file = BufWriter::new(fs::File::create(&tmp_path)?);
+
+    writeln!(file, "{head} {}", index.extensions)?;
+    writeln!(file, "{}", paths.len())?;
+    for (recency, path) in paths {
+        writeln!(file, "{recency}\t{path}")?;
+    }
+
+    for (term, postings) in &index.terms {
+        let postings = postings
+            .iter()
+            .filter_map(|posting| {
+                let doc = ids.get(&posting.doc)?;
+                let positions = posting
+                    .positions
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Some(format!("{doc}:{positions}"))
+            })
+            .collect::<Vec<_>>();
+
+        if !postings.is_empty() {
+            writeln!(file, "{term}\t{}", postings.join(" "))?;
+        }
+    }
+
+    file.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Search the notes at HEAD, updating the index first if needed.
+pub fn search(
+    repo: &Repository,
+    query: &str,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<SearchResult>, Error> {
+    let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+        Ok(commit) => commit.id(),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => return Ok(Vec::new()),
+        Err(e) => return Err(Error::git2(e, "head")),
+    };
+
+    let mut cached = INDEX.lock().expect("search index lock");
+
+    let index = match cached.take() {
+        Some((git_dir, index)) if git_dir == repo.path() => index,
+        _ => match read_index(&cache_path(repo)) {
+            Ok(index) => index,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("search index: {e}");
+                }
+                Index::default()
+            }
+        },
+    };
+    let (_, index) = cached.insert((repo.path().to_path_buf(), index));
+
+    if index
+        .update(repo, head)
+        .map_err(|e| Error::git2(e, "update search index"))?
+        && let Err(e) = write_index(&cache_path(repo), index)
+    {
+        warn!("can't write the search index: {e}");
+    }
+
+    Ok(index.search(query, limit, offset))
+}
@@ -0,0 +1,171 @@
+use std::sync::{LazyLock, Mutex};
+
+use git2::{Commit, Object, ObjectType, Oid, Repository, Signature, Tree};
+use ssh_key::{HashAlg, LineEnding, PrivateKey};
+
+use crate::key_store;
+
+/// Namespace used by git for ssh signatures (`gpg.format=ssh`).
+pub const GIT_NAMESPACE: &str = "git";
+
+/// Fingerprint of the key, in the key store, used to sign the commits of the app.
+static SIGNING_KEY: LazyLock<Mutex<Option<String>>> = LazyLock::new(|| Mutex::new(None));
+
+pub fn set_signing_key(fingerprint: Option<String>) {
+    *SIGNING_KEY.lock().expect("signing key lock") = fingerprint;
+}
+
+/// The key set with [`set_signing_key`], for the functions of this module.
+pub fn signing_key() -> Result<Option<PrivateKey>, git2::Error> {
+    let fingerprint = SIGNING_KEY.lock().expect("signing key lock").clone();
+
+    let Some(fingerprint) = fingerprint else {
+        return Ok(None);
+    };
+
+    // Fail instead of creating an unsigned commit
+    let Some((_public, private)) = key_store::get_key(&fingerprint) else {
+        return Err(git2::Error::from_str(
+            "the signing key is not in the key store anymore",
+        ));
+    };
+
+    PrivateKey::from_openssh(private.as_str())
+        .map(Some)
+        .map_err(|e| git2::Error::from_str(&format!("signing key: {e}")))
+}
+
+/// Armored ssh signature of `content`, in the format written by `git commit -S`.
+pub fn sign_buffer(key: &PrivateKey, content: &[u8]) -> Result<String, git2::Error> {
+    key.sign(GIT_NAMESPACE, HashAlg::Sha512, content)
+        .and_then(|sig| sig.to_pem(LineEnding::LF))
+        .map_err(|e| git2::Error::from_str(&format!("sign: {e}")))
+}
+
+/// Point HEAD, or the branch HEAD refers to, at `oid`.
+///
+/// Like `repo.commit(Some("HEAD"), ...)`, fail if the tip is not `parent` anymore.
+fn update_head(
+    repo: &Repository,
+    oid: Oid,
+    parent: Option<Oid>,
+    log_message: &str,
+) -> Result<(), git2::Error> {
+    let head = repo.find_reference("HEAD")?;
+    let name = head.symbolic_target()?.unwrap_or("HEAD");
+
+    match parent {
+        Some(parent) => repo.reference_matching(name, oid, true, parent, log_message)?,
+        // the branch is unborn, so it must not exist yet
+        None => repo.reference(name, oid, false, log_message)?,
+    };
+
+    Ok(())
+}
+
+/// Same as `repo.commit(Some("HEAD"), ...)`, but sign the commit with `key`.
+pub fn commit(
+    repo: &Repository,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &Tree,
+    parents: &[&Commit],
+    key: Option<&PrivateKey>,
+) -> Result<Oid, git2::Error> {
+    let Some(key) = key else {
+        return repo.commit(Some("HEAD"), author, committer, message, tree, parents);
+    };
+
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let content = buffer.as_str()?;
+
+    let signature = sign_buffer(key, content.as_bytes())?;
+
+    let oid = repo.commit_signed(content, &signature, Some("gpgsig"))?;
+
+    let summary = message.lines().next().unwrap_or_default();
+    let log_message = match parents.len() {
+        0 => format!("commit (initial): {summary}"),
+        1 => format!("commit: {summary}"),
+        _ => format!("commit (merge): {summary}"),
+    };
+
+    let parent = parents.first().map(|parent| parent.id());
+    update_head(repo, oid, parent, &log_message)?;
+
+    Ok(oid)
+}
+
+/// Signature line of a git object, like `Name <email> 1700000000 +0100`.
+fn signature_line(sig: &Signature) -> String {
+    let offset = sig.when().offset_minutes();
+    let sign = if offset < 0 { '-' } else { '+' };
+
+    format!(
+        "{} <{}> {} {sign}{:02}{:02}",
+        String::from_utf8_lossy(sig.name_bytes()),
+        String::from_utf8_lossy(sig.email_bytes()),
+        sig.when().seconds(),
+        offset.abs() / 60,
+        offset.abs() % 60,
+    )
+}
+
+/// Create the annotated tag `name`, signed with `key` like `git tag -s`:
+/// the signature is appended to the message.
+fn signed_tag(
+    repo: &Repository,
+    name: &str,
+    target: &Object,
+    tagger: &Signature,
+    message: &str,
+    force: bool,
+    key: &PrivateKey,
+) -> Result<Oid, git2::Error> {
+    let refname = format!("refs/tags/{name}");
+
+    if !force && repo.find_reference(&refname).is_ok() {
+        return Err(git2::Error::new(
+            git2::ErrorCode::Exists,
+            git2::ErrorClass::Tag,
+            format!("tag {name} already exists"),
+        ));
+    }
+
+    let kind = target
+        .kind()
+        .map(|kind| kind.str())
+        .ok_or_else(|| git2::Error::from_str("unknown object type"))?;
+
+    let content = format!(
+        "object {}\ntype {kind}\ntag {name}\ntagger {}\n\n{message}",
+        target.id(),
+        signature_line(tagger),
+    );
+    let signature = sign_buffer(key, content.as_bytes())?;
+
+    let oid = repo
+        .odb()?
+        .write(ObjectType::Tag, format!("{content}{signature}").as_bytes())?;
+
+    repo.reference(&refname, oid, force, &format!("tag: {name}"))?;
+
+    Ok(oid)
+}
+
+/// Same as `repo.tag(...)`, but sign the tag with `key`.
+pub fn tag(
+    repo: &Repository,
+    name: &str,
+    target: &Object,
+    tagger: &Signature,
+    message: &str,
+    force: bool,
+    key: Option<&PrivateKey>,
+) -> Result<Oid, git2::Error> {
+    match key {
+        Some(key) => signed_tag(repo, name, target, tagger, message, force, key),
+        None => repo.tag(name, target, tagger, message, force),
+    }
+}
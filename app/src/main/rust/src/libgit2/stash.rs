@@ -0,0 +1,130 @@
+//! Put the uncommitted changes aside, to sync without committing them.
+
+use git2::{ErrorCode, IndexEntry, Oid, Repository, Signature, StashApplyOptions, StashFlags};
+
+use crate::{Error, GitAuthor};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    /// Position in the stash list, 0 being the most recent.
+    pub index: usize,
+    pub message: String,
+    pub oid: Oid,
+    /// Time of the stash, in milliseconds.
+    pub time: i64,
+}
+
+/// A file which couldn't be restored without conflict.
+///
+/// Each side is the blob of this file, `None` when it doesn't exist on this side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub path: String,
+    pub ancestor: Option<Oid>,
+    /// Version of the worktree.
+    pub ours: Option<Oid>,
+    /// Version of the stash.
+    pub theirs: Option<Oid>,
+}
+
+/// Stash the changes of the index and the worktree.
+///
+/// Returns `None` when there is nothing to stash.
+pub fn save(
+    repo: &mut Repository,
+    author: &GitAuthor,
+    message: Option<&str>,
+    include_untracked: bool,
+) -> Result<Option<Oid>, Error> {
+    let sig = Signature::now(&author.name, &author.email)?;
+
+    let mut flags = StashFlags::DEFAULT;
+    if include_untracked {
+        flags |= StashFlags::INCLUDE_UNTRACKED;
+    }
+
+    match repo.stash_save2(&sig, message, Some(flags)) {
+        Ok(oid) => Ok(Some(oid)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(Error::git2(e, "stash_save")),
+    }
+}
+
+pub fn list(repo: &mut Repository) -> Result<Vec<StashEntry>, Error> {
+    let mut stashes = Vec::new();
+
+    repo.stash_foreach(|index, message, oid| {
+        stashes.push((index, message.to_string(), *oid));
+        true
+    })?;
+
+    stashes
+        .into_iter()
+        .map(|(index, message, oid)| {
+            let time = repo.find_commit(oid)?.time().seconds() * 1000;
+            Ok(StashEntry {
+                index,
+                message,
+                oid,
+                time,
+            })
+        })
+        .collect()
+}
+
+fn path(entry: &IndexEntry) -> String {
+    String::from_utf8_lossy(&entry.path).into_owned()
+}
+
+fn conflicts(repo: &Repository) -> Result<Vec<Conflict>, git2::Error> {
+    let index = repo.index()?;
+
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+
+    index
+        .conflicts()?
+        .map(|conflict| {
+            let conflict = conflict?;
+            let path = [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .map(path)
+                .next()
+                .unwrap_or_default();
+
+            Ok(Conflict {
+                path,
+                ancestor: conflict.ancestor.map(|e| e.id),
+                ours: conflict.our.map(|e| e.id),
+                theirs: conflict.their.map(|e| e.id),
+            })
+        })
+        .collect()
+}
+
+/// Restore the stash at `index` in the worktree.
+///
+/// With `pop`, the stash is dropped, unless there are conflicts:
+/// they are left in the index and the worktree, with conflict markers.
+pub fn apply(repo: &mut Repository, index: usize, pop: bool) -> Result<Vec<Conflict>, Error> {
+    let mut options = StashApplyOptions::new();
+
+    repo.stash_apply(index, Some(&mut options))
+        .map_err(|e| Error::git2(e, "stash_apply"))?;
+
+    let conflicts = conflicts(repo)?;
+
+    if pop && conflicts.is_empty() {
+        repo.stash_drop(index)
+            .map_err(|e| Error::git2(e, "stash_drop"))?;
+    }
+
+    Ok(conflicts)
+}
+
+pub fn drop(repo: &mut Repository, index: usize) -> Result<(), Error> {
+    repo.stash_drop(index)
+        .map_err(|e| Error::git2(e, "stash_drop"))
+}
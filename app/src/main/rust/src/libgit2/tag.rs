@@ -0,0 +1,268 @@
+//! Named snapshots of the notes, as git tags.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use git2::{Oid, Repository, Signature, build::CheckoutBuilder};
+use ssh_key::PrivateKey;
+
+use crate::{Error, GitAuthor};
+
+use super::sign;
+
+const SIGNATURE_BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+/// Written in the folders created by [`checkout`], which are the only ones it replaces.
+const CHECKOUT_MARKER: &str = ".gitnote-tag";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    pub name: String,
+    /// The tagged commit.
+    pub target: Oid,
+    pub annotated: bool,
+    /// Message of an annotated tag, without its signature.
+    pub message: String,
+    pub signed: bool,
+    /// Tagger of an annotated tag, or committer of the commit of a lightweight one.
+    pub tagger_name: String,
+    pub tagger_email: String,
+    /// In milliseconds.
+    pub time: i64,
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Tag `revision` as `name`.
+///
+/// The tag is annotated when there is a message, and signed
+/// with `key` when there is one.
+pub fn create(
+    repo: &Repository,
+    name: &str,
+    revision: &str,
+    message: Option<&str>,
+    tagger: &GitAuthor,
+    force: bool,
+    key: Option<&PrivateKey>,
+) -> Result<Oid, Error> {
+    if !git2::Reference::is_valid_name(&format!("refs/tags/{name}")) {
+        return Err(Error::git2(
+            git2::Error::from_str(&format!("invalid tag name: {name}")),
+            "create_tag",
+        ));
+    }
+
+    let target = repo
+        .revparse_single(revision)?
+        .peel_to_commit()?
+        .into_object();
+
+    let oid = match message {
+        Some(message) => {
+            let sig = Signature::now(&tagger.name, &tagger.email)?;
+            let message = format!("{}\n", message.trim_end());
+            sign::tag(repo, name, &target, &sig, &message, force, key)
+        }
+        None => repo.tag_lightweight(name, &target, force),
+    }
+    .map_err(|e| Error::git2(e, "create_tag"))?;
+
+    Ok(oid)
+}
+
+fn info(repo: &Repository, name: &str) -> Result<TagInfo, git2::Error> {
+    let reference = repo.find_reference(&format!("refs/tags/{name}"))?;
+    let commit = reference.peel_to_commit()?;
+
+    match reference.peel_to_tag() {
+        Ok(tag) => {
+            let message = lossy(tag.message_bytes().unwrap_or_default());
+            let (message, signed) = match message.split_once(SIGNATURE_BEGIN) {
+                Some((message, _)) => (message.to_string(), true),
+                None => (message, false),
+            };
+            let tagger = tag.tagger();
+
+            Ok(TagInfo {
+                name: name.to_string(),
+                target: commit.id(),
+                annotated: true,
+                message: message.trim_end().to_string(),
+                signed,
+                tagger_name: tagger
+                    .as_ref()
+                    .map(|t| lossy(t.name_bytes()))
+                    .unwrap_or_default(),
+                tagger_email: tagger
+                    .as_ref()
+                    .map(|t| lossy(t.email_bytes()))
+                    .unwrap_or_default(),
+                time: tagger
+                    .map(|t| t.when().seconds() * 1000)
+                    .unwrap_or_else(|| commit.time().seconds() * 1000),
+            })
+        }
+        Err(_) => {
+            let committer = commit.committer();
+
+            Ok(TagInfo {
+                name: name.to_string(),
+                target: commit.id(),
+                annotated: false,
+                message: String::new(),
+                signed: false,
+                tagger_name: lossy(committer.name_bytes()),
+                tagger_email: lossy(committer.email_bytes()),
+                time: commit.time().seconds() * 1000,
+            })
+        }
+    }
+}
+
+/// Tags pointing to a commit, newest first.
+pub fn list(repo: &Repository) -> Result<Vec<TagInfo>, Error> {
+    let names = repo.tag_names(None)?;
+
+    let mut tags = Vec::new();
+    for name in names.iter().flatten().flatten() {
+        match info(repo, name) {
+            Ok(tag) => tags.push(tag),
+            // e.g. a tag of a blob
+            Err(e) => warn!("tag {name}: {e}"),
+        }
+    }
+
+    tags.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(tags)
+}
+
+pub fn delete(repo: &Repository, name: &str) -> Result<(), Error> {
+    repo.tag_delete(name)
+        .map_err(|e| Error::git2(e, "delete_tag"))
+}
+
+/// `path` canonicalized, even when its last components don't exist yet.
+fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match path.canonicalize() {
+        Ok(path) => Ok(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let path = std::path::absolute(path)?;
+            match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => Ok(canonicalize(parent)?.join(name)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid path: {}", path.display()),
+                )),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// `dest` is neither in the repo, nor one of its parents.
+fn check_dest(repo: &Repository, dest: &Path) -> Result<(), Error> {
+    for protected in [Some(repo.path()), repo.workdir()].into_iter().flatten() {
+        let protected = protected
+            .canonicalize()
+            .map_err(|e| Error::io(e, "canonicalize repo"))?;
+
+        if dest.starts_with(&protected) || protected.starts_with(dest) {
+            return Err(Error::git2(
+                git2::Error::from_str(&format!(
+                    "{} would replace the repo {}",
+                    dest.display(),
+                    protected.display()
+                )),
+                "checkout_tag",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// `path` doesn't exist, or was created by [`checkout`].
+fn check_replaceable(path: &Path) -> Result<(), Error> {
+    if !path.exists() || path.join(CHECKOUT_MARKER).is_file() {
+        return Ok(());
+    }
+
+    Err(Error::git2(
+        git2::Error::from_str(&format!(
+            "{} already exists and is not a tag checkout",
+            path.display()
+        )),
+        "checkout_tag",
+    ))
+}
+
+/// Write the notes of the tag `name` in `dest`, as read-only files.
+///
+/// `dest` is replaced once the notes are written, and neither HEAD, the index,
+/// nor the worktree are modified. It can't be in the repo, and if it exists,
+/// it must come from a previous checkout.
+pub fn checkout(repo: &Repository, name: &str, dest: &Path) -> Result<(), Error> {
+    let tree = repo
+        .find_reference(&format!("refs/tags/{name}"))?
+        .peel_to_tree()?;
+
+    // libgit2 doesn't create the sub folders of a relative target dir
+    let dest = canonicalize(dest).map_err(|e| Error::io(e, "canonicalize dest"))?;
+    check_dest(repo, &dest)?;
+
+    let file_name = dest.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dest.with_file_name(format!(".{file_name}.tmp"));
+
+    check_replaceable(&dest)?;
+    check_replaceable(&tmp)?;
+
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp).map_err(|e| Error::io(e, "remove tmp"))?;
+    }
+    fs::create_dir_all(&tmp).map_err(|e| Error::io(e, "create tmp"))?;
+    fs::write(tmp.join(CHECKOUT_MARKER), name).map_err(|e| Error::io(e, "write marker"))?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout
+        .target_dir(&tmp)
+        .update_index(false)
+        .recreate_missing(true)
+        .force();
+
+    let res = repo
+        .checkout_tree(tree.as_object(), Some(&mut checkout))
+        .map_err(|e| Error::git2(e, "checkout_tree"))
+        .and_then(|_| set_read_only(&tmp).map_err(|e| Error::io(e, "set_read_only")));
+
+    if let Err(e) = res {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(e);
+    }
+
+    if dest.exists() {
+        fs::remove_dir_all(&dest).map_err(|e| Error::io(e, "remove dest"))?;
+    }
+    fs::rename(&tmp, &dest).map_err(|e| Error::io(e, "rename tmp"))
+}
+
+fn set_read_only(path: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            set_read_only(&entry.path())?;
+        } else if file_type.is_file() {
+            let mut permissions = entry.metadata()?.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(entry.path(), permissions)?;
+        }
+    }
+
+    Ok(())
+}
@@ -40,7 +40,7 @@ fn timestamp2() {
 
     let mut res = timestamps.into_iter().collect::<Vec<_>>();
 
-    res.sort_by(|a, b| a.1.cmp(&b.1));
+    res.sort_by_key(|a| a.1);
 
     dbg!(&res);
 
@@ -0,0 +1,124 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::attachment::{Reference, add, links, references, resolve};
+
+#[test]
+fn test_add_attachment() {
+    let path = "repo_test/attachment_add";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+    let workdir = repo.workdir().unwrap();
+
+    let image = [0x89, b'P', b'N', b'G', 0, 1, 2, 3];
+
+    let attachment = add(workdir, "folder/note.md", "PNG", &image).unwrap();
+    assert!(attachment.link.starts_with("attachments/"));
+    assert!(attachment.link.ends_with(".png"));
+    assert_eq!(attachment.path, format!("folder/{}", attachment.link));
+    assert_eq!(fs::read(workdir.join(&attachment.path)).unwrap(), image);
+
+    // content addressed
+    let again = add(workdir, "folder/other.md", ".png", &image).unwrap();
+    assert_eq!(again, attachment);
+
+    let root = add(workdir, "note.md", "png", &image).unwrap();
+    assert_eq!(root.path, root.link);
+    assert_eq!(root.link, attachment.link);
+
+    assert!(add(workdir, "note.md", "md", b"# note").is_err());
+
+    // never written out of the worktree
+    for note in [
+        "../note.md",
+        "folder/../../note.md",
+        "/tmp/note.md",
+        "./note.md",
+    ] {
+        assert!(add(workdir, note, "png", &image).is_err());
+    }
+}
+
+#[test]
+fn test_links() {
+    let content = r#"# Note
+
+![image](attachments/a.png) and [pdf](<my docs/b.pdf> "title")
+[site](https://example.com/c.png)
+
+<img src="../d.jpg" width="100">
+
+[ref]: /e.zip
+"#;
+
+    let mut links = links(content);
+    links.sort();
+    assert_eq!(
+        links,
+        [
+            "../d.jpg",
+            "/e.zip",
+            "attachments/a.png",
+            "https://example.com/c.png",
+            "my docs/b.pdf",
+        ]
+    );
+
+    assert_eq!(
+        resolve("folder/note.md", "attachments/a.png").as_deref(),
+        Some("folder/attachments/a.png")
+    );
+    assert_eq!(
+        resolve("folder/note.md", "../d%20e.jpg#anchor").as_deref(),
+        Some("d e.jpg")
+    );
+    assert_eq!(
+        resolve("folder/note.md", "/e.zip").as_deref(),
+        Some("e.zip")
+    );
+    assert_eq!(resolve("note.md", "../outside.png"), None);
+    assert_eq!(resolve("note.md", "https://example.com/c.png"), None);
+    assert_eq!(resolve("note.md", "mailto:me@example.com"), None);
+}
+
+#[test]
+fn test_references() {
+    let path = "repo_test/attachment_references";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+    let workdir = repo.workdir().unwrap();
+
+    fs::create_dir_all(workdir.join("folder/attachments")).unwrap();
+    fs::write(workdir.join("folder/attachments/a.png"), [0, 1]).unwrap();
+    fs::write(workdir.join("b.pdf"), [2, 3]).unwrap();
+
+    fs::write(
+        workdir.join("folder/note.md"),
+        "![](attachments/a.png)\n[doc](../b.pdf)\n[other](other.md)\n",
+    )
+    .unwrap();
+    fs::write(
+        workdir.join("root.md"),
+        "![](folder/attachments/a.png) ![](missing.jpg)\n",
+    )
+    .unwrap();
+    // not markdown
+    fs::write(workdir.join("plain.txt"), "![](b.pdf)\n").unwrap();
+
+    let reference = |note: &str, attachment: &str, exists: bool| Reference {
+        note: note.into(),
+        attachment: attachment.into(),
+        exists,
+    };
+
+    assert_eq!(
+        references(&repo, u64::MAX).unwrap(),
+        [
+            reference("folder/note.md", "b.pdf", true),
+            reference("folder/note.md", "folder/attachments/a.png", true),
+            reference("root.md", "folder/attachments/a.png", true),
+            reference("root.md", "missing.jpg", false),
+        ]
+    );
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::blame::{BlameLine, BlameOptions, blame};
+use crate::libgit2::test_utils::commit_changes;
+
+fn line(commit: Option<usize>, original_line: usize) -> BlameLine {
+    BlameLine {
+        commit,
+        original_line,
+    }
+}
+
+#[test]
+fn test_blame() {
+    let path = "repo_test/blame_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let first = commit_changes(
+        &repo,
+        &[("note.md", "one\ntwo\nthree\n")],
+        &[],
+        "commit",
+        "alice",
+        100,
+    );
+    let second = commit_changes(
+        &repo,
+        &[("note.md", "zero\none\ntwo  \nthree\n")],
+        &[],
+        "commit",
+        "bob",
+        200,
+    );
+
+    // not committed
+    fs::write(
+        repo.workdir().unwrap().join("note.md"),
+        "zero\none\ntwo  \nthree\nfour\n",
+    )
+    .unwrap();
+
+    let result = blame(&repo, "note.md", &BlameOptions::default()).unwrap();
+
+    assert_eq!(result.commits.len(), 2);
+    assert_eq!(result.commits[0].oid, second);
+    assert_eq!(result.commits[0].author_name, "bob");
+    assert_eq!(result.commits[0].time, 200_000);
+    assert_eq!(result.commits[1].oid, first);
+
+    assert_eq!(
+        result.lines,
+        vec![
+            line(Some(0), 1),
+            line(Some(1), 1),
+            line(Some(0), 3),
+            line(Some(1), 3),
+            line(None, 5),
+        ]
+    );
+
+    let options = BlameOptions {
+        ignore_whitespace: true,
+        ..Default::default()
+    };
+    let result = blame(&repo, "note.md", &options).unwrap();
+    assert_eq!(result.lines[2], line(Some(1), 2));
+
+    // everything older than the second commit is attributed to it
+    let options = BlameOptions {
+        oldest_commit: Some(second.to_string()),
+        ..Default::default()
+    };
+    let result = blame(&repo, "note.md", &options).unwrap();
+    assert_eq!(result.commits.len(), 1);
+    assert_eq!(result.commits[0].oid, second);
+}
@@ -0,0 +1,96 @@
+use std::fs;
+
+use git2::{Oid, Repository};
+
+use crate::libgit2::commit_log::{LogQuery, log};
+use crate::libgit2::test_utils::commit_changes;
+
+fn oids(query: &LogQuery, repo: &Repository) -> Vec<Oid> {
+    log(repo, query)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.oid)
+        .collect()
+}
+
+#[test]
+fn test_log() {
+    let path = "repo_test/log_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    assert!(log(&repo, &LogQuery::default()).unwrap().is_empty());
+
+    let c1 = commit_changes(
+        &repo,
+        &[("a.md", "a\n"), ("dir/b.md", "b\n")],
+        &[],
+        "first\n\nsome details\n",
+        "alice",
+        100,
+    );
+    let c2 = commit_changes(&repo, &[("a.md", "a\na2\n")], &[], "second", "bob", 200);
+    let c3 = commit_changes(&repo, &[("dir/b.md", "b2\n")], &[], "third", "Alice", 300);
+
+    let entries = log(&repo, &LogQuery::default()).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].oid, c3);
+
+    let first = &entries[2];
+    assert_eq!(first.summary, "first");
+    assert_eq!(first.body, "some details");
+    assert_eq!(first.author_name, "alice");
+    assert_eq!(first.time, 100_000);
+    assert_eq!(
+        (first.files_changed, first.insertions, first.deletions),
+        (2, 2, 0)
+    );
+    assert_eq!(
+        (
+            entries[0].files_changed,
+            entries[0].insertions,
+            entries[0].deletions
+        ),
+        (1, 1, 1)
+    );
+
+    // paging
+    let page = LogQuery {
+        after: Some(c3),
+        limit: 1,
+        ..Default::default()
+    };
+    assert_eq!(oids(&page, &repo), vec![c2]);
+
+    let last_page = LogQuery {
+        after: Some(c1),
+        ..Default::default()
+    };
+    assert!(oids(&last_page, &repo).is_empty());
+
+    let unknown = LogQuery {
+        after: Some(Oid::ZERO_SHA1),
+        ..Default::default()
+    };
+    assert!(log(&repo, &unknown).is_err());
+
+    let by_path = LogQuery {
+        path: Some("dir".into()),
+        ..Default::default()
+    };
+    assert_eq!(oids(&by_path, &repo), vec![c3, c1]);
+
+    let by_author = LogQuery {
+        author: Some("ALICE@".into()),
+        after: Some(c3),
+        ..Default::default()
+    };
+    assert_eq!(oids(&by_author, &repo), vec![c1]);
+
+    let by_time = LogQuery {
+        since: 150_000,
+        until: 200_000,
+        ..Default::default()
+    };
+    assert_eq!(oids(&by_time, &repo), vec![c2]);
+}
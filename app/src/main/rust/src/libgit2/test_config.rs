@@ -0,0 +1,124 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::config::{CONFIG_FILE, Config, DEFAULT_REMOTE, MergePolicy, load, parse};
+
+#[test]
+fn test_parse_config() {
+    let loaded = parse("");
+    assert!(loaded.errors.is_empty());
+    assert_eq!(loaded.config, Config::default());
+    assert_eq!(loaded.config.remote, DEFAULT_REMOTE);
+    assert_eq!(
+        loaded.config.commit_message("gitnote created a.md"),
+        "gitnote created a.md"
+    );
+
+    let loaded = parse(
+        r#"
+remote = "upstream"
+
+[commit]
+message = "{message} (phone)"
+
+[merge]
+policy = "fast-forward"
+
+[sync]
+stash = true
+
+[notes]
+ignore = ["drafts/", "*.tmp"]
+
+[extensions]
+org = "markdown"
+"#,
+    );
+    assert_eq!(loaded.errors, Vec::<String>::new());
+
+    let config = loaded.config;
+    assert_eq!(config.remote, "upstream");
+    assert_eq!(
+        config.commit_message("gitnote created a.md"),
+        "gitnote created a.md (phone)"
+    );
+    assert_eq!(config.merge_policy, MergePolicy::FastForward);
+    assert_eq!(config.stash_on_sync, Some(true));
+    assert_eq!(config.ignore, ["drafts/", "*.tmp"]);
+    assert_eq!(config.extensions.len(), 1);
+}
+
+#[test]
+fn test_config_errors() {
+    // the valid values are kept
+    let loaded = parse(
+        r#"
+remote = ""
+colour = "blue"
+
+[commit]
+message = "no placeholder"
+
+[merge]
+policy = "rebase"
+strategy = "ours"
+
+[sync]
+stash = "yes"
+
+[notes]
+ignore = ["drafts/", 1]
+
+[extensions]
+org = "video"
+"#,
+    );
+
+    assert_eq!(
+        loaded.errors,
+        [
+            "colour: unknown key",
+            "merge.strategy: unknown key",
+            "remote: empty name",
+            "commit.message: missing {message}",
+            "merge.policy: unknown policy \"rebase\"",
+            "sync.stash: expected a boolean, found a string",
+            "notes.ignore: expected a list of strings, found a array",
+            "extensions.org: unknown type \"video\"",
+        ]
+    );
+    assert_eq!(loaded.config, Config::default());
+
+    let loaded = parse("merge = 1\n[sync]\nstash = false");
+    assert_eq!(loaded.errors, ["merge: expected a table, found a integer"]);
+    assert_eq!(loaded.config.stash_on_sync, Some(false));
+
+    // not toml
+    let loaded = parse("[sync]\nremote = ");
+    assert_eq!(loaded.errors.len(), 1);
+    assert!(loaded.errors[0].starts_with("line 2: "));
+    assert_eq!(loaded.config, Config::default());
+}
+
+#[test]
+fn test_load_config() {
+    let path = "repo_test/config_load";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    // missing
+    let loaded = load(&repo);
+    assert!(loaded.errors.is_empty());
+    assert_eq!(loaded.config, Config::default());
+
+    fs::write(
+        repo.workdir().unwrap().join(CONFIG_FILE),
+        "[notes]\nignore = [\"drafts/\"]\n",
+    )
+    .unwrap();
+
+    let loaded = load(&repo);
+    assert!(loaded.errors.is_empty());
+    assert_eq!(loaded.config.ignore, ["drafts/"]);
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::Path;
+
+use git2::Repository;
+
+use crate::libgit2::diff::{DiffLine, LineKind, Side, diff};
+use crate::libgit2::test_utils::commit_file;
+
+fn line(kind: LineKind, old: Option<u32>, new: Option<u32>, content: &str) -> DiffLine {
+    DiffLine {
+        kind,
+        old_line: old,
+        new_line: new,
+        content: content.into(),
+        changes: Vec::new(),
+    }
+}
+
+#[test]
+fn test_diff() {
+    let path = "repo_test/diff_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+    let workdir = repo.workdir().unwrap().to_path_buf();
+
+    commit_file(&repo, "note.md", "one\ntwo\nthree\n", None);
+
+    // staged
+    fs::write(workdir.join("note.md"), "one\n2\nthree\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("note.md")).unwrap();
+    index.write().unwrap();
+
+    // not staged
+    fs::write(workdir.join("note.md"), "one\n2\nthree\nfour\n").unwrap();
+
+    let hunks = diff(
+        &repo,
+        "note.md",
+        &Side::Commit("HEAD".into()),
+        &Side::Index,
+        false,
+    )
+    .unwrap();
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(
+        hunks[0].lines,
+        vec![
+            line(LineKind::Context, Some(1), Some(1), "one"),
+            line(LineKind::Removed, Some(2), None, "two"),
+            line(LineKind::Added, None, Some(2), "2"),
+            line(LineKind::Context, Some(3), Some(3), "three"),
+        ]
+    );
+
+    let hunks = diff(&repo, "note.md", &Side::Index, &Side::Worktree, false).unwrap();
+    assert_eq!(
+        (hunks[0].old_start, hunks[0].old_lines),
+        (1, 3),
+        "{hunks:?}"
+    );
+    assert_eq!(
+        hunks[0].lines.last(),
+        Some(&line(LineKind::Added, None, Some(4), "four"))
+    );
+
+    // missing file on one side
+    let hunks = diff(
+        &repo,
+        "new.md",
+        &Side::Commit("HEAD".into()),
+        &Side::Worktree,
+        false,
+    )
+    .unwrap();
+    assert!(hunks.is_empty());
+
+    fs::write(workdir.join("new.md"), "a\n").unwrap();
+    let hunks = diff(
+        &repo,
+        "new.md",
+        &Side::Commit("HEAD".into()),
+        &Side::Worktree,
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        hunks[0].lines,
+        vec![line(LineKind::Added, None, Some(1), "a")]
+    );
+}
+
+#[test]
+fn test_word_diff() {
+    let path = "repo_test/word_diff_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    commit_file(&repo, "note.md", "The quick fox jumps.\nremoved\n", None);
+    fs::write(
+        repo.workdir().unwrap().join("note.md"),
+        "The slow 🦊 fox jumps!\n",
+    )
+    .unwrap();
+
+    let hunks = diff(
+        &repo,
+        "note.md",
+        &Side::Commit("HEAD".into()),
+        &Side::Worktree,
+        true,
+    )
+    .unwrap();
+
+    let lines = &hunks[0].lines;
+    assert_eq!(lines.len(), 3);
+
+    // "quick" and "."
+    assert_eq!(lines[0].changes, vec![(4, 9), (19, 20)]);
+    assert_eq!(lines[1].changes, vec![(0, 7)]);
+    // "slow 🦊" (the emoji is 2 utf-16 code units) and "!"
+    assert_eq!(lines[2].kind, LineKind::Added);
+    assert_eq!(lines[2].changes, vec![(4, 11), (21, 22)]);
+}
@@ -0,0 +1,82 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::callback::{DummyProgressCB, ProgressCB};
+use crate::libgit2::history::{HistoryQuery, search_history};
+use crate::libgit2::test_utils::commit_changes;
+
+fn query(pattern: &str, is_regex: bool) -> HistoryQuery {
+    HistoryQuery {
+        pattern: pattern.into(),
+        is_regex,
+        ignore_case: true,
+        context: 1,
+        max_matches: 100,
+    }
+}
+
+struct CancelCB;
+
+impl ProgressCB for CancelCB {
+    fn progress(&mut self, _progress: i32) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_search_history() {
+    let path = "repo_test/history_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let first = commit_changes(
+        &repo,
+        &[
+            ("a.md", "title\nthe Secret plan\nend"),
+            ("dir/b.md", "nothing"),
+            ("c.png", "secret"),
+        ],
+        &[],
+        "commit",
+        "Moi",
+        100,
+    );
+    // unchanged blobs and trees are not searched again
+    commit_changes(&repo, &[("d.md", "other")], &[], "commit", "Moi", 200);
+    let deleted = commit_changes(
+        &repo,
+        &[("dir/b.md", "secret (v2)")],
+        &["a.md"],
+        "commit",
+        "Moi",
+        300,
+    );
+    commit_changes(&repo, &[], &["dir/b.md"], "commit", "Moi", 400);
+
+    let matches = search_history(&repo, &query("secret", false), DummyProgressCB).unwrap();
+    assert_eq!(matches.len(), 2);
+
+    assert_eq!(matches[0].commit, deleted);
+    assert_eq!(matches[0].path, "dir/b.md");
+    assert_eq!(matches[0].line, "secret (v2)");
+    assert_eq!(matches[0].time, 300_000);
+
+    // a.md is in the tree of the second commit, which is more recent
+    assert_ne!(matches[1].commit, first);
+    assert_eq!(matches[1].path, "a.md");
+    assert_eq!(matches[1].line_number, 2);
+    assert_eq!(matches[1].context_before, "title");
+    assert_eq!(matches[1].context_after, "end");
+
+    let matches = search_history(&repo, &query(r"\(v\d\)", true), DummyProgressCB).unwrap();
+    assert_eq!(matches.len(), 1);
+
+    // a literal pattern is not a regex
+    let matches = search_history(&repo, &query("(v2", false), DummyProgressCB).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert!(search_history(&repo, &query("(v2", true), DummyProgressCB).is_err());
+
+    let matches = search_history(&repo, &query("secret", false), CancelCB).unwrap();
+    assert!(matches.is_empty());
+}
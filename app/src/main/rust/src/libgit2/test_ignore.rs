@@ -0,0 +1,101 @@
+use std::{fs, path::Path};
+
+use git2::{Repository, Signature, StatusOptions};
+
+use crate::libgit2::test_utils::write;
+use crate::libgit2::{
+    ignore::{IGNORE_FILE, add_patterns, is_ignored, set_rules},
+    lfs,
+    timestamps::timestamps,
+};
+
+fn untracked(repo: &Repository) -> Vec<String> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+
+    let mut paths = repo
+        .statuses(Some(&mut opts))
+        .unwrap()
+        .iter()
+        .map(|s| s.path().unwrap().to_string())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_ignore() {
+    let path = "repo_test/ignore_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    write(&repo, "note.md", "note");
+    write(&repo, ".DS_Store", "junk");
+    write(&repo, "folder/.note.md.swp", "junk");
+    write(&repo, "~$report.docx", "junk");
+    write(&repo, "drafts/a.md", "draft");
+    write(&repo, "backup.md~", "junk");
+    write(&repo, IGNORE_FILE, "# keep the backups\n!*~\n");
+
+    set_rules(&repo, &["drafts/".to_string()]);
+
+    assert_eq!(untracked(&repo), [IGNORE_FILE, "backup.md~", "note.md"]);
+
+    assert!(is_ignored(&repo, Path::new(".DS_Store"), false));
+    assert!(is_ignored(&repo, Path::new("drafts"), true));
+    assert!(is_ignored(&repo, Path::new("drafts/a.md"), false));
+    assert!(!is_ignored(&repo, Path::new("backup.md~"), false));
+    assert!(!is_ignored(&repo, Path::new("note.md"), false));
+
+    // the commits skip them too
+    let mut index = repo.index().unwrap();
+    lfs::add_all(&repo, &mut index).unwrap();
+    let mut indexed = index
+        .iter()
+        .map(|e| String::from_utf8(e.path).unwrap())
+        .collect::<Vec<_>>();
+    indexed.sort();
+    assert_eq!(indexed, [IGNORE_FILE, "backup.md~", "note.md"]);
+
+    // the patterns are added once
+    add_patterns(&repo, &["*.md~".to_string(), "*.md~".to_string()]).unwrap();
+    add_patterns(&repo, &["*.md~".to_string()]).unwrap();
+    assert_eq!(
+        fs::read_to_string(repo.workdir().unwrap().join(IGNORE_FILE)).unwrap(),
+        "# keep the backups\n!*~\n*.md~\n"
+    );
+    set_rules(&repo, &[]);
+    assert!(is_ignored(&repo, Path::new("backup.md~"), false));
+    assert!(!is_ignored(&repo, Path::new("drafts/a.md"), false));
+
+    assert!(add_patterns(&repo, &["a\nb".to_string()]).is_err());
+}
+
+#[test]
+fn test_ignored_timestamps() {
+    let path = "repo_test/ignore_timestamps_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    write(&repo, "a.md", "a");
+    write(&repo, "drafts/b.md", "b");
+
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.md")).unwrap();
+    index.add_path(Path::new("drafts/b.md")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("Moi", "test@example.com").unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "commit", &tree, &[])
+        .unwrap();
+
+    assert_eq!(timestamps(&repo).unwrap().len(), 2);
+
+    // the cache doesn't hide the new rules
+    set_rules(&repo, &["drafts/".to_string()]);
+    let ignored = timestamps(&repo).unwrap();
+    assert!(ignored.contains_key("a.md"));
+    assert!(!ignored.contains_key("drafts/b.md"));
+
+    set_rules(&repo, &[]);
+    assert_eq!(timestamps(&repo).unwrap().len(), 2);
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use git2::{Repository, StatusOptions};
+use regex::Regex;
+
+use crate::Cred;
+use crate::libgit2::lfs::{self, Pointer, endpoint_from_remote};
+use crate::libgit2::test_utils::{commit_index, signature_at};
+
+#[derive(Default)]
+struct ServerState {
+    objects: HashMap<String, Vec<u8>>,
+    authorizations: Vec<String>,
+    verified: usize,
+}
+
+/// Local stand-in of an LFS server, with the basic transfer.
+fn start_server() -> (String, Arc<Mutex<ServerState>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let state = Arc::new(Mutex::new(ServerState::default()));
+
+    let base = url.clone();
+    let server_state = state.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            handle(stream.unwrap(), &base, &server_state);
+        }
+    });
+
+    (url, state)
+}
+
+fn handle(stream: TcpStream, base: &str, state: &Mutex<ServerState>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap().to_string();
+    let path = parts.next().unwrap().to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (key, value) = line.split_once(':').unwrap();
+        headers.insert(key.to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let len = headers
+        .get("content-length")
+        .map_or(0, |len| len.parse().unwrap());
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut state = state.lock().unwrap();
+
+    let (status, response) = match (method.as_str(), path.as_str()) {
+        ("POST", "/objects/batch") => {
+            if let Some(auth) = headers.get("authorization") {
+                state.authorizations.push(auth.clone());
+            }
+
+            let body = String::from_utf8(body).unwrap();
+            let upload = body.contains(r#""operation":"upload""#);
+
+            let re = Regex::new(r#""oid":"([0-9a-f]{64})","size":(\d+)"#).unwrap();
+            let objects = re
+                .captures_iter(&body)
+                .map(|c| {
+                    let (oid, size) = (&c[1], &c[2]);
+                    let href = format!("{base}/objects/{oid}");
+                    let actions = match (upload, state.objects.contains_key(oid)) {
+                        (true, true) => String::new(),
+                        (true, false) => format!(
+                            r#","actions":{{"upload":{{"href":"{href}"}},"verify":{{"href":"{base}/verify"}}}}"#
+                        ),
+                        (false, true) => format!(
+                            r#","actions":{{"download":{{"href":"{href}","header":{{"X-Test":"1"}}}}}}"#
+                        ),
+                        (false, false) => {
+                            r#","error":{"code":404,"message":"not found"}"#.to_string()
+                        }
+                    };
+                    format!(r#"{{"oid":"{oid}","size":{size}{actions}}}"#)
+                })
+                .collect::<Vec<_>>();
+
+            (
+                200,
+                format!(
+                    r#"{{"transfer":"basic","objects":[{}]}}"#,
+                    objects.join(",")
+                )
+                .into_bytes(),
+            )
+        }
+        ("PUT", path) => {
+            let oid = path.trim_start_matches("/objects/").to_string();
+            state.objects.insert(oid, body);
+            (200, Vec::new())
+        }
+        ("POST", "/verify") => {
+            state.verified += 1;
+            (200, Vec::new())
+        }
+        ("GET", path) => {
+            assert_eq!(headers.get("x-test").map(String::as_str), Some("1"));
+
+            let oid = path.trim_start_matches("/objects/");
+            match state.objects.get(oid) {
+                Some(content) => {
+                    // chunked, like most servers
+                    let (first, second) = content.split_at(content.len() / 2);
+                    let mut response =
+                        b"HTTP/1.1 200 OK\r\nConnection: close\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+                    for chunk in [first, second] {
+                        response.extend(format!("{:x}\r\n", chunk.len()).as_bytes());
+                        response.extend(chunk);
+                        response.extend(b"\r\n");
+                    }
+                    response.extend(b"0\r\n\r\n");
+                    stream.write_all(&response).unwrap();
+                    return;
+                }
+                None => (404, Vec::new()),
+            }
+        }
+        _ => (404, Vec::new()),
+    };
+
+    // one request per connection
+    let header = format!(
+        "HTTP/1.1 {status} OK\r\nConnection: close\r\nContent-Type: application/vnd.git-lfs+json\r\nContent-Length: {}\r\n\r\n",
+        response.len()
+    );
+    stream.write_all(header.as_bytes()).unwrap();
+    stream.write_all(&response).unwrap();
+}
+
+/// Commit the worktree, the lfs files as pointers.
+fn commit(repo: &Repository, time: i64) {
+    let mut index = repo.index().unwrap();
+    lfs::add_all(repo, &mut index).unwrap();
+    index.write().unwrap();
+
+    commit_index(repo, "commit", &signature_at("Moi", time), None);
+}
+
+fn is_clean(repo: &Repository) -> bool {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    repo.statuses(Some(&mut opts)).unwrap().is_empty()
+}
+
+fn blob_content(repo: &Repository, path: &str) -> Vec<u8> {
+    let tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let entry = tree.get_path(Path::new(path)).unwrap();
+    repo.find_blob(entry.id()).unwrap().content().to_vec()
+}
+
+#[test]
+fn test_pointer() {
+    let pointer = Pointer::from_content(b"hello");
+    assert_eq!(
+        pointer.oid,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(pointer.size, 5);
+
+    let bytes = pointer.to_bytes();
+    assert!(bytes.starts_with(b"version https://git-lfs.github.com/spec/v1\n"));
+    assert_eq!(Pointer::parse(&bytes), Some(pointer));
+
+    assert_eq!(Pointer::parse(b"hello"), None);
+    assert_eq!(
+        Pointer::parse(b"version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 5\n"),
+        None
+    );
+}
+
+#[test]
+fn test_endpoint_from_remote() {
+    assert_eq!(
+        endpoint_from_remote("https://github.com/user/notes.git").as_deref(),
+        Some("https://github.com/user/notes.git/info/lfs")
+    );
+    assert_eq!(
+        endpoint_from_remote("https://github.com/user/notes").as_deref(),
+        Some("https://github.com/user/notes.git/info/lfs")
+    );
+    assert_eq!(
+        endpoint_from_remote("git@github.com:user/notes.git").as_deref(),
+        Some("https://github.com/user/notes.git/info/lfs")
+    );
+    assert_eq!(
+        endpoint_from_remote("ssh://git@example.com:2222/user/notes.git").as_deref(),
+        Some("https://example.com/user/notes.git/info/lfs")
+    );
+}
+
+#[test]
+fn test_lfs() {
+    let (url, state) = start_server();
+
+    let remote_path = "repo_test/lfs_remote.git";
+    let local_path = "repo_test/lfs_local";
+    let clone_path = "repo_test/lfs_clone";
+    for path in [remote_path, local_path, clone_path] {
+        let _ = fs::remove_dir_all(path);
+    }
+
+    Repository::init_bare(remote_path).unwrap();
+    let remote_url = fs::canonicalize(remote_path).unwrap();
+
+    let repo = Repository::init(local_path).unwrap();
+    repo.remote("origin", remote_url.to_str().unwrap()).unwrap();
+
+    let image = (0..4000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let workdir = repo.workdir().unwrap();
+    fs::write(
+        workdir.join(".gitattributes"),
+        "*.png filter=lfs diff=lfs merge=lfs -text\n",
+    )
+    .unwrap();
+    fs::write(
+        workdir.join(".lfsconfig"),
+        format!("[lfs]\n\turl = {url}\n"),
+    )
+    .unwrap();
+    fs::write(workdir.join("image.png"), &image).unwrap();
+    fs::write(workdir.join("note.md"), "![](image.png)\n").unwrap();
+
+    // clean: the tree has the pointer, the cache the content
+    commit(&repo, 100);
+
+    let pointer = Pointer::from_content(&image);
+    assert_eq!(blob_content(&repo, "image.png"), pointer.to_bytes());
+    assert_eq!(blob_content(&repo, "note.md"), b"![](image.png)\n");
+    assert_eq!(
+        fs::read(lfs::object_path(&repo, &pointer.oid)).unwrap(),
+        image
+    );
+    assert_eq!(fs::read(workdir.join("image.png")).unwrap(), image);
+    assert!(is_clean(&repo));
+
+    // upload on push
+    let cred = Cred::UserPassPlainText {
+        username: "user".into(),
+        password: "pass".into(),
+    };
+    let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+
+    // refused before any upload
+    let ssh = Cred::SshKeyRef {
+        fingerprint: "SHA256:key".into(),
+    };
+    assert!(lfs::push(&repo, "origin", &branch, Some(&ssh)).is_err());
+    assert!(state.lock().unwrap().objects.is_empty());
+
+    assert_eq!(lfs::push(&repo, "origin", &branch, Some(&cred)).unwrap(), 1);
+    {
+        let state = state.lock().unwrap();
+        assert_eq!(state.objects.get(&pointer.oid), Some(&image));
+        assert_eq!(state.verified, 1);
+        assert_eq!(state.authorizations, ["Basic dXNlcjpwYXNz"]);
+    }
+    // already on the server
+    assert_eq!(lfs::push(&repo, "origin", &branch, Some(&cred)).unwrap(), 0);
+
+    let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+    repo.find_remote("origin")
+        .unwrap()
+        .push(&[&refspec], None)
+        .unwrap();
+
+    // smudge: the clone has the pointer until the download
+    let clone = Repository::clone(remote_url.to_str().unwrap(), clone_path).unwrap();
+    let clone_workdir = clone.workdir().unwrap();
+    assert_eq!(
+        fs::read(clone_workdir.join("image.png")).unwrap(),
+        pointer.to_bytes()
+    );
+
+    let clone_head = clone.head().unwrap().target().unwrap();
+    assert!(lfs::check_download(&clone, clone_head, None, Some(&ssh)).is_err());
+    lfs::check_download(&clone, clone_head, None, Some(&cred)).unwrap();
+
+    assert_eq!(lfs::pull(&clone, "origin", Some(&cred)).unwrap(), 1);
+    assert_eq!(fs::read(clone_workdir.join("image.png")).unwrap(), image);
+    assert!(lfs::object_path(&clone, &pointer.oid).exists());
+    assert!(is_clean(&clone));
+
+    // nothing left to smudge
+    assert_eq!(lfs::pull(&clone, "origin", Some(&cred)).unwrap(), 0);
+    // everything is cached
+    lfs::check_download(&clone, clone_head, None, Some(&ssh)).unwrap();
+}
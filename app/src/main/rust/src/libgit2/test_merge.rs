@@ -3,7 +3,7 @@ use std::path::Path;
 use std::{fs, io};
 
 use crate::GitAuthor;
-use crate::libgit2::merge::do_merge;
+use crate::libgit2::{config::MergePolicy, merge::do_merge};
 
 fn clear_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
     for entry in fs::read_dir(path)? {
@@ -109,7 +109,15 @@ fn test_clean_merge_flow() {
     };
 
     let author = GitAuthor::from(signature());
-    do_merge(&repo, "dev", annotated_dev, &author).expect("Merge failed");
+    do_merge(
+        &repo,
+        "dev",
+        annotated_dev,
+        &author,
+        MergePolicy::Merge,
+        None,
+    )
+    .expect("Merge failed");
 
     assert_content(&repo, "file1.txt", "hello world");
     assert_content(&repo, "file2.txt", "hello");
@@ -146,9 +154,59 @@ fn test_clean_merge_flow2() {
     };
 
     let author = GitAuthor::from(signature());
-    do_merge(&repo, "dev", annotated_dev, &author).expect("Merge failed");
+    do_merge(
+        &repo,
+        "dev",
+        annotated_dev,
+        &author,
+        MergePolicy::Merge,
+        None,
+    )
+    .expect("Merge failed");
 
     assert_content(&repo, "file1.txt", "Contenu Initial");
     assert_content(&repo, "file2.txt", "Contenu Dev");
     assert_content(&repo, "file3.txt", "Contenu Master");
 }
+
+#[test]
+fn test_fast_forward_policy() {
+    let path = "repo_test/merge_fast_forward";
+    let _ = clear_dir(path);
+    let repo = Repository::init(path).unwrap();
+
+    add_file(&repo, "file1.txt", "hello");
+    let oid1 = commit_current_state(&repo, "Initial commit on master");
+
+    let commit1 = repo.find_commit(oid1).unwrap();
+    repo.branch("dev", &commit1, false).unwrap();
+    switch_to_branch(&repo, "dev");
+    add_file(&repo, "file2.txt", "dev");
+    commit_current_state(&repo, "Add file2 on dev");
+
+    switch_to_branch(&repo, "master");
+    add_file(&repo, "file3.txt", "master");
+    let master = commit_current_state(&repo, "Add file3 on master");
+
+    let annotated_dev = {
+        let dev_ref = repo.find_reference("refs/heads/dev").unwrap();
+        repo.reference_to_annotated_commit(&dev_ref).unwrap()
+    };
+
+    let author = GitAuthor::from(signature());
+    assert!(
+        do_merge(
+            &repo,
+            "dev",
+            annotated_dev,
+            &author,
+            MergePolicy::FastForward,
+            None
+        )
+        .is_err()
+    );
+
+    // nothing changed
+    assert_eq!(repo.head().unwrap().target(), Some(master));
+    assert!(!repo.workdir().unwrap().join("file2.txt").exists());
+}
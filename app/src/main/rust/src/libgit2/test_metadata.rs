@@ -0,0 +1,103 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::metadata::{NoteMetadata, metadata};
+use crate::libgit2::test_utils::{add_file, commit, commit_changes, signature_at};
+
+fn checkout(repo: &Repository, branch: &str) {
+    repo.set_head(&format!("refs/heads/{branch}")).unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+}
+
+#[test]
+fn test_note_metadata() {
+    let path = "repo_test/metadata_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let content = "# Title\n\nsome content, long enough for the rename detection\n";
+
+    commit_changes(&repo, &[("a.md", "# Title\n")], &[], "commit", "alice", 100);
+    commit_changes(&repo, &[("a.md", content)], &[], "commit", "bob", 200);
+    commit_changes(
+        &repo,
+        &[("notes/a.md", content)],
+        &["a.md"],
+        "commit",
+        "bob",
+        300,
+    );
+    commit_changes(&repo, &[("c.md", "c")], &[], "commit", "alice", 400);
+
+    let metadata = metadata(&repo).unwrap();
+
+    assert_eq!(metadata.len(), 2);
+    // the move is not a revision
+    assert_eq!(
+        metadata.get("notes/a.md"),
+        Some(&NoteMetadata {
+            created_at: 100_000,
+            last_modified: 200_000,
+            author_name: "bob".into(),
+            author_email: "bob@example.com".into(),
+            revisions: 2,
+        })
+    );
+    assert_eq!(
+        metadata.get("c.md"),
+        Some(&NoteMetadata {
+            created_at: 400_000,
+            last_modified: 400_000,
+            author_name: "alice".into(),
+            author_email: "alice@example.com".into(),
+            revisions: 1,
+        })
+    );
+}
+
+#[test]
+fn test_revisions_of_merges() {
+    let path = "repo_test/metadata_merge_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let base = commit(&repo, &[("a.md", "1\n2\n3\n")], 100);
+    let main = repo.head().unwrap().shorthand().unwrap().to_string();
+    repo.branch("side", &repo.find_commit(base).unwrap(), false)
+        .unwrap();
+
+    let ours = commit(&repo, &[("a.md", "1 ours\n2\n3\n")], 200);
+
+    checkout(&repo, "side");
+    commit(&repo, &[("a.md", "1 theirs\n2\n3\n")], 300);
+    let theirs = commit(&repo, &[("b.md", "b")], 400);
+
+    checkout(&repo, &main);
+    add_file(&repo, "a.md", "1 both\n2\n3\n");
+    add_file(&repo, "b.md", "b");
+    let parents = [ours, theirs].map(|oid| repo.find_commit(oid).unwrap());
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let sig = signature_at("Moi", 500);
+    repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        "merge",
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )
+    .unwrap();
+
+    let metadata = metadata(&repo).unwrap();
+
+    // base, ours, theirs and the merge, the base once
+    assert_eq!(metadata["a.md"].revisions, 4);
+    assert_eq!(metadata["a.md"].created_at, 100_000);
+    assert_eq!(metadata["a.md"].last_modified, 500_000);
+    // the merge is TREESAME to the side branch for b.md
+    assert_eq!(metadata["b.md"].revisions, 1);
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository};
+
+use crate::libgit2::test_utils::{commit_index, signature_at, write};
+use crate::libgit2::{
+    commit_log::{LogQuery, log},
+    diff::{Side, diff},
+    rename::move_path,
+    timestamps::timestamps,
+};
+
+/// Stage the worktree and commit it at `time` seconds.
+fn commit_all(repo: &Repository, time: i64) -> Oid {
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.update_all(["*"].iter(), None).unwrap();
+    index.write().unwrap();
+
+    commit_index(repo, "commit", &signature_at("Moi", time), None)
+}
+
+fn staged(repo: &Repository, path: &str) -> bool {
+    repo.index().unwrap().get_path(Path::new(path), 0).is_some()
+}
+
+#[test]
+fn test_move_path() {
+    let path = "repo_test/move_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    write(&repo, "a.md", "a\n");
+    write(&repo, "dir/b.md", "b\n");
+    write(&repo, "dir/sub/c.md", "c\n");
+    commit_all(&repo, 100);
+
+    move_path(&repo, "a.md", "notes/a.md").unwrap();
+    assert!(!staged(&repo, "a.md"));
+    assert!(staged(&repo, "notes/a.md"));
+    assert!(repo.workdir().unwrap().join("notes/a.md").exists());
+
+    move_path(&repo, "dir", "other").unwrap();
+    assert!(!staged(&repo, "dir/b.md"));
+    assert!(staged(&repo, "other/b.md"));
+    assert!(staged(&repo, "other/sub/c.md"));
+
+    // nothing changed but the paths
+    let statuses = repo.statuses(None).unwrap();
+    assert!(
+        statuses
+            .iter()
+            .all(|s| !s.status().is_wt_new() && !s.status().is_wt_modified())
+    );
+
+    assert!(move_path(&repo, "notes/a.md", "other/b.md").is_err());
+    assert!(move_path(&repo, "other", "other/sub/x").is_err());
+    assert!(move_path(&repo, "missing.md", "x.md").is_err());
+
+    // outside of the worktree
+    assert!(move_path(&repo, "other/b.md", "../b.md").is_err());
+    assert!(move_path(&repo, "other/b.md", "other/../../b.md").is_err());
+    assert!(move_path(&repo, "../move_repo/other/b.md", "b.md").is_err());
+    assert!(move_path(&repo, "./other/b.md", "b.md").is_err());
+    assert!(repo.workdir().unwrap().join("other/b.md").exists());
+    assert!(!Path::new("repo_test/b.md").exists());
+}
+
+#[test]
+fn test_follow_rename() {
+    let path = "repo_test/follow_rename_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let content = "# Title\n\nsome content, long enough for the rename detection\n";
+
+    write(&repo, "a.md", "# Title\n");
+    let first = commit_all(&repo, 100);
+    write(&repo, "a.md", content);
+    let second = commit_all(&repo, 200);
+
+    move_path(&repo, "a.md", "notes/a.md").unwrap();
+    let moved = commit_all(&repo, 300);
+
+    // a pure move keeps the timestamp
+    let timestamps = timestamps(&repo).unwrap();
+    assert_eq!(timestamps.get("notes/a.md"), Some(&200_000));
+
+    let query = LogQuery {
+        path: Some("notes/a.md".into()),
+        ..Default::default()
+    };
+    let oids = log(&repo, &query)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.oid)
+        .collect::<Vec<_>>();
+    assert_eq!(oids, vec![moved, second, first]);
+
+    // the old version is found under its old path
+    write(&repo, "notes/a.md", &format!("{content}more\n"));
+    let hunks = diff(
+        &repo,
+        "notes/a.md",
+        &Side::Commit(second.to_string()),
+        &Side::Worktree,
+        false,
+    )
+    .unwrap();
+    assert_eq!(hunks.len(), 1);
+    assert_eq!((hunks[0].old_lines, hunks[0].new_lines), (3, 4));
+}
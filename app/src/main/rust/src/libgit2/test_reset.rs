@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository};
+
+use crate::libgit2::reset::{BACKUP_REFS, ResetKind, reflog, reset_to};
+use crate::libgit2::test_utils::{commit_changes, read, signature};
+
+#[test]
+fn test_reset() {
+    let path = "repo_test/reset_repo";
+    let _ = fs::remove_dir_all(path);
+    let mut repo = Repository::init(path).unwrap();
+
+    let first = commit_changes(&repo, &[("a.md", "a\n")], &[], "first", "Moi", 100);
+    let second = commit_changes(&repo, &[("a.md", "a2\n")], &[], "second", "Moi", 200);
+
+    let entries = reflog(&repo, None).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].new, second);
+    assert_eq!(entries[0].old, first);
+    assert!(entries[0].message.contains("second"));
+    assert_eq!(entries[1].old, Oid::ZERO_SHA1);
+
+    let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+    assert_eq!(reflog(&repo, Some(&branch)).unwrap().len(), 2);
+
+    // soft: the changes of the second commit stay staged
+    let backup = reset_to(&mut repo, &first.to_string(), ResetKind::Soft, &signature()).unwrap();
+    assert_eq!(backup, None);
+    assert_eq!(repo.head().unwrap().target(), Some(first));
+    assert_eq!(read(&repo, "a.md"), "a2\n");
+    assert!(
+        repo.statuses(None)
+            .unwrap()
+            .iter()
+            .any(|s| s.status().is_index_modified())
+    );
+
+    let entries = reflog(&repo, None).unwrap();
+    assert_eq!(entries[0].new, first);
+    assert_eq!(entries[0].old, second);
+
+    // hard: the uncommitted changes are backed up
+    reset_to(
+        &mut repo,
+        &second.to_string(),
+        ResetKind::Mixed,
+        &signature(),
+    )
+    .unwrap();
+    fs::write(repo.workdir().unwrap().join("a.md"), "local\n").unwrap();
+    fs::write(repo.workdir().unwrap().join("new.md"), "new\n").unwrap();
+
+    let backup = reset_to(&mut repo, &first.to_string(), ResetKind::Hard, &signature())
+        .unwrap()
+        .unwrap();
+    assert!(backup.starts_with(BACKUP_REFS));
+    assert_eq!(read(&repo, "a.md"), "a\n");
+    assert!(!repo.workdir().unwrap().join("new.md").exists());
+
+    let backup = repo
+        .find_reference(&backup)
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(backup.parent_id(0).unwrap(), second);
+    let tree = backup.tree().unwrap();
+    let blob = tree
+        .get_path(Path::new("a.md"))
+        .unwrap()
+        .to_object(&repo)
+        .unwrap();
+    assert_eq!(blob.as_blob().unwrap().content(), b"local\n");
+    drop((blob, tree, backup));
+
+    // not a stash entry
+    let mut stashes = 0;
+    repo.stash_foreach(|_, _, _| {
+        stashes += 1;
+        true
+    })
+    .unwrap();
+    assert_eq!(stashes, 0);
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Repository, Signature, Time};
+
+use crate::callback::ScanCB;
+use crate::libgit2::scan::{BATCH_SIZE, ScannedNote, scan_notes};
+
+#[derive(Default)]
+struct CollectCB {
+    notes: Vec<ScannedNote>,
+    folders: Vec<String>,
+    batches: usize,
+    max_batches: Option<usize>,
+}
+
+impl ScanCB for &mut CollectCB {
+    fn batch(&mut self, notes: Vec<ScannedNote>, folders: Vec<String>) -> bool {
+        assert!(notes.len() <= BATCH_SIZE && folders.len() <= BATCH_SIZE);
+        self.notes.extend(notes);
+        self.folders.extend(folders);
+        self.batches += 1;
+        self.max_batches.is_none_or(|max| self.batches < max)
+    }
+}
+
+fn write(root: &Path, filename: &str, content: &str) {
+    let path = root.join(filename);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_scan_notes() {
+    let path = "repo_test/scan_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+    let root = repo.workdir().unwrap().to_path_buf();
+
+    write(&root, ".gitignore", "build/\n*.log.md\n");
+    write(&root, "a.md", "a");
+    write(&root, "dir/b.txt", "b");
+    write(&root, "dir/image.png", "not a note");
+    write(&root, "big.md", &"x".repeat(100));
+    write(&root, "build/c.md", "ignored");
+    write(&root, "debug.log.md", "ignored");
+    write(&root, ".hidden/d.md", "hidden");
+
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.md")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::new("Moi", "test@example.com", &Time::new(100, 0)).unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "commit", &tree, &[])
+        .unwrap();
+
+    let mut cb = CollectCB::default();
+    scan_notes(&repo, &root, 50, &mut cb).unwrap();
+
+    assert_eq!(cb.folders, vec!["dir".to_string()]);
+
+    cb.notes.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(cb.notes.len(), 2);
+
+    assert_eq!(
+        cb.notes[0],
+        ScannedNote {
+            path: "a.md".into(),
+            size: 1,
+            content: "a".into(),
+            timestamp: 100_000,
+        }
+    );
+    // not committed: the modification time of the file
+    assert_eq!(cb.notes[1].path, "dir/b.txt");
+    assert_eq!(cb.notes[1].content, "b");
+    assert!(cb.notes[1].timestamp > 100_000);
+}
+
+#[test]
+fn test_scan_notes_stop() {
+    let path = "repo_test/scan_stop_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+    let root = repo.workdir().unwrap().to_path_buf();
+
+    for i in 0..BATCH_SIZE * 3 {
+        write(&root, &format!("{i}.md"), "note");
+    }
+
+    // no commit yet
+    let mut cb = CollectCB::default();
+    scan_notes(&repo, &root, u64::MAX, &mut cb).unwrap();
+    assert_eq!(cb.notes.len(), BATCH_SIZE * 3);
+
+    let mut cb = CollectCB {
+        max_batches: Some(1),
+        ..Default::default()
+    };
+    scan_notes(&repo, &root, u64::MAX, &mut cb).unwrap();
+    assert_eq!(cb.notes.len(), BATCH_SIZE);
+    assert_eq!(cb.batches, 1);
+}
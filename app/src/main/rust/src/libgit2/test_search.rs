@@ -0,0 +1,82 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::search::{clear, search, tokenize};
+use crate::libgit2::test_utils::{commit, commit_changes};
+
+fn paths(repo: &Repository, query: &str) -> Vec<String> {
+    search(repo, query, 10, 0)
+        .unwrap()
+        .into_iter()
+        .map(|r| r.path)
+        .collect()
+}
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(
+        tokenize("Hello, Wörld! l'été 2024 日本"),
+        vec!["hello", "wörld", "l", "été", "2024", "日", "本"]
+    );
+}
+
+#[test]
+fn test_search() {
+    let path = "repo_test/search_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let day = 86_400;
+
+    commit_changes(
+        &repo,
+        &[
+            (
+                "recipes.md",
+                "# Recipes\n\nchocolate cake, then a lemon cake",
+            ),
+            ("old.md", "a chocolate cake"),
+            ("ideas.txt", "cake chocolate"),
+            ("image.png", "chocolate"),
+        ],
+        &[],
+        "commit",
+        "Moi",
+        0,
+    );
+    commit(&repo, &[("new.md", "the chocolate cake")], 100 * day);
+
+    // the most recent note first, then by term frequency
+    assert_eq!(
+        paths(&repo, "cake"),
+        vec!["new.md", "recipes.md", "ideas.txt", "old.md"]
+    );
+    assert_eq!(
+        paths(&repo, "\"chocolate cake\""),
+        vec!["new.md", "old.md", "recipes.md"]
+    );
+    assert_eq!(paths(&repo, "lem* cake"), vec!["recipes.md"]);
+    assert_eq!(paths(&repo, "missing"), Vec::<String>::new());
+    assert_eq!(search(&repo, "cake", 2, 1).unwrap().len(), 2);
+
+    commit_changes(
+        &repo,
+        &[("dir/moved.md", "a chocolate pie")],
+        &["old.md", "ideas.txt"],
+        "commit",
+        "Moi",
+        101 * day,
+    );
+
+    let incremental = paths(&repo, "chocolate");
+
+    // rebuild from the index file
+    clear();
+    assert_eq!(paths(&repo, "chocolate"), incremental);
+    assert_eq!(incremental, vec!["dir/moved.md", "new.md", "recipes.md"]);
+
+    fs::remove_file(repo.path().join("gitnote/search")).unwrap();
+    clear();
+    assert_eq!(paths(&repo, "chocolate"), incremental);
+}
@@ -0,0 +1,60 @@
+use std::fs;
+
+use git2::Repository;
+use ssh_key::{PrivateKey, PublicKey, SshSig};
+
+use crate::key_gen::{KeyAlgorithm, gen_keys};
+use crate::libgit2::sign::{self, GIT_NAMESPACE};
+use crate::libgit2::test_utils::{commit_file, signature};
+
+#[test]
+fn test_signed_commits() {
+    let path = "repo_test/signed_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let keys = gen_keys(KeyAlgorithm::Ed25519, "GitNote", None).unwrap();
+    let key = PrivateKey::from_openssh(keys.private.as_str()).unwrap();
+
+    let first = commit_file(&repo, "file1.md", "hello", Some(&key));
+    let second = commit_file(&repo, "file1.md", "hello world", Some(&key));
+
+    // HEAD moved like with a regular commit
+    assert_eq!(repo.head().unwrap().target(), Some(second));
+    assert_eq!(
+        repo.find_commit(second).unwrap().parent_id(0).unwrap(),
+        first
+    );
+
+    let public_key = PublicKey::from_openssh(&keys.public).unwrap();
+
+    for oid in [first, second] {
+        let (signature, content) = repo.extract_signature(&oid, None).unwrap();
+        let signature = SshSig::from_pem(&*signature).unwrap();
+
+        public_key
+            .verify(GIT_NAMESPACE, &content, &signature)
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_signed_commit_on_a_moved_branch() {
+    let path = "repo_test/signed_moved_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let keys = gen_keys(KeyAlgorithm::Ed25519, "GitNote", None).unwrap();
+    let key = PrivateKey::from_openssh(keys.private.as_str()).unwrap();
+
+    let first = commit_file(&repo, "file1.md", "hello", Some(&key));
+    let second = commit_file(&repo, "file1.md", "hello world", None);
+
+    // the branch moved since `first` was read
+    let first = repo.find_commit(first).unwrap();
+    let tree = first.tree().unwrap();
+    let sig = signature();
+    assert!(sign::commit(&repo, &sig, &sig, "stale", &tree, &[&first], Some(&key)).is_err());
+
+    assert_eq!(repo.head().unwrap().target(), Some(second));
+}
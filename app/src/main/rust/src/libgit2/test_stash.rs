@@ -0,0 +1,75 @@
+use std::fs;
+
+use git2::Repository;
+
+use crate::libgit2::stash::{apply, drop, list, save};
+use crate::libgit2::test_utils::{author, commit, read};
+
+#[test]
+fn test_stash() {
+    let path = "repo_test/stash_repo";
+    let _ = fs::remove_dir_all(path);
+    let mut repo = Repository::init(path).unwrap();
+
+    commit(&repo, &[("a.md", "a\n")], 100);
+
+    assert_eq!(save(&mut repo, &author(), None, true).unwrap(), None);
+
+    fs::write(repo.workdir().unwrap().join("a.md"), "a2\n").unwrap();
+    fs::write(repo.workdir().unwrap().join("new.md"), "new\n").unwrap();
+
+    let oid = save(&mut repo, &author(), Some("edits"), true)
+        .unwrap()
+        .unwrap();
+    assert_eq!(read(&repo, "a.md"), "a\n");
+    assert!(!repo.workdir().unwrap().join("new.md").exists());
+
+    let stashes = list(&mut repo).unwrap();
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].index, 0);
+    assert_eq!(stashes[0].oid, oid);
+    assert!(stashes[0].message.contains("edits"));
+
+    // apply keeps the stash
+    assert!(apply(&mut repo, 0, false).unwrap().is_empty());
+    assert_eq!(read(&repo, "a.md"), "a2\n");
+    assert_eq!(read(&repo, "new.md"), "new\n");
+    assert_eq!(list(&mut repo).unwrap().len(), 1);
+
+    drop(&mut repo, 0).unwrap();
+    assert!(list(&mut repo).unwrap().is_empty());
+
+    // pop after a change of HEAD, like a pull
+    save(&mut repo, &author(), None, true).unwrap().unwrap();
+    commit(&repo, &[("b.md", "b\n")], 200);
+
+    assert!(apply(&mut repo, 0, true).unwrap().is_empty());
+    assert_eq!(read(&repo, "a.md"), "a2\n");
+    assert!(list(&mut repo).unwrap().is_empty());
+}
+
+#[test]
+fn test_stash_pop_conflict() {
+    let path = "repo_test/stash_conflict_repo";
+    let _ = fs::remove_dir_all(path);
+    let mut repo = Repository::init(path).unwrap();
+
+    commit(&repo, &[("a.md", "a\n")], 100);
+
+    fs::write(repo.workdir().unwrap().join("a.md"), "local\n").unwrap();
+    save(&mut repo, &author(), None, false).unwrap().unwrap();
+
+    commit(&repo, &[("a.md", "remote\n")], 200);
+
+    let conflicts = apply(&mut repo, 0, true).unwrap();
+
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, "a.md");
+    assert!(conflicts[0].ancestor.is_some());
+    assert!(conflicts[0].ours.is_some());
+    assert!(conflicts[0].theirs.is_some());
+
+    // the stash is kept to not lose the local changes
+    assert_eq!(list(&mut repo).unwrap().len(), 1);
+    assert!(read(&repo, "a.md").contains("<<<<<<<"));
+}
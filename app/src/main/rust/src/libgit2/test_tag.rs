@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::Path;
+
+use git2::Repository;
+use ssh_key::{PublicKey, SshSig};
+
+use crate::key_gen::{KeyAlgorithm, gen_keys};
+use crate::libgit2::sign::GIT_NAMESPACE;
+use crate::libgit2::tag::{checkout, create, delete, list};
+use crate::libgit2::test_utils::{author, commit};
+
+#[test]
+fn test_tags() {
+    let path = "repo_test/tag_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let first = commit(&repo, &[("a.md", "a\n"), ("dir/b.md", "b\n")], 100);
+    create(&repo, "light", "HEAD", None, &author(), false, None).unwrap();
+
+    let second = commit(&repo, &[("a.md", "a2\n")], 200);
+    create(
+        &repo,
+        "end-of-sprint",
+        "HEAD",
+        Some("End of sprint 12"),
+        &author(),
+        false,
+        None,
+    )
+    .unwrap();
+
+    // already exists
+    assert!(create(&repo, "light", "HEAD", None, &author(), false, None).is_err());
+    assert!(create(&repo, "bad..name", "HEAD", None, &author(), false, None).is_err());
+
+    let tags = list(&repo).unwrap();
+    assert_eq!(tags.len(), 2);
+
+    let annotated = &tags[0];
+    assert_eq!(annotated.name, "end-of-sprint");
+    assert_eq!(annotated.target, second);
+    assert!(annotated.annotated);
+    assert!(!annotated.signed);
+    assert_eq!(annotated.message, "End of sprint 12");
+    assert_eq!(annotated.tagger_name, "Moi");
+
+    let light = &tags[1];
+    assert_eq!(light.name, "light");
+    assert_eq!(light.target, first);
+    assert!(!light.annotated);
+    assert_eq!(light.time, 100_000);
+
+    // read-only view of the first snapshot
+    let dest = Path::new("repo_test/tag_view");
+    let _ = fs::remove_dir_all(dest);
+    checkout(&repo, "light", dest).unwrap();
+    assert_eq!(fs::read_to_string(dest.join("a.md")).unwrap(), "a\n");
+    assert!(
+        fs::metadata(dest.join("dir/b.md"))
+            .unwrap()
+            .permissions()
+            .readonly()
+    );
+
+    // replaced
+    checkout(&repo, "end-of-sprint", dest).unwrap();
+    assert_eq!(fs::read_to_string(dest.join("a.md")).unwrap(), "a2\n");
+
+    // only a previous checkout is replaced
+    let other = Path::new("repo_test/tag_other");
+    let _ = fs::remove_dir_all(other);
+    fs::create_dir_all(other).unwrap();
+    fs::write(other.join("mine.md"), "mine").unwrap();
+    assert!(checkout(&repo, "light", other).is_err());
+    assert_eq!(fs::read_to_string(other.join("mine.md")).unwrap(), "mine");
+
+    // never in the repo
+    let workdir = repo.workdir().unwrap();
+    for dest in [
+        workdir,
+        repo.path(),
+        &workdir.join("dir"),
+        Path::new("repo_test"),
+    ] {
+        assert!(checkout(&repo, "light", dest).is_err());
+    }
+
+    // the worktree and HEAD are untouched
+    assert_eq!(
+        fs::read_to_string(repo.workdir().unwrap().join("a.md")).unwrap(),
+        "a2\n"
+    );
+    assert_eq!(repo.head().unwrap().target(), Some(second));
+    assert!(repo.statuses(None).unwrap().is_empty());
+
+    delete(&repo, "light").unwrap();
+    assert_eq!(list(&repo).unwrap().len(), 1);
+}
+
+#[test]
+fn test_signed_tag() {
+    let path = "repo_test/signed_tag_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let head = commit(&repo, &[("a.md", "a\n")], 100);
+
+    let keys = gen_keys(KeyAlgorithm::Ed25519, "GitNote", None).unwrap();
+    let private = ssh_key::PrivateKey::from_openssh(keys.private.as_str()).unwrap();
+
+    let oid = create(
+        &repo,
+        "signed",
+        "HEAD",
+        Some("a snapshot"),
+        &author(),
+        false,
+        Some(&private),
+    )
+    .unwrap();
+
+    let tag = repo.find_tag(oid).unwrap();
+    assert_eq!(tag.name().unwrap(), "signed");
+    assert_eq!(tag.target_id(), head);
+
+    let tags = list(&repo).unwrap();
+    assert!(tags[0].signed);
+    assert_eq!(tags[0].message, "a snapshot");
+
+    // the signature covers the tag without it, like `git tag -v`
+    let odb = repo.odb().unwrap();
+    let raw = odb.read(oid).unwrap();
+    let raw = std::str::from_utf8(raw.data()).unwrap();
+    let (content, signature) = raw.split_at(raw.find("-----BEGIN SSH SIGNATURE-----").unwrap());
+
+    let public_key = PublicKey::from_openssh(&keys.public).unwrap();
+    public_key
+        .verify(
+            GIT_NAMESPACE,
+            content.as_bytes(),
+            &SshSig::from_pem(signature).unwrap(),
+        )
+        .unwrap();
+}
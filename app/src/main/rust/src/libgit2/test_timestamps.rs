@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Repository, Signature, Time};
+
+use crate::libgit2::test_utils::commit;
+use crate::libgit2::timestamps::timestamps;
+
+fn cache_path(repo: &Repository) -> std::path::PathBuf {
+    repo.path().join("gitnote/timestamps")
+}
+
+#[test]
+fn test_incremental_timestamps() {
+    let path = "repo_test/timestamps_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    commit(
+        &repo,
+        &[("a.md", "a"), ("dir/b.txt", "b"), ("c.bin", "c")],
+        100,
+    );
+    let second = commit(&repo, &[("a.md", "a2")], 200);
+
+    let full = timestamps(&repo).unwrap();
+    assert_eq!(full.get("a.md"), Some(&200_000));
+    assert_eq!(full.get("dir/b.txt"), Some(&100_000));
+    assert_eq!(full.get("c.bin"), None);
+
+    let cache = fs::read_to_string(cache_path(&repo)).unwrap();
+    assert!(cache.starts_with(&second.to_string()));
+
+    // only the new commit is walked
+    commit(&repo, &[("dir/b.txt", "b2"), ("d.md", "d")], 300);
+    let incremental = timestamps(&repo).unwrap();
+
+    fs::remove_file(cache_path(&repo)).unwrap();
+    let full = timestamps(&repo).unwrap();
+
+    assert_eq!(incremental, full);
+    assert_eq!(incremental.get("dir/b.txt"), Some(&300_000));
+    assert_eq!(incremental.get("d.md"), Some(&300_000));
+
+    // rewrite the history: the cached commit is not an ancestor anymore
+    let second = repo.find_commit(second).unwrap();
+    repo.reset(second.as_object(), git2::ResetType::Hard, None)
+        .unwrap();
+    commit(&repo, &[("e.md", "e")], 400);
+
+    let rewritten = timestamps(&repo).unwrap();
+    assert_eq!(rewritten.get("dir/b.txt"), Some(&100_000));
+    assert_eq!(rewritten.get("d.md"), None);
+    assert_eq!(rewritten.get("e.md"), Some(&400_000));
+}
+
+#[test]
+fn test_merge_timestamps() {
+    let path = "repo_test/timestamps_merge_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let base = commit(
+        &repo,
+        &[("a.md", "a"), ("b.md", "b"), ("deleted.md", "x")],
+        100,
+    );
+
+    // dev: modify b.md after master modified a.md
+    let base = repo.find_commit(base).unwrap();
+    repo.branch("dev", &base, false).unwrap();
+    repo.set_head("refs/heads/dev").unwrap();
+    let dev = commit(&repo, &[("b.md", "b2")], 300);
+
+    repo.set_head("refs/heads/master").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+    let master = commit(&repo, &[("a.md", "a2")], 200);
+
+    // the cache is at master, before the merge
+    assert_eq!(timestamps(&repo).unwrap().get("b.md"), Some(&100_000));
+
+    let mut index = repo
+        .merge_commits(
+            &repo.find_commit(master).unwrap(),
+            &repo.find_commit(dev).unwrap(),
+            None,
+        )
+        .unwrap();
+    index.remove_path(Path::new("deleted.md")).unwrap();
+    let tree = repo.find_tree(index.write_tree_to(&repo).unwrap()).unwrap();
+    let sig = Signature::new("Moi", "test@example.com", &Time::new(400, 0)).unwrap();
+    repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        "merge",
+        &tree,
+        &[
+            &repo.find_commit(master).unwrap(),
+            &repo.find_commit(dev).unwrap(),
+        ],
+    )
+    .unwrap();
+
+    let incremental = timestamps(&repo).unwrap();
+
+    fs::remove_file(cache_path(&repo)).unwrap();
+    let full = timestamps(&repo).unwrap();
+    assert_eq!(incremental, full);
+
+    // same as `git log -1 --format=%ct -- <path>`
+    assert_eq!(full.get("a.md"), Some(&200_000));
+    assert_eq!(full.get("b.md"), Some(&300_000));
+    // deleted by the merge
+    assert_eq!(full.get("deleted.md"), None);
+    assert_eq!(full.len(), 2);
+}
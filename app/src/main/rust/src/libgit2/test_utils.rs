@@ -0,0 +1,97 @@
+//! Fixtures shared by the tests of the libgit2 module.
+
+use std::{fs, path::Path};
+
+use git2::{Oid, Repository, Signature, Time};
+use ssh_key::PrivateKey;
+
+use crate::GitAuthor;
+use crate::libgit2::sign;
+
+pub fn signature() -> Signature<'static> {
+    Signature::now("Moi", "test@example.com").unwrap()
+}
+
+/// Signature of `name`, with a `name@example.com` email, at `time` seconds.
+pub fn signature_at(name: &str, time: i64) -> Signature<'static> {
+    Signature::new(name, &format!("{name}@example.com"), &Time::new(time, 0)).unwrap()
+}
+
+pub fn author() -> GitAuthor {
+    GitAuthor::from(signature())
+}
+
+/// Write `filename` in the worktree, with its folders.
+pub fn write(repo: &Repository, filename: &str, content: &str) {
+    let path = repo.workdir().unwrap().join(filename);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+pub fn read(repo: &Repository, filename: &str) -> String {
+    fs::read_to_string(repo.workdir().unwrap().join(filename)).unwrap()
+}
+
+/// Write `filename` and add it to the index.
+pub fn add_file(repo: &Repository, filename: &str, content: &str) {
+    write(repo, filename, content);
+
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(filename)).unwrap();
+    index.write().unwrap();
+}
+
+/// Commit the index on HEAD as `sig`, signed with `key` when given.
+pub fn commit_index(
+    repo: &Repository,
+    message: &str,
+    sig: &Signature,
+    key: Option<&PrivateKey>,
+) -> Oid {
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+
+    sign::commit(repo, sig, sig, message, &tree, &parents, key).unwrap()
+}
+
+/// Write `files`, remove `removed`, and commit them as `author` at `time` seconds.
+pub fn commit_changes(
+    repo: &Repository,
+    files: &[(&str, &str)],
+    removed: &[&str],
+    message: &str,
+    author: &str,
+    time: i64,
+) -> Oid {
+    for (filename, content) in files {
+        add_file(repo, filename, content);
+    }
+
+    let mut index = repo.index().unwrap();
+    for filename in removed {
+        fs::remove_file(repo.workdir().unwrap().join(filename)).unwrap();
+        index.remove_path(Path::new(filename)).unwrap();
+    }
+    index.write().unwrap();
+
+    commit_index(repo, message, &signature_at(author, time), None)
+}
+
+/// Write `files` and commit them at `time` seconds.
+pub fn commit(repo: &Repository, files: &[(&str, &str)], time: i64) -> Oid {
+    commit_changes(repo, files, &[], "commit", "Moi", time)
+}
+
+/// Commit `filename`, signed with `key` when given.
+pub fn commit_file(
+    repo: &Repository,
+    filename: &str,
+    content: &str,
+    key: Option<&PrivateKey>,
+) -> Oid {
+    add_file(repo, filename, content);
+    commit_index(repo, "commit", &signature(), key)
+}
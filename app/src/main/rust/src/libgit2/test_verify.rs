@@ -0,0 +1,71 @@
+use std::fs;
+
+use git2::Repository;
+use ssh_key::PrivateKey;
+
+use crate::key_gen::{KeyAlgorithm, SshKeys, gen_keys};
+use crate::libgit2::test_utils::{commit_file, signature};
+use crate::libgit2::verify::{AllowedSigners, SignatureStatus, check_range, verify_commit};
+
+#[test]
+fn test_verify() {
+    let path = "repo_test/verify_repo";
+    let _ = fs::remove_dir_all(path);
+    let repo = Repository::init(path).unwrap();
+
+    let allowed = gen_keys(KeyAlgorithm::Ed25519, "GitNote", None).unwrap();
+    let unknown = gen_keys(KeyAlgorithm::Ed25519, "GitNote", None).unwrap();
+
+    let signers = AllowedSigners::parse(&format!(
+        "# team keys\n\
+         test@example.com namespaces=\"git,file\" {}\n\
+         other@example.com namespaces=\"file\" {}\n",
+        allowed.public, unknown.public
+    ));
+
+    let private = |keys: &SshKeys| PrivateKey::from_openssh(keys.private.as_str()).unwrap();
+    let unsigned = commit_file(&repo, "file1.md", "1", None);
+    let good = commit_file(&repo, "file2.md", "2", Some(&private(&allowed)));
+    let unknown = commit_file(&repo, "file3.md", "3", Some(&private(&unknown)));
+
+    assert_eq!(
+        verify_commit(&repo, unsigned, &signers).unwrap(),
+        SignatureStatus::Unsigned
+    );
+    assert_eq!(
+        verify_commit(&repo, good, &signers).unwrap(),
+        SignatureStatus::Good {
+            principal: "test@example.com".into()
+        }
+    );
+    // the key is only allowed for the "file" namespace
+    assert_eq!(
+        verify_commit(&repo, unknown, &signers).unwrap(),
+        SignatureStatus::UnknownKey
+    );
+
+    // reuse the signature of another commit
+    let (good_signature, _) = repo.extract_signature(&good, None).unwrap();
+    let sig = signature();
+    let tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let buffer = repo
+        .commit_create_buffer(&sig, &sig, "forged", &tree, &[&head])
+        .unwrap();
+    let bad = repo
+        .commit_signed(
+            buffer.as_str().unwrap(),
+            good_signature.as_str().unwrap(),
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        verify_commit(&repo, bad, &signers).unwrap(),
+        SignatureStatus::Bad
+    );
+
+    assert!(check_range(&repo, Some(unsigned), good, &signers).is_ok());
+    assert!(check_range(&repo, Some(unsigned), unknown, &signers).is_err());
+    assert!(check_range(&repo, None, good, &signers).is_err());
+}
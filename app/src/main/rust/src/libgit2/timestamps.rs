@@ -0,0 +1,350 @@
+//! Last modification time of each note, cached in `.git/gitnote/timestamps`,
+//! so only the commits added since the last call need to be walked.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use git2::{Oid, Repository};
+
+use crate::mime_types::{self, is_note_name};
+
+use super::{ignore, rename::renames};
+
+const CACHE_DIR: &str = "gitnote";
+const CACHE_FILE: &str = "timestamps";
+
+/// Path -> last modification time, in milliseconds.
+pub type Timestamps = HashMap<String, i64>;
+
+struct Cache {
+    head: Oid,
+    /// [`mime_types::extensions_fingerprint`], the notes depend on it.
+    extensions: u64,
+    timestamps: Timestamps,
+}
+
+fn cache_path(repo: &Repository) -> PathBuf {
+    repo.path().join(CACHE_DIR).join(CACHE_FILE)
+}
+
+/// Format: the commit oid and the fingerprint of the extensions on the first line,
+/// then one `timestamp\tpath` per line.
+fn read_cache(path: &Path) -> io::Result<Cache> {
+    let mut lines = BufReader::new(fs::File::open(path)?).lines();
+
+    let (head, extensions) = lines
+        .next()
+        .transpose()?
+        .and_then(|line| {
+            // the fingerprint is missing from the old caches
+            let (head, extensions) = line.split_once(' ').unwrap_or((&line, "0"));
+            Some((Oid::from_str(head).ok()?, extensions.parse().ok()?))
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid cache header"))?;
+
+    let mut timestamps = Timestamps::new();
+
+    for line in lines {
+        let line = line?;
+
+        let Some((timestamp, path)) = line
+            .split_once('\t')
+            .and_then(|(t, p)| Some((t.parse::<i64>().ok()?, p)))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid cache line: {line}"),
+            ));
+        };
+
+        timestamps.insert(path.to_string(), timestamp);
+    }
+
+    Ok(Cache {
+        head,
+        extensions,
+        timestamps,
+    })
+}
+
+fn write_cache(path: &Path, head: Oid, extensions: u64, timestamps: &Timestamps) -> io::Result<()> {
+    fs::create_dir_all(path.parent().expect("cache dir"))?;
+
+    // write in a temporary file first, so a crash can't leave a truncated cache
+    let tmp_path = path.with_extension("tmp");
+    let mut file = BufWriter::new(fs::File::create(&tmp_path)?);
+
+    writeln!(file, "{head} {extensions}")?;
+
+    for (path, timestamp) in timestamps {
+        if path.contains('\n') {
+            continue;
+        }
+        writeln!(file, "{timestamp}\t{path}")?;
+    }
+
+    file.into_inner()?.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+pub(super) fn is_note(path: &Path) -> bool {
+    is_note_name(
+        &path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default(),
+    )
+}
+
+pub(super) fn is_attachment(path: &Path) -> bool {
+    mime_types::is_attachment(
+        path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default(),
+    )
+}
+
+/// Note paths of the tree of `commit`.
+pub(super) fn note_paths(repo: &Repository, commit: Oid) -> Result<HashSet<String>, git2::Error> {
+    blob_paths(repo, commit, is_note)
+}
+
+/// Notes and attachments: the app also shows when an image last changed.
+fn timestamped_paths(repo: &Repository, commit: Oid) -> Result<HashSet<String>, git2::Error> {
+    blob_paths(repo, commit, |path| is_note(path) || is_attachment(path))
+}
+
+/// Paths of the tree of `commit` accepted by `filter`.
+fn blob_paths(
+    repo: &Repository,
+    commit: Oid,
+    filter: fn(&Path) -> bool,
+) -> Result<HashSet<String>, git2::Error> {
+    let tree = repo.find_commit(commit)?.tree()?;
+    let mut paths = HashSet::new();
+
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob)
+            && let Ok(name) = entry.name()
+        {
+            let path = format!("{dir}{name}");
+            if filter(Path::new(&path)) {
+                paths.insert(path);
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+
+    Ok(paths)
+}
+
+/// Paths changed between `parent` (or an empty tree) and `commit`.
+pub(super) fn changed_paths(
+    repo: &Repository,
+    parent: Option<&git2::Tree>,
+    commit: &git2::Tree,
+) -> Result<HashSet<String>, git2::Error> {
+    let diff = repo.diff_tree_to_tree(parent, Some(commit), None)?;
+
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .filter_map(|path| path.to_str().map(str::to_string))
+        .collect())
+}
+
+/// A note of HEAD, followed in the history.
+#[derive(Clone)]
+pub(super) struct Tracked {
+    /// Path at HEAD.
+    pub path: String,
+    /// Path at the current commit, which differs after a rename.
+    pub name: String,
+}
+
+impl Tracked {
+    pub fn new(path: String) -> Self {
+        Self {
+            name: path.clone(),
+            path,
+        }
+    }
+}
+
+struct Walk {
+    timestamps: Timestamps,
+    /// Notes which reached a hidden commit without being resolved.
+    pending: HashMap<Oid, Vec<Tracked>>,
+}
+
+/// Find the last commit modifying each of `paths`, like `git log -1 -- <path>`.
+///
+/// Each path follows the history from `head`: at a commit which is TREESAME
+/// to one of its parents for this path, it continues with this parent only,
+/// otherwise the commit is the one that last modified it.
+/// A note moved without modification continues with its previous path.
+/// The walk stops as soon as every path is resolved.
+fn walk(
+    repo: &Repository,
+    head: Oid,
+    hide: Option<Oid>,
+    paths: HashSet<String>,
+) -> Result<Walk, git2::Error> {
+    let mut timestamps = Timestamps::new();
+
+    // notes still looking for their last modification, by the commit they reached
+    let mut live: HashMap<Oid, Vec<Tracked>> = HashMap::new();
+    let mut remaining = paths.len();
+    live.insert(head, paths.into_iter().map(Tracked::new).collect());
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head)?;
+    if let Some(hide) = hide {
+        revwalk.hide(hide)?;
+    }
+    // children must be visited before their parents
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+    for oid in revwalk {
+        if remaining == 0 {
+            break;
+        }
+
+        let oid = oid?;
+        let Some(tracked) = live.remove(&oid) else {
+            continue;
+        };
+
+        let commit = repo.find_commit(oid)?;
+        let tree = commit.tree()?;
+        let time = commit.time().seconds() * 1000;
+
+        let parents = commit
+            .parents()
+            .map(|parent| {
+                let parent_tree = parent.tree()?;
+                let changed = changed_paths(repo, Some(&parent_tree), &tree)?;
+                Ok((parent.id(), parent_tree, changed))
+            })
+            .collect::<Result<Vec<_>, git2::Error>>()?;
+
+        // computed at most once by commit, only when a note was added
+        let mut commit_renames = None;
+
+        for note in tracked {
+            if let Some((parent, _, _)) = parents
+                .iter()
+                .find(|(_, _, changed)| !changed.contains(&note.name))
+            {
+                live.entry(*parent).or_default().push(note);
+                continue;
+            }
+
+            if let Some((parent, parent_tree, _)) = parents.first()
+                && parent_tree.get_path(Path::new(&note.name)).is_err()
+            {
+                if commit_renames.is_none() {
+                    commit_renames = Some(renames(repo, parent_tree, &tree)?);
+                }
+
+                if let Some(rename) = commit_renames.as_ref().and_then(|r| r.get(&note.name))
+                    && rename.exact
+                {
+                    live.entry(*parent).or_default().push(Tracked {
+                        path: note.path,
+                        name: rename.from.clone(),
+                    });
+                    continue;
+                }
+            }
+
+            timestamps.insert(note.path, time);
+            remaining -= 1;
+        }
+    }
+
+    Ok(Walk {
+        timestamps,
+        pending: live,
+    })
+}
+
+/// Timestamps at HEAD, updated incrementally from the cache when possible.
+pub fn timestamps(repo: &Repository) -> Result<Timestamps, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let cache_path = cache_path(repo);
+    let extensions = mime_types::extensions_fingerprint();
+
+    let cache = match read_cache(&cache_path) {
+        // other files are notes: start over
+        Ok(cache) if cache.extensions != extensions => None,
+        Ok(cache) => Some(cache),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("timestamps cache: {e}");
+            None
+        }
+    };
+
+    let timestamps = match cache {
+        Some(cache) if cache.head == head => {
+            debug!("timestamps cache is up to date");
+            return Ok(without_ignored(repo, cache.timestamps));
+        }
+        // the cached commit is still in the history: only walk the new commits
+        Some(cache) if repo.graph_descendant_of(head, cache.head)? => {
+            debug!("timestamps cache: incremental walk from {}", cache.head);
+            let paths = timestamped_paths(repo, head)?;
+            let mut incremental = walk(repo, head, Some(cache.head), paths)?;
+
+            let mut unresolved = HashSet::new();
+
+            for (oid, notes) in incremental.pending {
+                for note in notes {
+                    match cache.timestamps.get(&note.name) {
+                        Some(timestamp) if oid == cache.head => {
+                            incremental.timestamps.insert(note.path, *timestamp);
+                        }
+                        // reached an older commit through a merged branch
+                        _ => {
+                            unresolved.insert(note.path);
+                        }
+                    }
+                }
+            }
+
+            if !unresolved.is_empty() {
+                debug!(
+                    "timestamps cache: {} paths need a full walk",
+                    unresolved.len()
+                );
+                let full = walk(repo, head, None, unresolved)?;
+                incremental.timestamps.extend(full.timestamps);
+            }
+
+            incremental.timestamps
+        }
+        // no cache, or the history was rewritten
+        _ => {
+            debug!("timestamps cache: full walk");
+            let paths = timestamped_paths(repo, head)?;
+            walk(repo, head, None, paths)?.timestamps
+        }
+    };
+
+    if let Err(e) = write_cache(&cache_path, head, extensions, &timestamps) {
+        warn!("can't write the timestamps cache: {e}");
+    }
+
+    Ok(without_ignored(repo, timestamps))
+}
+
+/// The cache keeps the ignored paths, so it stays valid when the ignore rules change.
+fn without_ignored(repo: &Repository, mut timestamps: Timestamps) -> Timestamps {
+    timestamps.retain(|path, _| !ignore::is_ignored(repo, Path::new(path), false));
+    timestamps
+}
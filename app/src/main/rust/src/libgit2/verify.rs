@@ -0,0 +1,294 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use git2::{Oid, Repository};
+use ssh_key::{PublicKey, SshSig, public::KeyData};
+
+use crate::Error;
+
+use super::sign::GIT_NAMESPACE;
+
+struct VerifyConfig {
+    allowed_signers: Option<PathBuf>,
+    /// Refuse to merge commits that are not signed by an allowed signer.
+    strict: bool,
+}
+
+static VERIFY_CONFIG: LazyLock<Mutex<VerifyConfig>> = LazyLock::new(|| {
+    Mutex::new(VerifyConfig {
+        allowed_signers: None,
+        strict: false,
+    })
+});
+
+pub fn set_verification(allowed_signers: Option<PathBuf>, strict: bool) {
+    let mut config = VERIFY_CONFIG.lock().expect("verify config lock");
+    config.allowed_signers = allowed_signers;
+    config.strict = strict;
+}
+
+// important: must match the order of SignatureStatus in kotlin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Valid signature from a key of the allowed signers file.
+    Good {
+        principal: String,
+    },
+    /// The signature can't be checked, because its key is not allowed
+    /// (or it is not an ssh signature).
+    UnknownKey,
+    /// The signature doesn't match the commit.
+    Bad,
+    Unsigned,
+}
+
+impl SignatureStatus {
+    pub fn code(&self) -> i32 {
+        match self {
+            SignatureStatus::Good { .. } => 0,
+            SignatureStatus::UnknownKey => 1,
+            SignatureStatus::Bad => 2,
+            SignatureStatus::Unsigned => 3,
+        }
+    }
+}
+
+impl Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureStatus::Good { principal } => write!(f, "good signature from {principal}"),
+            SignatureStatus::UnknownKey => write!(f, "signed with an unknown key"),
+            SignatureStatus::Bad => write!(f, "bad signature"),
+            SignatureStatus::Unsigned => write!(f, "unsigned"),
+        }
+    }
+}
+
+struct AllowedSigner {
+    principals: String,
+    namespaces: Option<Vec<String>>,
+    key: KeyData,
+}
+
+/// Entries of an allowed signers file, see the `ALLOWED SIGNERS` section of ssh-keygen(1).
+///
+/// `valid-after`, `valid-before` and `cert-authority` are not supported,
+/// entries using `cert-authority` are ignored.
+pub struct AllowedSigners {
+    signers: Vec<AllowedSigner>,
+}
+
+impl AllowedSigners {
+    pub fn parse(content: &str) -> Self {
+        let signers = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let signer = Self::parse_line(line);
+                if signer.is_none() {
+                    warn!("invalid allowed signers line: {line}");
+                }
+                signer
+            })
+            .collect();
+
+        Self { signers }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(e, "read allowed signers"))?;
+
+        Ok(Self::parse(&content))
+    }
+
+    fn parse_line(line: &str) -> Option<AllowedSigner> {
+        let (principals, rest) = line.split_once(char::is_whitespace)?;
+        let rest = rest.trim_start();
+
+        // options are optional, so first try to read the key directly
+        let (options, key) = match PublicKey::from_openssh(rest) {
+            Ok(key) => ("", key),
+            Err(_) => {
+                let (options, key) = split_options(rest)?;
+                (options, PublicKey::from_openssh(key.trim_start()).ok()?)
+            }
+        };
+
+        let mut namespaces = None;
+
+        for option in options.split(',').filter(|o| !o.is_empty()) {
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+
+            match name.to_ascii_lowercase().as_str() {
+                "namespaces" => {
+                    namespaces = Some(
+                        value
+                            .trim_matches('"')
+                            .split(',')
+                            .map(|n| n.trim().to_string())
+                            .collect(),
+                    )
+                }
+                "cert-authority" => return None,
+                _ => {}
+            }
+        }
+
+        Some(AllowedSigner {
+            principals: principals.to_string(),
+            namespaces,
+            key: key.key_data().clone(),
+        })
+    }
+
+    fn find(&self, key: &KeyData) -> Option<&AllowedSigner> {
+        self.signers.iter().find(|signer| {
+            &signer.key == key
+                && signer
+                    .namespaces
+                    .as_ref()
+                    .is_none_or(|n| n.iter().any(|n| n == GIT_NAMESPACE))
+        })
+    }
+}
+
+/// Split the options, which can contain quoted whitespace, from the key.
+fn split_options(s: &str) -> Option<(&str, &str)> {
+    let mut in_quotes = false;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => return Some((&s[..i], &s[i..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn verify_commit(
+    repo: &Repository,
+    oid: Oid,
+    signers: &AllowedSigners,
+) -> Result<SignatureStatus, git2::Error> {
+    let (signature, content) = match repo.extract_signature(&oid, None) {
+        Ok(res) => res,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {
+            return Ok(SignatureStatus::Unsigned);
+        }
+        Err(e) => return Err(e),
+    };
+
+    if !signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+        // gpg or x509 signature
+        return Ok(SignatureStatus::UnknownKey);
+    }
+
+    let Ok(signature) = SshSig::from_pem(&*signature) else {
+        return Ok(SignatureStatus::Bad);
+    };
+
+    let Some(signer) = signers.find(signature.public_key()) else {
+        return Ok(SignatureStatus::UnknownKey);
+    };
+
+    let key = PublicKey::from(signer.key.clone());
+
+    match key.verify(GIT_NAMESPACE, &content, &signature) {
+        Ok(()) => Ok(SignatureStatus::Good {
+            principal: signer.principals.clone(),
+        }),
+        Err(_) => Ok(SignatureStatus::Bad),
+    }
+}
+
+/// Verify the last `limit` commits reachable from HEAD, newest first.
+pub fn verify_commits(
+    repo: &Repository,
+    signers: &AllowedSigners,
+    limit: usize,
+) -> Result<Vec<(Oid, SignatureStatus)>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+    revwalk
+        .take(limit)
+        .map(|oid| {
+            let oid = oid?;
+            Ok((oid, verify_commit(repo, oid, signers)?))
+        })
+        .collect()
+}
+
+/// Check that every commit reachable from `to`, but not from `from`, is signed by an allowed signer.
+pub fn check_range(
+    repo: &Repository,
+    from: Option<Oid>,
+    to: Oid,
+    signers: &AllowedSigners,
+) -> Result<(), Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(to)?;
+    if let Some(from) = from {
+        revwalk.hide(from)?;
+    }
+
+    for oid in revwalk {
+        let oid = oid?;
+
+        let status = verify_commit(repo, oid, signers)?;
+
+        if !matches!(status, SignatureStatus::Good { .. }) {
+            return Err(Error::git2(
+                git2::Error::from_str(&format!("commit {oid} is not verified: {status}")),
+                "verify",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// In strict mode, refuse fetched commits that are not signed by an allowed signer.
+pub fn check_fetched(repo: &Repository, fetched: Oid) -> Result<(), Error> {
+    let strict = VERIFY_CONFIG.lock().expect("verify config lock").strict;
+
+    if !strict {
+        return Ok(());
+    }
+
+    let Some(path) = allowed_signers_path(repo) else {
+        return Err(Error::git2(
+            git2::Error::from_str("strict mode without allowed signers file"),
+            "verify",
+        ));
+    };
+
+    let signers = AllowedSigners::from_file(&path)?;
+
+    let head = repo.head().ok().and_then(|h| h.target());
+
+    check_range(repo, head, fetched, &signers)
+}
+
+/// Allowed signers file set by the app, or `gpg.ssh.allowedSignersFile` from the git config.
+pub fn allowed_signers_path(repo: &Repository) -> Option<PathBuf> {
+    let path = VERIFY_CONFIG
+        .lock()
+        .expect("verify config lock")
+        .allowed_signers
+        .clone();
+
+    path.or_else(|| {
+        repo.config()
+            .ok()?
+            .get_path("gpg.ssh.allowedSignersFile")
+            .ok()
+    })
+}
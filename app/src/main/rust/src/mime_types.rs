@@ -1,31 +1,477 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::Path,
+    sync::{LazyLock, RwLock, RwLockReadGuard},
+};
+
 use include_lines::include_lines;
+use regex::Regex;
+use sha2::{Digest, Sha256};
 
 // important: 0 is reserved for None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExtensionType {
     Text = 1,
     Markdown = 2,
+    /// Not a note, but displayed by the notes referencing it.
+    Image = 3,
+    /// Any other binary file referenced by the notes.
+    Attachment = 4,
+    /// Source code, with a language id.
+    Code = 5,
+    /// Unknown content which can't be edited as text.
+    Binary = 6,
 }
 
-pub fn extension_type(extension: &str) -> Option<ExtensionType> {
-    let text_extensions = include_lines!("./supported_extensions/text.txt");
-    let markdown_extensions = include_lines!("./supported_extensions/markdown.txt");
+impl ExtensionType {
+    pub fn is_note(self) -> bool {
+        matches!(self, Self::Text | Self::Markdown | Self::Code)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "markdown" => Some(Self::Markdown),
+            "image" => Some(Self::Image),
+            "attachment" => Some(Self::Attachment),
+            "code" => Some(Self::Code),
+            "binary" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileType {
+    pub kind: ExtensionType,
+    /// Like `rust` or `python`, for [`ExtensionType::Code`].
+    pub language: Option<String>,
+}
 
-    if text_extensions.binary_search(&extension).is_ok() {
-        return Some(ExtensionType::Text);
+impl FileType {
+    fn new(kind: ExtensionType) -> Self {
+        Self {
+            kind,
+            language: None,
+        }
     }
-    if markdown_extensions.binary_search(&extension).is_ok() {
-        return Some(ExtensionType::Markdown);
+
+    fn code(language: &str) -> Self {
+        Self {
+            kind: ExtensionType::Code,
+            language: Some(language.to_string()),
+        }
     }
-    None
 }
 
+/// Like git, a NUL byte in the first 8000 bytes means binary content.
+pub const SNIFF_LEN: usize = 8000;
+
+/// Files recognized by their name, compared without case.
+const WELL_KNOWN_NAMES: &[(&str, ExtensionType, Option<&str>)] = &[
+    ("AUTHORS", ExtensionType::Text, None),
+    ("CHANGELOG", ExtensionType::Text, None),
+    ("CONTRIBUTING", ExtensionType::Text, None),
+    ("COPYING", ExtensionType::Text, None),
+    ("LICENSE", ExtensionType::Text, None),
+    ("NOTICE", ExtensionType::Text, None),
+    ("README", ExtensionType::Text, None),
+    ("TODO", ExtensionType::Text, None),
+    ("Dockerfile", ExtensionType::Code, Some("dockerfile")),
+    ("GNUmakefile", ExtensionType::Code, Some("make")),
+    ("Justfile", ExtensionType::Code, Some("just")),
+    ("Makefile", ExtensionType::Code, Some("make")),
+    (".editorconfig", ExtensionType::Code, Some("ini")),
+    (".gitattributes", ExtensionType::Code, Some("gitattributes")),
+    (".gitignore", ExtensionType::Code, Some("gitignore")),
+    (".gitmodules", ExtensionType::Code, Some("ini")),
+    (".lfsconfig", ExtensionType::Code, Some("ini")),
+];
+
+/// Names used by the shebangs and the modelines, and their language id.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("bash", "shell"),
+    ("bun", "javascript"),
+    ("dash", "shell"),
+    ("deno", "javascript"),
+    ("js", "javascript"),
+    ("ksh", "shell"),
+    ("kt", "kotlin"),
+    ("node", "javascript"),
+    ("nu", "nushell"),
+    ("pwsh", "powershell"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("sh", "shell"),
+    ("zsh", "shell"),
+];
+
+/// Extensions configured at runtime, over the built-in lists.
+///
+/// `None` removes a built-in extension.
+pub type Extensions = HashMap<String, Option<FileType>>;
+
+#[derive(Default)]
+struct Registry {
+    /// From the configuration file of the repo.
+    repo: Extensions,
+    /// From the settings of the app, over the ones of the repo.
+    app: Extensions,
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(Default::default);
+
+fn registry() -> RwLockReadGuard<'static, Registry> {
+    REGISTRY.read().expect("registry lock")
+}
+
+pub fn set_repo_extensions(extensions: Extensions) {
+    REGISTRY.write().expect("registry lock").repo = extensions;
+}
+
+pub fn set_app_extensions(extensions: Extensions) {
+    REGISTRY.write().expect("registry lock").app = extensions;
+}
+
+/// Changes with the configured extensions, for the caches depending on them.
+///
+/// 0 without configured extension.
+pub fn extensions_fingerprint() -> u64 {
+    registry().fingerprint()
+}
+
+pub fn extension_type(extension: &str) -> Option<ExtensionType> {
+    registry().extension_type(extension)
+}
+
+/// The `[extensions]` table of a configuration file, like:
+///
+/// ```toml
+/// [extensions]
+/// org = "markdown"
+/// py = { type = "code", language = "python" }
+/// # not a note anymore
+/// xml = "none"
+/// ```
+pub fn parse_extensions(config: &toml::Table) -> Result<Extensions, String> {
+    let Some(table) = config.get("extensions") else {
+        return Ok(Extensions::new());
+    };
+    let table = table
+        .as_table()
+        .ok_or("extensions: expected a table".to_string())?;
+
+    let mut extensions = Extensions::new();
+
+    for (extension, value) in table {
+        let (kind, language) = match value {
+            toml::Value::String(kind) => (kind.as_str(), None),
+            toml::Value::Table(table) => (
+                table
+                    .get("type")
+                    .and_then(toml::Value::as_str)
+                    .ok_or(format!("extensions.{extension}: missing type"))?,
+                table.get("language").and_then(toml::Value::as_str),
+            ),
+            other => {
+                return Err(format!(
+                    "extensions.{extension}: expected a string or a table, found a {}",
+                    other.type_str()
+                ));
+            }
+        };
+
+        let file_type = match kind {
+            "none" => None,
+            kind => Some(FileType {
+                kind: ExtensionType::from_name(kind)
+                    .ok_or(format!("extensions.{extension}: unknown type {kind:?}"))?,
+                language: language.map(str::to_string),
+            }),
+        };
+
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        extensions.insert(extension, file_type);
+    }
+
+    Ok(extensions)
+}
+
+/// The extension is the one of a note.
 pub fn is_extension_supported(extension: &str) -> bool {
-    extension_type(extension).is_some()
+    extension_type(extension).is_some_and(ExtensionType::is_note)
+}
+
+pub fn is_attachment(extension: &str) -> bool {
+    matches!(
+        extension_type(extension),
+        Some(ExtensionType::Image | ExtensionType::Attachment)
+    )
+}
+
+/// The file name is the one of a note, without looking at its content.
+///
+/// The dot files are the configuration of the repo, not notes.
+pub fn is_note_name(name: &str) -> bool {
+    !name.starts_with('.') && classify(name, &[]).is_some_and(|t| t.kind.is_note())
+}
+
+fn is_binary(head: &[u8]) -> bool {
+    head.iter().take(SNIFF_LEN).any(|b| *b == 0)
+}
+
+/// Valid utf-8, except a character cut at the end of `head`.
+fn is_utf8(head: &[u8]) -> bool {
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+fn language_id(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+
+    match LANGUAGE_ALIASES.binary_search_by(|(alias, _)| alias.cmp(&name.as_str())) {
+        Ok(i) => LANGUAGE_ALIASES[i].1.to_string(),
+        Err(_) => name,
+    }
+}
+
+/// Type named by a modeline or a shebang.
+fn from_language(name: &str) -> FileType {
+    match language_id(name).as_str() {
+        "markdown" | "md" => FileType::new(ExtensionType::Markdown),
+        "text" | "txt" => FileType::new(ExtensionType::Text),
+        language => FileType::code(language),
+    }
+}
+
+/// `#!/usr/bin/env python3` or `#!/bin/sh`.
+fn shebang(first_line: &str) -> Option<FileType> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+
+    // python3.12 -> python
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if interpreter.is_empty() {
+        return None;
+    }
+
+    Some(from_language(interpreter))
+}
+
+static VIM_MODELINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|\s)(?:vi|vim|ex):.*?\b(?:ft|filetype|syntax)=([\w+-]+)")
+        .expect("vim modeline")
+});
+static EMACS_MODELINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"-\*-\s*(?:.*?\bmode:\s*)?([\w+-]+)\s*(?:;[^*]*)?-\*-").expect("emacs modeline")
+});
+
+/// Vim modelines in the first or last lines, emacs ones in the first two lines.
+fn modeline(content: &str) -> Option<FileType> {
+    let lines = content.lines().collect::<Vec<_>>();
+
+    let emacs = lines.iter().take(2);
+    if let Some(c) = emacs
+        .filter_map(|line| EMACS_MODELINE.captures(line))
+        .next()
+    {
+        return Some(from_language(&c[1]));
+    }
+
+    let vim = lines.iter().take(5).chain(lines.iter().rev().take(5));
+    vim.filter_map(|line| VIM_MODELINE.captures(line))
+        .next()
+        .map(|c| from_language(&c[1]))
+}
+
+impl Registry {
+    /// Configured type of `extension`, which must be lowercase.
+    fn configured(&self, extension: &str) -> Option<Option<FileType>> {
+        self.app
+            .get(extension)
+            .or_else(|| self.repo.get(extension))
+            .cloned()
+    }
+
+    fn fingerprint(&self) -> u64 {
+        if self.repo.is_empty() && self.app.is_empty() {
+            return 0;
+        }
+
+        // saved with the caches, so stable across the builds of the app
+        let mut hasher = Sha256::new();
+        for extensions in [&self.repo, &self.app] {
+            let mut entries = extensions.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(extension, _)| *extension);
+
+            for (extension, file_type) in entries {
+                hasher.update(extension.as_bytes());
+                hasher.update([0]);
+                match file_type {
+                    Some(file_type) => {
+                        hasher.update([file_type.kind as u8]);
+                        hasher.update(file_type.language.as_deref().unwrap_or("").as_bytes());
+                    }
+                    None => hasher.update([0]),
+                }
+                hasher.update([0]);
+            }
+            // separates the repo from the app
+            hasher.update([0xff]);
+        }
+
+        let digest = hasher.finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"))
+    }
+
+    fn extension_type(&self, extension: &str) -> Option<ExtensionType> {
+        let extension = extension.to_ascii_lowercase();
+        let extension = extension.as_str();
+
+        if let Some(file_type) = self.configured(extension) {
+            return file_type.map(|t| t.kind);
+        }
+
+        let text_extensions = include_lines!("./supported_extensions/text.txt");
+        let markdown_extensions = include_lines!("./supported_extensions/markdown.txt");
+        let image_extensions = include_lines!("./supported_extensions/image.txt");
+        let attachment_extensions = include_lines!("./supported_extensions/attachment.txt");
+
+        if text_extensions.binary_search(&extension).is_ok() {
+            return Some(ExtensionType::Text);
+        }
+        if markdown_extensions.binary_search(&extension).is_ok() {
+            return Some(ExtensionType::Markdown);
+        }
+        if self.code_language(extension).is_some() {
+            return Some(ExtensionType::Code);
+        }
+        if image_extensions.binary_search(&extension).is_ok() {
+            return Some(ExtensionType::Image);
+        }
+        if attachment_extensions.binary_search(&extension).is_ok() {
+            return Some(ExtensionType::Attachment);
+        }
+        None
+    }
+
+    /// Language id of a code extension, from `code.txt` (`extension language` lines).
+    fn code_language(&self, extension: &str) -> Option<String> {
+        if let Some(file_type) = self.configured(extension) {
+            return file_type.and_then(|t| t.language);
+        }
+
+        let code_extensions = include_lines!("./supported_extensions/code.txt");
+
+        fn key(line: &str) -> &str {
+            line.split_once(' ').map_or(line, |(ext, _)| ext)
+        }
+
+        code_extensions
+            .binary_search_by(|line| key(line).cmp(extension))
+            .ok()
+            .and_then(|i| code_extensions[i].split_once(' '))
+            .map(|(_, language)| language.to_string())
+    }
+
+    /// Type of the file `name`, using `head`, the first bytes of its content, when not empty.
+    ///
+    /// In order: the image and attachment extensions, binary content,
+    /// a modeline, the extension, the well-known names, a shebang,
+    /// and finally any text content for the names without extension.
+    fn classify(&self, name: &str, head: &[u8]) -> Option<FileType> {
+        let path = Path::new(name);
+        let extension = path.extension().and_then(|e| e.to_str());
+        let kind = extension.and_then(|e| self.extension_type(e));
+
+        if let Some(kind @ (ExtensionType::Image | ExtensionType::Attachment)) = kind {
+            return Some(FileType::new(kind));
+        }
+
+        if is_binary(head) {
+            return Some(FileType::new(ExtensionType::Binary));
+        }
+
+        let content = if is_utf8(head) {
+            String::from_utf8_lossy(head)
+        } else {
+            Default::default()
+        };
+
+        if let Some(file_type) = modeline(&content) {
+            return Some(file_type);
+        }
+
+        match kind {
+            Some(ExtensionType::Code) => {
+                let language = extension.and_then(|e| self.code_language(&e.to_ascii_lowercase()));
+                return Some(FileType {
+                    kind: ExtensionType::Code,
+                    language,
+                });
+            }
+            Some(kind) => return Some(FileType::new(kind)),
+            None => {}
+        }
+
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or(name);
+        // README.old or LICENSE-MIT are still well-known
+        let stem = file_name
+            .split(['.', '-', '_'])
+            .find(|s| !s.is_empty())
+            .unwrap_or(file_name);
+
+        if let Some((_, kind, language)) = WELL_KNOWN_NAMES.iter().find(|(known, _, _)| {
+            known.eq_ignore_ascii_case(file_name)
+                || (!known.starts_with('.') && known.eq_ignore_ascii_case(stem))
+        }) {
+            return Some(FileType {
+                kind: *kind,
+                language: language.map(str::to_string),
+            });
+        }
+
+        if let Some(file_type) = content.lines().next().and_then(shebang) {
+            return Some(file_type);
+        }
+
+        if extension.is_none() && !head.is_empty() && is_utf8(head) {
+            return Some(FileType::new(ExtensionType::Text));
+        }
+
+        None
+    }
+}
+
+/// Type of the file `name`, using `head`, the first bytes of its content, when not empty.
+///
+/// See [`Registry::classify`].
+pub fn classify(name: &str, head: &[u8]) -> Option<FileType> {
+    registry().classify(name, head)
+}
+
+/// First bytes of the file at `path`, for [`classify`].
+pub fn sniff(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    fs::File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+
+    Ok(head)
 }
 
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, fs, path::Path};
+    use std::{collections::HashMap, fs, path::Path, str::FromStr};
 
     use super::*;
 
@@ -34,6 +480,106 @@ mod test {
         assert!(is_extension_supported("md"));
         assert!(is_extension_supported("txt"));
         assert!(!is_extension_supported("bin"));
+
+        assert!(!is_extension_supported("png"));
+        assert_eq!(extension_type("png"), Some(ExtensionType::Image));
+        assert_eq!(extension_type("pdf"), Some(ExtensionType::Attachment));
+        assert!(is_attachment("jpg"));
+        assert!(!is_attachment("md"));
+
+        assert!(is_extension_supported("MD"));
+        assert_eq!(extension_type("Rs"), Some(ExtensionType::Code));
+    }
+
+    #[test]
+    fn test_classify() {
+        let kind = |name: &str, head: &str| classify(name, head.as_bytes()).map(|t| t.kind);
+        let language =
+            |name: &str, head: &str| classify(name, head.as_bytes()).and_then(|t| t.language);
+
+        assert_eq!(kind("note.MD", ""), Some(ExtensionType::Markdown));
+        assert_eq!(language("main.rs", "fn main() {}"), Some("rust".into()));
+        assert_eq!(kind("image.PNG", "\0"), Some(ExtensionType::Image));
+        assert_eq!(kind("note.txt", "a\0b"), Some(ExtensionType::Binary));
+        assert_eq!(kind("data.bin", ""), None);
+
+        // well-known names
+        assert_eq!(kind("README", ""), Some(ExtensionType::Text));
+        assert_eq!(kind("license-MIT", ""), Some(ExtensionType::Text));
+        assert_eq!(language("Makefile", ""), Some("make".into()));
+        assert_eq!(language(".gitignore", ""), Some("gitignore".into()));
+
+        // shebangs
+        assert_eq!(
+            language("deploy", "#!/usr/bin/env python3\nprint()"),
+            Some("python".into())
+        );
+        assert_eq!(language("run", "#!/bin/bash\n"), Some("shell".into()));
+
+        // modelines
+        assert_eq!(
+            language("notes.txt", "some code\n// vim: set ft=rust:\n"),
+            Some("rust".into())
+        );
+        assert_eq!(
+            kind("journal", "-*- mode: markdown -*-\n# Title"),
+            Some(ExtensionType::Markdown)
+        );
+
+        // any text without extension
+        assert_eq!(kind("ideas", "buy milk"), Some(ExtensionType::Text));
+        assert_eq!(kind("ideas", ""), None);
+
+        assert!(is_note_name("README"));
+        assert!(is_note_name("todo.Md"));
+        assert!(!is_note_name(".gitignore"));
+        assert!(!is_note_name("image.png"));
+    }
+
+    #[test]
+    fn test_registry() {
+        let config = toml::Table::from_str(
+            r#"
+[extensions]
+ORG = "markdown"
+tex = { type = "code", language = "latex" }
+"#,
+        )
+        .unwrap();
+        let extensions = parse_extensions(&config).unwrap();
+        assert_eq!(extensions.len(), 2);
+
+        let mut registry = Registry::default();
+        assert_eq!(registry.extension_type("org"), None);
+        assert_eq!(registry.fingerprint(), 0);
+
+        registry.repo = extensions;
+        assert_eq!(
+            registry.extension_type("org"),
+            Some(ExtensionType::Markdown)
+        );
+        assert_eq!(
+            registry.classify("paper.tex", b"").and_then(|t| t.language),
+            Some("latex".into())
+        );
+
+        // the app is over the repo
+        let config = toml::Table::from_str(
+            "[extensions]
+org = \"none\"",
+        )
+        .unwrap();
+        registry.app = parse_extensions(&config).unwrap();
+        assert_eq!(registry.classify("notes.org", b""), None);
+        // saved in the repo, so it must not change between the builds
+        assert_eq!(registry.fingerprint(), 8663420248606583389);
+
+        let config = toml::Table::from_str(
+            "[extensions]
+org = \"video\"",
+        )
+        .unwrap();
+        assert!(parse_extensions(&config).is_err());
     }
 
     #[test]
@@ -68,8 +614,8 @@ mod test {
             })
             .collect::<Vec<_>>();
 
-        extensions.sort_by(|a, b| b.1.cmp(&a.1));
-        extensions.sort_by(|a, b| b.2.cmp(&a.2));
+        extensions.sort_by_key(|b| std::cmp::Reverse(b.1));
+        extensions.sort_by_key(|b| std::cmp::Reverse(b.2));
 
         println!("extension     supported     count");
         // Print results